@@ -1,3 +1,4 @@
+use cap_media::encoders::{AudioCodec, EncoderSettings, StreamOutput};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use specta::Type;
@@ -13,8 +14,23 @@ pub struct GeneralSettingsStore {
     pub hide_dock_icon: bool,
     #[serde(default)]
     pub auto_create_shareable_link: bool,
+    #[serde(default)]
+    pub encoder_settings: EncoderSettings,
+    #[serde(default)]
+    pub audio_codec: AudioCodec,
+    /// Streams the display live while recording, e.g. to a local RTMP server.
+    #[serde(default)]
+    pub live_stream: Option<StreamOutput>,
+    /// Counts down this many seconds before a recording starts, e.g. 3, 5 or 10.
+    #[serde(default)]
+    pub recording_countdown_secs: Option<u32>,
+    /// How many seconds a saved replay goes back. Uses `DEFAULT_REPLAY_DURATION_SECS` if unset.
+    #[serde(default)]
+    pub replay_duration_secs: Option<u32>,
 }
 
+pub const DEFAULT_REPLAY_DURATION_SECS: u32 = 30;
+
 impl GeneralSettingsStore {
     pub fn get(app: &AppHandle<Wry>) -> Result<Option<Self>, String> {
         println!("Attempting to get GeneralSettingsStore");
@@ -1,5 +1,6 @@
 use crate::{
-    RequestNewScreenshot, RequestRestartRecording, RequestStartRecording, RequestStopRecording,
+    RequestCancelRecording, RequestNewScreenshot, RequestRestartRecording, RequestSaveReplay,
+    RequestStartRecording, RequestStopRecording,
 };
 use global_hotkey::HotKeyState;
 use serde::{Deserialize, Serialize};
@@ -49,6 +50,8 @@ pub enum HotkeyAction {
     StopRecording,
     RestartRecording,
     TakeScreenshot,
+    SaveReplay,
+    CancelRecording,
 }
 
 #[derive(Serialize, Deserialize, Type, Default)]
@@ -99,6 +102,12 @@ pub fn init(app: &AppHandle) {
                             HotkeyAction::TakeScreenshot => {
                                 let _ = RequestNewScreenshot.emit(app);
                             }
+                            HotkeyAction::SaveReplay => {
+                                let _ = RequestSaveReplay.emit(app);
+                            }
+                            HotkeyAction::CancelRecording => {
+                                let _ = RequestCancelRecording.emit(app);
+                            }
                         }
                     }
                 }
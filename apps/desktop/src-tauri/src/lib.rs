@@ -10,6 +10,7 @@ mod macos;
 mod notifications;
 mod permissions;
 mod recording;
+mod segment_upload;
 mod tray;
 mod upload;
 mod web_api;
@@ -20,24 +21,35 @@ use auth::AuthStore;
 use cap_editor::{AudioData, EditorState, ProjectRecordings};
 use cap_editor::{EditorInstance, FRAMES_WS_PATH};
 use cap_media::{
-    feeds::{CameraFeed, CameraFrameSender},
+    description::PipelineDescription,
+    encoders::{AudioCodec, SegmentFormat, SegmentedOutput, StreamStatus},
+    feeds::{CameraFeed, CameraFeedEvent, CameraFormatPreference, CameraFrameSender},
+    filters::RedactionRegion,
+    pipeline::{control::PipelineStatus, limits::RecordingLimits},
     platform::Bounds,
-    sources::{AudioInputSource, ScreenCaptureTarget},
+    sources::{
+        AudioInputSource, AudioInputStatus, AudioLevelMonitor, AudioLevels, ScreenCaptureTarget,
+    },
 };
 use cap_project::{
     ProjectConfiguration, RecordingMeta, SharingMeta, TimelineConfiguration, TimelineSegment,
+    ValidationReport,
 };
 use cap_rendering::ProjectUniforms;
 use cap_utils::create_named_pipe;
 // use display::{list_capture_windows, Bounds, CaptureTarget, FPS};
-use general_settings::GeneralSettingsStore;
+use general_settings::{GeneralSettingsStore, DEFAULT_REPLAY_DURATION_SECS};
 use image::{ImageBuffer, Rgba};
 use mp4::Mp4Reader;
 use num_traits::ToBytes;
 use png::{ColorType, Encoder};
-use recording::{list_cameras, list_capture_windows, InProgressRecording, FPS};
+use recording::{
+    list_camera_formats, list_cameras, list_capture_windows, InProgressRecording, RecordingMode,
+    ReplayBuffer, FPS,
+};
 use scap::capturer::Capturer;
 use scap::frame::Frame;
+use segment_upload::SegmentUploader;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use specta::Type;
@@ -46,7 +58,11 @@ use std::io::BufWriter;
 use std::io::{BufReader, Write};
 use std::time::{SystemTime, UNIX_EPOCH};
 use std::{
-    collections::HashMap, marker::PhantomData, path::PathBuf, process::Command, sync::Arc,
+    collections::HashMap,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Arc,
     time::Duration,
 };
 use tauri::{AppHandle, Manager, Runtime, State, WindowEvent};
@@ -65,9 +81,24 @@ use windows::CapWindow;
 #[derive(specta::Type, Serialize, Deserialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct RecordingOptions {
+    /// Whether the capture target is recorded, or just the camera or microphone.
+    #[serde(default)]
+    mode: RecordingMode,
     capture_target: ScreenCaptureTarget,
+    /// Recorded alongside the capture target, e.g. to compare two apps side by side.
+    #[serde(default)]
+    additional_capture_targets: Vec<ScreenCaptureTarget>,
     camera_label: Option<String>,
+    /// Which of the camera's formats to capture with.
+    #[serde(default)]
+    camera_format: CameraFormatPreference,
     audio_input_name: Option<String>,
+    /// Parts of the screen or window to pixelate or cover before they're recorded.
+    #[serde(default)]
+    redactions: Vec<RedactionRegion>,
+    /// When to stop recording without being asked to.
+    #[serde(default)]
+    limits: RecordingLimits,
 }
 
 impl RecordingOptions {
@@ -90,9 +121,18 @@ pub struct App {
     #[serde(skip)]
     camera_feed: Option<CameraFeed>,
     #[serde(skip)]
+    audio_level_monitor: Option<AudioLevelMonitor>,
+    #[serde(skip)]
     handle: AppHandle,
     #[serde(skip)]
     current_recording: Option<InProgressRecording>,
+    /// Built and waiting for its countdown to finish.
+    #[serde(skip)]
+    pending_recording: Option<InProgressRecording>,
+    #[serde(skip)]
+    scheduled_recording: Option<tauri::async_runtime::JoinHandle<()>>,
+    #[serde(skip)]
+    replay_buffer: Option<ReplayBuffer>,
     #[serde(skip)]
     pre_created_video: Option<PreCreatedVideo>,
 }
@@ -120,6 +160,19 @@ struct PreCreatedVideo {
 }
 
 impl App {
+    /// Whether a recording is counting down or scheduled to start.
+    fn has_pending_recording(&self) -> bool {
+        self.pending_recording.is_some() || self.scheduled_recording.is_some()
+    }
+
+    /// Whether the recording set up in `recording_dir` is still counting down, rather than
+    /// cancelled or replaced by another one.
+    fn is_pending_recording(&self, recording_dir: &Path) -> bool {
+        self.pending_recording
+            .as_ref()
+            .is_some_and(|recording| recording.recording_dir == recording_dir)
+    }
+
     pub fn set_current_recording(&mut self, new_value: InProgressRecording) {
         let option = Some(new_value);
         let json = JsonValue::new(&option);
@@ -130,7 +183,7 @@ impl App {
 
         CurrentRecordingChanged(json).emit(&self.handle).ok();
 
-        if let ScreenCaptureTarget::Window { .. } = &current_recording.display_source {
+        if let Some(ScreenCaptureTarget::Window { .. }) = &current_recording.display_source {
             let _ = CapWindow::WindowCaptureOccluder.show(&self.handle);
         } else {
             self.close_occluder_window();
@@ -169,16 +222,38 @@ impl App {
         match &new_options.camera_label {
             Some(camera_label) => {
                 if self.camera_feed.is_none() {
-                    self.camera_feed = CameraFeed::init(&camera_label, self.camera_tx.clone())
-                        .await
-                        .map_err(|error| eprintln!("{error}"))
-                        .ok();
+                    self.camera_feed = CameraFeed::init(
+                        &camera_label,
+                        new_options.camera_format,
+                        self.camera_tx.clone(),
+                    )
+                    .await
+                    .map_err(|error| eprintln!("{error}"))
+                    .ok();
+
+                    if let Some(camera_feed) = &self.camera_feed {
+                        let events = camera_feed.events();
+                        let app = self.handle.clone();
+                        tokio::spawn(async move {
+                            while let Ok(event) = events.recv_async().await {
+                                println!("Camera feed: {event:?}");
+                                CameraFeedChanged { event }.emit(&app).ok();
+                            }
+                        });
+                    }
                 } else if let Some(camera_feed) = self.camera_feed.as_mut() {
-                    camera_feed
-                        .switch_cameras(&camera_label)
+                    let switched = camera_feed
+                        .switch_cameras(&camera_label, new_options.camera_format)
                         .await
                         .map_err(|error| eprintln!("{error}"))
-                        .ok();
+                        .is_ok();
+
+                    if switched && self.start_recording_options.camera_label() != Some(camera_label)
+                    {
+                        if let Some(recording) = &mut self.current_recording {
+                            recording.add_camera_switch(camera_label.clone());
+                        }
+                    }
                 }
             }
             None => {
@@ -186,6 +261,34 @@ impl App {
             }
         }
 
+        match &new_options.audio_input_name {
+            Some(audio_input_name)
+                if self
+                    .audio_level_monitor
+                    .as_ref()
+                    .map(AudioLevelMonitor::device_name)
+                    != Some(audio_input_name.as_str()) =>
+            {
+                self.audio_level_monitor = AudioLevelMonitor::start(audio_input_name)
+                    .map_err(|error| eprintln!("{error}"))
+                    .ok();
+
+                if let Some(monitor) = &self.audio_level_monitor {
+                    let levels = monitor.subscribe();
+                    let app = self.handle.clone();
+                    tokio::spawn(async move {
+                        while let Ok(levels) = levels.recv_async().await {
+                            AudioInputLevelChanged { levels }.emit(&app).ok();
+                        }
+                    });
+                }
+            }
+            Some(_) => {}
+            None => {
+                self.audio_level_monitor = None;
+            }
+        }
+
         self.start_recording_options = new_options;
 
         RecordingOptionsChanged.emit(&self.handle).ok();
@@ -217,6 +320,33 @@ pub struct RecordingStopped {
     path: PathBuf,
 }
 
+#[derive(Deserialize, specta::Type, Serialize, tauri_specta::Event, Debug, Clone)]
+pub struct LiveStreamStatusChanged {
+    status: StreamStatus,
+}
+
+#[derive(Deserialize, specta::Type, Serialize, tauri_specta::Event, Debug, Clone)]
+pub struct AudioInputStatusChanged {
+    status: AudioInputStatus,
+}
+
+#[derive(Deserialize, specta::Type, Serialize, tauri_specta::Event, Debug, Clone)]
+pub struct AudioInputLevelChanged {
+    levels: AudioLevels,
+}
+
+/// The microphone stayed quiet for the first seconds of a recording, so it's likely muted or
+/// the wrong one.
+#[derive(Deserialize, specta::Type, Serialize, tauri_specta::Event, Debug, Clone)]
+pub struct AudioInputSilent {
+    device: String,
+}
+
+#[derive(Deserialize, specta::Type, Serialize, tauri_specta::Event, Debug, Clone)]
+pub struct CameraFeedChanged {
+    event: CameraFeedEvent,
+}
+
 #[derive(Deserialize, specta::Type, Serialize, tauri_specta::Event, Debug, Clone)]
 pub struct RequestStartRecording;
 
@@ -226,6 +356,76 @@ pub struct RequestRestartRecording;
 #[derive(Deserialize, specta::Type, Serialize, tauri_specta::Event, Debug, Clone)]
 pub struct RequestNewScreenshot;
 
+#[derive(Deserialize, specta::Type, Serialize, tauri_specta::Event, Debug, Clone)]
+pub struct RequestSaveReplay;
+
+#[derive(Deserialize, specta::Type, Serialize, tauri_specta::Event, Debug, Clone)]
+pub struct RequestCancelRecording;
+
+/// Sent every second while counting down to the start of a recording.
+#[derive(Deserialize, specta::Type, Serialize, tauri_specta::Event, Debug, Clone)]
+pub struct RecordingCountdown {
+    secs_left: u32,
+}
+
+#[derive(Deserialize, specta::Type, Serialize, tauri_specta::Event, Debug, Clone)]
+pub struct RecordingCountdownCancelled;
+
+#[derive(Deserialize, specta::Type, Serialize, tauri_specta::Event, Debug, Clone)]
+pub struct RecordingScheduleChanged {
+    trigger: Option<RecordingTrigger>,
+}
+
+#[derive(specta::Type, Serialize, Deserialize, Clone, Debug)]
+#[serde(
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase",
+    tag = "kind"
+)]
+pub enum RecordingTrigger {
+    /// A point in time, in milliseconds since the Unix epoch.
+    At { unix_time_ms: f64 },
+    /// A window whose title contains `name` being open.
+    WindowAppears { name: String },
+}
+
+impl RecordingTrigger {
+    const WINDOW_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+    async fn wait(&self) {
+        match self {
+            Self::At { unix_time_ms } => {
+                let now_ms = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap()
+                    .as_secs_f64()
+                    * 1000.0;
+                sleep(Duration::from_secs_f64(
+                    ((unix_time_ms - now_ms) / 1000.0).max(0.0),
+                ))
+                .await;
+            }
+            Self::WindowAppears { name } => loop {
+                let windows = task::spawn_blocking(list_capture_windows)
+                    .await
+                    .unwrap_or_default();
+                if windows
+                    .iter()
+                    .any(|window| window.name.contains(name.as_str()))
+                {
+                    return;
+                }
+                sleep(Self::WINDOW_POLL_INTERVAL).await;
+            },
+        }
+    }
+}
+
+#[derive(Deserialize, specta::Type, Serialize, tauri_specta::Event, Debug, Clone)]
+pub struct ReplayBufferChanged {
+    running: bool,
+}
+
 #[derive(Deserialize, specta::Type, Serialize, tauri_specta::Event, Debug, Clone)]
 pub struct RequestStopRecording;
 
@@ -287,14 +487,126 @@ async fn get_current_recording(
     Ok(JsonValue::new(&state.current_recording))
 }
 
+/// How long the microphone may stay quiet at the start of a recording before the user is warned.
+const SILENCE_WARNING_AFTER: Duration = Duration::from_secs(3);
+/// Peaks below this, in dBFS, count as silence. Room noise on a working mic is well above it.
+const SILENCE_THRESHOLD: f32 = -60.0;
+
+fn warn_if_silent(app: AppHandle, monitor: &AudioLevelMonitor) {
+    let levels = monitor.subscribe();
+    let device = monitor.device_name().to_string();
+
+    tokio::spawn(async move {
+        let deadline = tokio::time::Instant::now() + SILENCE_WARNING_AFTER;
+        loop {
+            match tokio::time::timeout_at(deadline, levels.recv_async()).await {
+                Ok(Ok(levels)) if levels.peak > SILENCE_THRESHOLD => return,
+                Ok(Ok(_)) => {}
+                // The monitor went away, e.g. the input was changed.
+                Ok(Err(_)) => return,
+                Err(_) => break,
+            }
+        }
+
+        println!("Audio input {device} has been silent since recording started");
+        AudioInputSilent { device }.emit(&app).ok();
+    });
+}
+
 #[derive(Serialize, Type, tauri_specta::Event, Clone)]
 pub struct CurrentRecordingChanged(JsonValue<Option<InProgressRecording>>);
 
+/// The user's recording profile if they have one, or the built-in one otherwise.
+fn recording_profile(
+    app: &AppHandle,
+    audio_codec: AudioCodec,
+) -> Result<PipelineDescription, String> {
+    let profile_path = app
+        .path()
+        .app_data_dir()
+        .unwrap()
+        .join("recording-profile.json");
+
+    if profile_path.exists() {
+        PipelineDescription::load(&profile_path).map_err(|error| {
+            eprintln!("{error}");
+            "Failed to load recording profile".to_string()
+        })
+    } else {
+        Ok(PipelineDescription::with_audio_codec(audio_codec))
+    }
+}
+
 #[tauri::command]
 #[specta::specta]
 async fn start_recording(app: AppHandle, state: MutableState<'_, App>) -> Result<(), String> {
-    let mut state = state.write().await;
+    let countdown_secs = GeneralSettingsStore::get(&app)?
+        .and_then(|settings| settings.recording_countdown_secs)
+        .unwrap_or(0);
+
+    let recording_dir = {
+        let mut state = state.write().await;
+        if state.current_recording.is_some() || state.pending_recording.is_some() {
+            return Err("Recording already in progress".to_string());
+        }
+
+        let recording = prepare_recording(&app, &mut state).await?;
+        let recording_dir = recording.recording_dir.clone();
+        state.pending_recording = Some(recording);
+        recording_dir
+    };
+
+    // The pipeline is already built, so capture starts as soon as the countdown ends.
+    for secs_left in (1..=countdown_secs).rev() {
+        if !state.read().await.is_pending_recording(&recording_dir) {
+            // Cancelled during the countdown.
+            return Ok(());
+        }
+        RecordingCountdown { secs_left }.emit(&app).ok();
+        sleep(Duration::from_secs(1)).await;
+    }
+
+    {
+        let mut state = state.write().await;
+        if !state.is_pending_recording(&recording_dir) {
+            return Ok(());
+        }
+        let mut recording = state.pending_recording.take().unwrap();
+
+        if let Err(error) = recording.begin().await {
+            eprintln!("{error}");
+            recording.stop_and_discard().await;
+            return Err("Failed to start recording".into());
+        }
+
+        if let Some(monitor) = &state.audio_level_monitor {
+            warn_if_silent(app.clone(), monitor);
+        }
+
+        state.set_current_recording(recording);
+    }
+
+    if let Some(window) = CapWindow::Main.get(&app) {
+        window.minimize().ok();
+    }
 
+    if let Some(window) = (CapWindow::InProgressRecording { position: None }).get(&app) {
+        window.eval("window.location.reload()").unwrap();
+        window.show().unwrap();
+    }
+
+    AppSounds::StartRecording.play();
+
+    RecordingStarted.emit(&app).ok();
+
+    Ok(())
+}
+
+/// Sets up a recording with the current options, ready to be started.
+async fn prepare_recording(
+    app: &AppHandle,
+    state: &mut App,
+) -> Result<InProgressRecording, String> {
     let id = uuid::Uuid::new_v4().to_string();
 
     let recording_dir = app
@@ -305,16 +617,26 @@ async fn start_recording(app: AppHandle, state: MutableState<'_, App>) -> Result
         .join(format!("{id}.cap"));
 
     // Check if auto_create_shareable_link is true and user is upgraded
-    let general_settings = GeneralSettingsStore::get(&app)?;
+    let general_settings = GeneralSettingsStore::get(app)?;
     let auto_create_shareable_link = general_settings
+        .as_ref()
         .map(|settings| settings.auto_create_shareable_link)
         .unwrap_or(false);
+    let (encoder_settings, audio_codec, live_stream) = general_settings
+        .map(|settings| {
+            (
+                settings.encoder_settings,
+                settings.audio_codec,
+                settings.live_stream,
+            )
+        })
+        .unwrap_or_default();
 
     if auto_create_shareable_link {
-        if let Ok(Some(auth)) = AuthStore::get(&app) {
+        if let Ok(Some(auth)) = AuthStore::get(app) {
             if auth.is_upgraded() {
                 // Pre-create the video and get the shareable link
-                let s3_config = get_s3_config(&app, false).await?;
+                let s3_config = get_s3_config(app, false).await?;
                 let link = web_api::make_url(format!("/s/{}", s3_config.id()));
 
                 state.pre_created_video = Some(PreCreatedVideo {
@@ -328,32 +650,159 @@ async fn start_recording(app: AppHandle, state: MutableState<'_, App>) -> Result
         }
     }
 
-    match recording::start(
+    let description = recording_profile(app, audio_codec)?;
+
+    // Recordings take over the camera feed, which only feeds one pipeline at a time.
+    if let Some(replay_buffer) = state.replay_buffer.take() {
+        replay_buffer.stop().await;
+        ReplayBufferChanged { running: false }.emit(app).ok();
+    }
+
+    // Upload the display as it's recorded, so the shareable link works as soon as it stops
+    let display_segments = state.pre_created_video.as_ref().map(|_| SegmentedOutput {
+        dir: recording_dir.join("content").join("segments"),
+        format: SegmentFormat::MpegTs,
+        segment_duration_secs: encoder_settings.keyframe_interval_secs.max(1),
+        max_segments: None,
+    });
+
+    match recording::prepare(
         recording_dir,
         &state.start_recording_options,
         state.camera_feed.as_ref(),
+        &description,
+        &encoder_settings,
+        display_segments.as_ref(),
+        live_stream.as_ref(),
     )
     .await
     {
-        Ok(recording) => state.set_current_recording(recording),
+        Ok(mut recording) => {
+            let status_updates = recording.pipeline.status_updates();
+            let status_app = app.clone();
+            tokio::spawn(async move {
+                while let Ok((task, status)) = status_updates.recv_async().await {
+                    match status {
+                        PipelineStatus::Stream(status) => {
+                            println!("Live stream from {task}: {status:?}");
+                            LiveStreamStatusChanged { status }.emit(&status_app).ok();
+                        }
+                        PipelineStatus::AudioInput(status) => {
+                            println!("Audio input of {task}: {status:?}");
+                            AudioInputStatusChanged { status }.emit(&status_app).ok();
+                        }
+                        PipelineStatus::EndOfInput => {}
+                        PipelineStatus::InputFailed(error) => {
+                            eprintln!("Input of {task} failed: {error}");
+                        }
+                        PipelineStatus::LimitReached(limit) => {
+                            let app = status_app.clone();
+                            tokio::spawn(async move {
+                                if let Err(e) = stop_recording(app.clone(), app.state()).await {
+                                    eprintln!("Failed to stop recording: {}", e);
+                                    return;
+                                }
+                                notifications::send_notification(
+                                    &app,
+                                    notifications::NotificationType::RecordingLimitReached(limit),
+                                );
+                            });
+                        }
+                    }
+                }
+            });
+
+            if let (Some(segments), Some(video)) =
+                (&recording.display_segments, &state.pre_created_video)
+            {
+                recording.segment_uploader = Some(SegmentUploader::spawn(
+                    app.clone(),
+                    segments,
+                    video.config.clone(),
+                ));
+            }
+
+            Ok(recording)
+        }
         Err(error) => {
             eprintln!("{error}");
-            return Err("Failed to set up recording".into());
+            Err("Failed to set up recording".into())
         }
-    };
+    }
+}
 
-    if let Some(window) = CapWindow::Main.get(&app) {
-        window.minimize().ok();
+/// Cancels a recording that's counting down or scheduled to start.
+#[tauri::command]
+#[specta::specta]
+async fn cancel_pending_recording(
+    app: AppHandle,
+    state: MutableState<'_, App>,
+) -> Result<(), String> {
+    let mut state = state.write().await;
+    let scheduled = state.scheduled_recording.take();
+    let pending = state.pending_recording.take();
+
+    if scheduled.is_none() && pending.is_none() {
+        return Err("No recording is waiting to start".to_string());
     }
 
-    if let Some(window) = (CapWindow::InProgressRecording { position: None }).get(&app) {
-        window.eval("window.location.reload()").unwrap();
-        window.show().unwrap();
+    if let Some(scheduled) = scheduled {
+        scheduled.abort();
+        RecordingScheduleChanged { trigger: None }.emit(&app).ok();
     }
 
-    AppSounds::StartRecording.play();
+    if let Some(mut recording) = pending {
+        if let Some(uploader) = recording.segment_uploader.take() {
+            uploader.cancel();
+        }
+        recording.stop_and_discard().await;
+        state.pre_created_video = None;
+        RecordingCountdownCancelled.emit(&app).ok();
+    }
 
-    RecordingStarted.emit(&app).ok();
+    Ok(())
+}
+
+/// Starts a recording with the current options once `trigger` fires, replacing any recording
+/// that was already scheduled.
+#[tauri::command]
+#[specta::specta]
+async fn schedule_recording(
+    app: AppHandle,
+    state: MutableState<'_, App>,
+    trigger: RecordingTrigger,
+) -> Result<(), String> {
+    let mut state = state.write().await;
+    if state.current_recording.is_some() || state.pending_recording.is_some() {
+        return Err("Recording already in progress".to_string());
+    }
+
+    if let Some(previous) = state.scheduled_recording.take() {
+        previous.abort();
+    }
+
+    state.scheduled_recording = Some(tauri::async_runtime::spawn({
+        let app = app.clone();
+        let trigger = trigger.clone();
+        async move {
+            trigger.wait().await;
+
+            // No longer cancellable as a schedule, only as a countdown.
+            let state = app.state::<Arc<RwLock<App>>>();
+            state.write().await.scheduled_recording = None;
+            RecordingScheduleChanged { trigger: None }.emit(&app).ok();
+
+            if let Err(e) = start_recording(app.clone(), app.state()).await {
+                eprintln!("Failed to start scheduled recording: {}", e);
+            }
+        }
+    }));
+
+    RecordingScheduleChanged {
+        trigger: Some(trigger),
+    }
+    .emit(&app)
+    .ok();
 
     Ok(())
 }
@@ -392,6 +841,163 @@ async fn resume_recording(state: MutableState<'_, App>) -> Result<(), String> {
     Ok(())
 }
 
+#[tauri::command]
+#[specta::specta]
+async fn add_recording_marker(
+    state: MutableState<'_, App>,
+    label: Option<String>,
+) -> Result<f64, String> {
+    let mut state = state.write().await;
+    let Some(recording) = &mut state.current_recording else {
+        return Err("Recording not in progress".to_string());
+    };
+
+    let label = label.unwrap_or_else(|| format!("Marker {}", recording.markers.len() + 1));
+    recording.add_marker(label)
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn start_replay_buffer(app: AppHandle, state: MutableState<'_, App>) -> Result<(), String> {
+    let mut state = state.write().await;
+
+    if state.replay_buffer.is_some() {
+        return Ok(());
+    }
+    if state.current_recording.is_some() {
+        return Err("Cannot start the replay buffer while recording".to_string());
+    }
+
+    let (encoder_settings, audio_codec, replay_duration_secs) = GeneralSettingsStore::get(&app)?
+        .map(|settings| {
+            (
+                settings.encoder_settings,
+                settings.audio_codec,
+                settings.replay_duration_secs,
+            )
+        })
+        .unwrap_or_default();
+    let description = recording_profile(&app, audio_codec)?;
+
+    // Each buffer gets a directory of its own, as a stopped one may still be saving from its own
+    let replay_buffer = ReplayBuffer::start(
+        app.path()
+            .app_data_dir()
+            .unwrap()
+            .join("replay-buffer")
+            .join(uuid::Uuid::new_v4().to_string()),
+        replay_duration_secs.unwrap_or(DEFAULT_REPLAY_DURATION_SECS),
+        &state.start_recording_options,
+        state.camera_feed.as_ref(),
+        &description,
+        &encoder_settings,
+    )
+    .await
+    .map_err(|error| {
+        eprintln!("{error}");
+        "Failed to start the replay buffer".to_string()
+    })?;
+
+    state.replay_buffer = Some(replay_buffer);
+    ReplayBufferChanged { running: true }.emit(&app).ok();
+
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn stop_replay_buffer(app: AppHandle, state: MutableState<'_, App>) -> Result<(), String> {
+    let mut state = state.write().await;
+    let Some(replay_buffer) = state.replay_buffer.take() else {
+        return Err("Replay buffer not running".to_string());
+    };
+
+    replay_buffer.stop().await;
+    ReplayBufferChanged { running: false }.emit(&app).ok();
+
+    Ok(())
+}
+
+/// Saves the last moments of the replay buffer as a new recording, returning its path.
+#[tauri::command]
+#[specta::specta]
+async fn save_replay(app: AppHandle, state: MutableState<'_, App>) -> Result<PathBuf, String> {
+    let snapshot = {
+        let state = state.read().await;
+        let Some(replay_buffer) = &state.replay_buffer else {
+            return Err("Replay buffer not running".to_string());
+        };
+        replay_buffer.snapshot()
+    };
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let recording_dir = app
+        .path()
+        .app_data_dir()
+        .unwrap()
+        .join("recordings")
+        .join(format!("{id}.cap"));
+
+    let meta = task::spawn_blocking({
+        let recording_dir = recording_dir.clone();
+        move || snapshot.save(recording_dir)
+    })
+    .await
+    .map_err(|error| error.to_string())?
+    .map_err(|error| {
+        eprintln!("{error}");
+        "Failed to save replay".to_string()
+    })?;
+
+    if let Some(display) = &meta.display {
+        create_screenshots(&recording_dir, recording_dir.join(&display.path)).await?;
+    }
+
+    let config = ProjectConfiguration {
+        timeline: Some(TimelineConfiguration {
+            segments: meta
+                .segments
+                .iter()
+                .map(|segment| TimelineSegment {
+                    start: segment.start,
+                    end: segment.end,
+                    timescale: 1.0,
+                })
+                .collect(),
+        }),
+        ..Default::default()
+    };
+    config
+        .save(&recording_dir)
+        .map_err(|error| error.to_string())?;
+
+    AppSounds::Screenshot.play();
+
+    ShowCapturesPanel.emit(&app).ok();
+
+    NewRecordingAdded {
+        path: recording_dir.clone(),
+    }
+    .emit(&app)
+    .ok();
+
+    Ok(recording_dir)
+}
+
+/// Saves a screenshot and thumbnail of a recording's first frame.
+async fn create_screenshots(recording_dir: &Path, video_path: PathBuf) -> Result<(), String> {
+    std::fs::create_dir_all(recording_dir.join("screenshots")).ok();
+    let display_screenshot = recording_dir.join("screenshots/display.jpg");
+    create_screenshot(video_path, display_screenshot.clone(), None).await?;
+
+    create_thumbnail(
+        display_screenshot,
+        recording_dir.join("screenshots/thumbnail.png"),
+        (100, 100),
+    )
+    .await
+}
+
 #[tauri::command]
 #[specta::specta]
 async fn stop_recording(app: AppHandle, state: MutableState<'_, App>) -> Result<(), String> {
@@ -407,7 +1013,7 @@ async fn stop_recording(app: AppHandle, state: MutableState<'_, App>) -> Result<
             .as_secs_f64(),
     );
 
-    current_recording.stop().await;
+    let saved = current_recording.stop().await;
     println!("Recording stopped");
 
     if let Some(window) = (CapWindow::InProgressRecording { position: None }).get(&app) {
@@ -418,22 +1024,12 @@ async fn stop_recording(app: AppHandle, state: MutableState<'_, App>) -> Result<
         window.unminimize().ok();
     }
 
-    std::fs::create_dir_all(current_recording.recording_dir.join("screenshots")).ok();
-    let display_screenshot = current_recording
-        .recording_dir
-        .join("screenshots/display.jpg");
-    create_screenshot(
-        current_recording.display_output_path.clone(),
-        display_screenshot.clone(),
-        None,
-    )
-    .await?;
+    saved.map_err(|error| format!("Failed to save recording: {error}"))?;
 
-    // Create thumbnail
-    let thumbnail = current_recording
-        .recording_dir
-        .join("screenshots/thumbnail.png");
-    create_thumbnail(display_screenshot, thumbnail, (100, 100)).await?;
+    // Audio-only recordings have nothing to take a screenshot of
+    if let Some(video_path) = current_recording.preview_video_path() {
+        create_screenshots(&current_recording.recording_dir, video_path.clone()).await?;
+    }
 
     let recording_dir = current_recording.recording_dir.clone();
 
@@ -471,11 +1067,9 @@ async fn stop_recording(app: AppHandle, state: MutableState<'_, App>) -> Result<
         }
     };
 
-    std::fs::write(
-        current_recording.recording_dir.join("project-config.json"),
-        serde_json::to_string_pretty(&json!(&config)).unwrap(),
-    )
-    .unwrap();
+    config
+        .save(&current_recording.recording_dir)
+        .map_err(|error| format!("Failed to save project config: {error}"))?;
 
     AppSounds::StopRecording.play();
 
@@ -729,7 +1323,7 @@ async fn get_rendered_video(
     video_id: String,
     project: ProjectConfiguration,
 ) -> Result<PathBuf, String> {
-    let editor_instance = upsert_editor_instance(&app, video_id.clone()).await;
+    let editor_instance = upsert_editor_instance(&app, video_id.clone()).await?;
 
     get_rendered_video_impl(editor_instance, project).await
 }
@@ -857,12 +1451,39 @@ struct AudioRender {
     pipe_tx: tokio::sync::mpsc::Sender<Vec<f64>>,
 }
 
+fn log_validation_warnings(report: &ValidationReport) {
+    for warning in &report.warnings {
+        println!("Project config warning: {warning}");
+    }
+}
+
+fn describe_validation_errors(report: &ValidationReport) -> String {
+    let errors = report
+        .errors
+        .iter()
+        .map(|error| error.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    format!("Invalid project config: {errors}")
+}
+
 async fn render_to_file_impl(
     editor_instance: &Arc<EditorInstance>,
-    project: ProjectConfiguration,
+    mut project: ProjectConfiguration,
     output_path: PathBuf,
     on_progress: impl Fn(u32) + Send + 'static,
 ) -> Result<PathBuf, String> {
+    // Exporting a broken frame or an empty video helps nobody, so fix what can be fixed
+    if !project.validate(&editor_instance.recordings).is_valid() {
+        project.fix(&editor_instance.recordings);
+    }
+    let report = project.validate(&editor_instance.recordings);
+    log_validation_warnings(&report);
+    if !report.is_valid() {
+        return Err(describe_validation_errors(&report));
+    }
+
     let recording_dir = &editor_instance.project_path;
     let audio = editor_instance.audio.clone();
     let decoders = editor_instance.decoders.clone();
@@ -1128,16 +1749,22 @@ impl EditorStateChanged {
 #[tauri::command]
 #[specta::specta]
 async fn start_playback(app: AppHandle, video_id: String) {
-    upsert_editor_instance(&app, video_id)
-        .await
-        .start_playback()
-        .await
+    match upsert_editor_instance(&app, video_id).await {
+        Ok(editor_instance) => editor_instance.start_playback().await,
+        Err(error) => eprintln!("Failed to start playback: {error}"),
+    }
 }
 
 #[tauri::command]
 #[specta::specta]
 async fn stop_playback(app: AppHandle, video_id: String) {
-    let editor_instance = upsert_editor_instance(&app, video_id).await;
+    let editor_instance = match upsert_editor_instance(&app, video_id).await {
+        Ok(editor_instance) => editor_instance,
+        Err(error) => {
+            eprintln!("Failed to stop playback: {error}");
+            return;
+        }
+    };
 
     let mut state = editor_instance.state.lock().await;
 
@@ -1153,17 +1780,25 @@ struct SerializedEditorInstance {
     recording_duration: f64,
     saved_project_config: ProjectConfiguration,
     recordings: ProjectRecordings,
+    /// The video crops apply to, which is the camera or a waveform if there's no display.
+    frame_size: FrameSize,
     path: PathBuf,
     pretty_name: String,
 }
 
+#[derive(Serialize, Type, Debug)]
+struct FrameSize {
+    width: u32,
+    height: u32,
+}
+
 #[tauri::command]
 #[specta::specta]
 async fn create_editor_instance(
     app: AppHandle,
     video_id: String,
 ) -> Result<SerializedEditorInstance, String> {
-    let editor_instance = upsert_editor_instance(&app, video_id).await;
+    let editor_instance = upsert_editor_instance(&app, video_id).await?;
 
     // Load the RecordingMeta to get the pretty name
     let meta = RecordingMeta::load_for_project(&editor_instance.project_path)
@@ -1178,7 +1813,11 @@ async fn create_editor_instance(
             let project_config = editor_instance.project_config.1.borrow();
             project_config.clone()
         },
-        recordings: editor_instance.recordings,
+        recordings: editor_instance.recordings.clone(),
+        frame_size: {
+            let (width, height) = editor_instance.render_constants.options.frame_size();
+            FrameSize { width, height }
+        },
         path: editor_instance.project_path.clone(),
         pretty_name: meta.pretty_name,
     })
@@ -1192,7 +1831,7 @@ async fn copy_rendered_video_to_clipboard(
     project: ProjectConfiguration,
 ) -> Result<(), String> {
     println!("copying");
-    let editor_instance = upsert_editor_instance(&app, video_id.clone()).await;
+    let editor_instance = upsert_editor_instance(&app, video_id.clone()).await?;
 
     let output_path = match get_rendered_video_impl(editor_instance, project).await {
         Ok(path) => {
@@ -1471,7 +2110,13 @@ async fn render_to_file(
     // 30 FPS (calculated for output video)
     let total_frames = (duration * 30.0).round() as u32;
 
-    let editor_instance = upsert_editor_instance(&app, video_id.clone()).await;
+    let editor_instance = match upsert_editor_instance(&app, video_id.clone()).await {
+        Ok(editor_instance) => editor_instance,
+        Err(error) => {
+            eprintln!("Failed to render {video_id}: {error}");
+            return;
+        }
+    };
 
     render_to_file_impl(
         &editor_instance,
@@ -1497,7 +2142,13 @@ async fn render_to_file(
 #[tauri::command]
 #[specta::specta]
 async fn set_playhead_position(app: AppHandle, video_id: String, frame_number: u32) {
-    let editor_instance = upsert_editor_instance(&app, video_id).await;
+    let editor_instance = match upsert_editor_instance(&app, video_id).await {
+        Ok(editor_instance) => editor_instance,
+        Err(error) => {
+            eprintln!("Failed to move the playhead: {error}");
+            return;
+        }
+    };
 
     editor_instance
         .modify_and_emit_state(|state| {
@@ -1508,16 +2159,34 @@ async fn set_playhead_position(app: AppHandle, video_id: String, frame_number: u
 
 #[tauri::command]
 #[specta::specta]
-async fn set_project_config(app: AppHandle, video_id: String, config: ProjectConfiguration) {
-    let editor_instance = upsert_editor_instance(&app, video_id).await;
+async fn set_project_config(
+    app: AppHandle,
+    video_id: String,
+    mut config: ProjectConfiguration,
+) -> Result<Option<ProjectConfiguration>, String> {
+    let editor_instance = upsert_editor_instance(&app, video_id).await?;
+
+    // Rejecting the edit would leave the editor showing something that's neither saved
+    // nor previewed, so fix it like an export would and hand the fixed config back
+    let mut report = config.validate(&editor_instance.recordings);
+    let fixed = !report.is_valid();
+    if fixed {
+        println!("{}, fixing it", describe_validation_errors(&report));
+        config.fix(&editor_instance.recordings);
+        report = config.validate(&editor_instance.recordings);
+    }
+    log_validation_warnings(&report);
+    if !report.is_valid() {
+        return Err(describe_validation_errors(&report));
+    }
 
-    std::fs::write(
-        editor_instance.project_path.join("project-config.json"),
-        serde_json::to_string_pretty(&json!(config)).unwrap(),
-    )
-    .unwrap();
+    config
+        .save(&editor_instance.project_path)
+        .map_err(|error| error.to_string())?;
+
+    editor_instance.project_config.0.send(config.clone()).ok();
 
-    editor_instance.project_config.0.send(config).ok();
+    Ok(fixed.then_some(config))
 }
 
 #[tauri::command(async)]
@@ -1619,9 +2288,9 @@ async fn upload_rendered_video(
         }
     }
 
-    let editor_instance = upsert_editor_instance(&app, video_id.clone()).await;
+    let editor_instance = upsert_editor_instance(&app, video_id.clone()).await?;
 
-    let mut meta = editor_instance.meta();
+    let mut meta = editor_instance.meta().map_err(|e| e.to_string())?;
 
     let share_link = if let Some(sharing) = meta.sharing {
         sharing.link
@@ -1645,7 +2314,7 @@ async fn upload_rendered_video(
             link: pre_created.link.clone(),
             id: pre_created.id.clone(),
         });
-        meta.save_for_project();
+        meta.save_for_project().map_err(|e| e.to_string())?;
         RecordingMetaChanged { id: video_id }.emit(&app).ok();
 
         pre_created.link
@@ -1673,8 +2342,14 @@ async fn upload_rendered_video(
                     .join("recordings")
                     .join(format!("{}.cap", video_id));
 
+                let audio_path = RecordingMeta::load_for_project(&video_dir)
+                    .ok()
+                    .and_then(|meta| meta.audio)
+                    .map(|audio| video_dir.join(audio.path))
+                    .unwrap_or_else(|| video_dir.join("content/audio-input.mp3"));
+
                 let files_to_upload = vec![
-                    (video_dir.join("content/audio-input.mp3"), true),
+                    (audio_path, true),
                     (video_dir.join("content/camera.mp4"), false),
                     (video_dir.join("content/display.mp4"), false),
                 ];
@@ -1717,7 +2392,7 @@ async fn upload_rendered_video(
             link: uploaded_video.link.clone(),
             id: uploaded_video.id.clone(),
         });
-        meta.save_for_project();
+        meta.save_for_project().map_err(|e| e.to_string())?;
         RecordingMetaChanged { id: video_id }.emit(&app).ok();
 
         uploaded_video.link
@@ -1799,7 +2474,7 @@ async fn upload_screenshot(
     println!("Uploading screenshot: {:?}", screenshot_path);
 
     let screenshot_dir = screenshot_path.parent().unwrap().to_path_buf();
-    let mut meta = RecordingMeta::load_for_project(&screenshot_dir).unwrap();
+    let mut meta = RecordingMeta::load_for_project(&screenshot_dir).map_err(|e| e.to_string())?;
 
     let share_link = if let Some(sharing) = meta.sharing.as_ref() {
         // Screenshot already uploaded, use existing link
@@ -1815,7 +2490,7 @@ async fn upload_screenshot(
             link: uploaded.link.clone(),
             id: uploaded.id.clone(),
         });
-        meta.save_for_project();
+        meta.save_for_project().map_err(|e| e.to_string())?;
 
         RecordingMetaChanged {
             id: screenshot_path
@@ -1953,14 +2628,18 @@ async fn take_screenshot(app: AppHandle, state: MutableState<'_, App>) -> Result
                 project_path: recording_dir.clone(),
                 sharing: None,
                 pretty_name: screenshot_name,
-                display: Display {
+                display: Some(Display {
                     path: screenshot_path.clone(),
-                },
+                }),
+                additional_displays: vec![],
                 camera: None,
                 audio: None,
                 segments: vec![],
+                markers: vec![],
+                camera_switches: vec![],
             }
-            .save_for_project();
+            .save_for_project()
+            .map_err(|e| e.to_string())?;
 
             NewScreenshotAdded {
                 path: screenshot_path,
@@ -2054,14 +2733,18 @@ struct RecordingMetaChanged {
 
 #[tauri::command(async)]
 #[specta::specta]
-fn get_recording_meta(app: AppHandle, id: String, file_type: String) -> RecordingMeta {
+fn get_recording_meta(
+    app: AppHandle,
+    id: String,
+    file_type: String,
+) -> Result<RecordingMeta, String> {
     let meta_path = match file_type.as_str() {
         "recording" => recording_path(&app, &id),
         "screenshot" => screenshot_path(&app, &id),
         _ => panic!("Invalid file type: {}", file_type),
     };
 
-    RecordingMeta::load_for_project(&meta_path).unwrap()
+    RecordingMeta::load_for_project(&meta_path).map_err(|e| e.to_string())
 }
 #[tauri::command]
 #[specta::specta]
@@ -2075,7 +2758,9 @@ fn list_recordings(app: AppHandle) -> Result<Vec<(String, PathBuf, RecordingMeta
             let path = entry.path();
             if path.is_dir() && path.extension().and_then(|s| s.to_str()) == Some("cap") {
                 let id = path.file_stem()?.to_str()?.to_string();
-                let meta = get_recording_meta(app.clone(), id.clone(), "recording".to_string());
+                let meta = get_recording_meta(app.clone(), id.clone(), "recording".to_string())
+                    .map_err(|error| eprintln!("Skipping {}: {error}", path.display()))
+                    .ok()?;
                 Some((id, path.clone(), meta))
             } else {
                 None
@@ -2109,7 +2794,9 @@ fn list_screenshots(app: AppHandle) -> Result<Vec<(String, PathBuf, RecordingMet
             let path = entry.path();
             if path.is_dir() && path.extension().and_then(|s| s.to_str()) == Some("cap") {
                 let id = path.file_stem()?.to_str()?.to_string();
-                let meta = get_recording_meta(app.clone(), id.clone(), "screenshot".to_string());
+                let meta = get_recording_meta(app.clone(), id.clone(), "screenshot".to_string())
+                    .map_err(|error| eprintln!("Skipping {}: {error}", path.display()))
+                    .ok()?;
 
                 // Find the nearest .png file inside the .cap folder
                 let png_path = std::fs::read_dir(&path)
@@ -2248,8 +2935,15 @@ pub async fn run() {
             stop_recording,
             pause_recording,
             resume_recording,
+            add_recording_marker,
+            cancel_pending_recording,
+            schedule_recording,
+            start_replay_buffer,
+            stop_replay_buffer,
+            save_replay,
             take_screenshot,
             list_cameras,
+            list_camera_formats,
             list_capture_windows,
             list_audio_devices,
             show_previous_recordings_window,
@@ -2306,10 +3000,21 @@ pub async fn run() {
             RecordingMetaChanged,
             RecordingStarted,
             RecordingStopped,
+            LiveStreamStatusChanged,
+            CameraFeedChanged,
+            AudioInputStatusChanged,
+            AudioInputLevelChanged,
+            AudioInputSilent,
             RequestStartRecording,
             RequestRestartRecording,
             RequestStopRecording,
             RequestNewScreenshot,
+            RequestSaveReplay,
+            ReplayBufferChanged,
+            RequestCancelRecording,
+            RecordingCountdown,
+            RecordingCountdownCancelled,
+            RecordingScheduleChanged,
             RequestOpenSettings,
         ])
         .ty::<ProjectConfiguration>()
@@ -2366,17 +3071,31 @@ pub async fn run() {
                 permissions::open_permissions_window(app_handle.clone());
             }
 
+            // Replay buffers left over from before a crash
+            if let Ok(app_data_dir) = app.path().app_data_dir() {
+                std::fs::remove_dir_all(app_data_dir.join("replay-buffer")).ok();
+            }
+
             app.manage(Arc::new(RwLock::new(App {
                 handle: app_handle.clone(),
                 camera_tx,
                 camera_ws_port,
                 camera_feed: None,
+                audio_level_monitor: None,
                 start_recording_options: RecordingOptions {
+                    mode: RecordingMode::Screen,
                     capture_target: ScreenCaptureTarget::Screen,
+                    additional_capture_targets: vec![],
                     camera_label: None,
+                    camera_format: Default::default(),
                     audio_input_name: None,
+                    redactions: vec![],
+                    limits: Default::default(),
                 },
                 current_recording: None,
+                pending_recording: None,
+                scheduled_recording: None,
+                replay_buffer: None,
                 pre_created_video: None,
             })));
 
@@ -2393,12 +3112,21 @@ pub async fn run() {
                 let app_handle = app_handle_clone.clone();
                 tauri::async_runtime::spawn(async move {
                     let state = app_handle.state::<Arc<RwLock<App>>>();
-                    let is_recording = {
+                    let (is_recording, is_pending) = {
                         let app_state = state.read().await;
-                        app_state.current_recording.is_some()
+                        (
+                            app_state.current_recording.is_some(),
+                            app_state.has_pending_recording(),
+                        )
                     };
 
-                    if is_recording {
+                    if is_pending {
+                        if let Err(e) =
+                            cancel_pending_recording(app_handle.clone(), app_handle.state()).await
+                        {
+                            eprintln!("Failed to cancel recording: {}", e);
+                        }
+                    } else if is_recording {
                         if let Err(e) = stop_recording(app_handle.clone(), app_handle.state()).await
                         {
                             eprintln!("Failed to stop recording: {}", e);
@@ -2417,12 +3145,32 @@ pub async fn run() {
             RequestStopRecording::listen_any(app, move |_| {
                 let app_handle = app_handle_clone.clone();
                 tauri::async_runtime::spawn(async move {
-                    if let Err(e) = stop_recording(app_handle.clone(), app_handle.state()).await {
+                    let state = app_handle.state::<Arc<RwLock<App>>>();
+                    let is_pending = state.read().await.has_pending_recording();
+
+                    let result = if is_pending {
+                        cancel_pending_recording(app_handle.clone(), app_handle.state()).await
+                    } else {
+                        stop_recording(app_handle.clone(), app_handle.state()).await
+                    };
+                    if let Err(e) = result {
                         eprintln!("Failed to stop recording: {}", e);
                     }
                 });
             });
 
+            let app_handle_clone = app_handle.clone();
+            RequestCancelRecording::listen_any(app, move |_| {
+                let app_handle = app_handle_clone.clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) =
+                        cancel_pending_recording(app_handle.clone(), app_handle.state()).await
+                    {
+                        eprintln!("Failed to cancel recording: {}", e);
+                    }
+                });
+            });
+
             let app_handle_clone = app_handle.clone();
             RequestRestartRecording::listen_any(app, move |_| {
                 let app_handle = app_handle_clone.clone();
@@ -2449,6 +3197,16 @@ pub async fn run() {
                 });
             });
 
+            let app_handle_clone = app_handle.clone();
+            RequestSaveReplay::listen_any(app, move |_| {
+                let app_handle = app_handle_clone.clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = save_replay(app_handle.clone(), app_handle.state()).await {
+                        eprintln!("Failed to save replay: {}", e);
+                    }
+                });
+            });
+
             let app_handle_clone = app_handle.clone();
             RequestNewScreenshot::listen_any(app, move |_| {
                 let app_handle = app_handle_clone.clone();
@@ -2547,7 +3305,10 @@ pub async fn remove_editor_instance(
     result
 }
 
-pub async fn upsert_editor_instance(app: &AppHandle, video_id: String) -> Arc<EditorInstance> {
+pub async fn upsert_editor_instance(
+    app: &AppHandle,
+    video_id: String,
+) -> Result<Arc<EditorInstance>, String> {
     let map = match app.try_state::<EditorInstancesState>() {
         Some(s) => (*s).clone(),
         None => {
@@ -2561,23 +3322,27 @@ pub async fn upsert_editor_instance(app: &AppHandle, video_id: String) -> Arc<Ed
 
     use std::collections::hash_map::Entry;
     match map.entry(video_id.clone()) {
-        Entry::Occupied(o) => o.get().clone(),
+        Entry::Occupied(o) => Ok(o.get().clone()),
         Entry::Vacant(v) => {
-            let instance = create_editor_instance_impl(app, video_id).await;
+            let instance = create_editor_instance_impl(app, video_id).await?;
             v.insert(instance.clone());
-            instance
+            Ok(instance)
         }
     }
 }
 
-async fn create_editor_instance_impl(app: &AppHandle, video_id: String) -> Arc<EditorInstance> {
+async fn create_editor_instance_impl(
+    app: &AppHandle,
+    video_id: String,
+) -> Result<Arc<EditorInstance>, String> {
     let instance = EditorInstance::new(recordings_path(app), video_id, {
         let app = app.clone();
         move |state| {
             EditorStateChanged::new(state).emit(&app).ok();
         }
     })
-    .await;
+    .await
+    .map_err(|e| format!("Failed to open the recording: {e}"))?;
 
     RenderFrameEvent::listen_any(app, {
         let preview_tx = instance.preview_tx.clone();
@@ -2586,7 +3351,7 @@ async fn create_editor_instance_impl(app: &AppHandle, video_id: String) -> Arc<E
         }
     });
 
-    instance
+    Ok(instance)
 }
 
 // use EditorInstance.project_path instead of this
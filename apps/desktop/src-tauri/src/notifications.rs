@@ -1,3 +1,4 @@
+use cap_media::pipeline::limits::RecordingLimit;
 use tauri_plugin_notification::NotificationExt;
 
 pub enum NotificationType {
@@ -5,6 +6,7 @@ pub enum NotificationType {
     VideoCopiedToClipboard,
     ShareableLinkCopied,
     UploadFailed,
+    RecordingLimitReached(RecordingLimit),
 }
 
 impl NotificationType {
@@ -25,6 +27,18 @@ impl NotificationType {
                 "Upload Failed",
                 "Failed to upload your video after multiple attempts. Please try again later.",
             ),
+            NotificationType::RecordingLimitReached(RecordingLimit::Duration) => (
+                "Recording Stopped",
+                "Your recording reached its maximum length and has been saved.",
+            ),
+            NotificationType::RecordingLimitReached(RecordingLimit::Size) => (
+                "Recording Stopped",
+                "Your recording reached its maximum size and has been saved.",
+            ),
+            NotificationType::RecordingLimitReached(RecordingLimit::FreeDiskSpace) => (
+                "Recording Stopped",
+                "Your disk is almost full, so your recording has been saved early.",
+            ),
         }
     }
 
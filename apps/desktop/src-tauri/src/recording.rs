@@ -1,15 +1,26 @@
-use cap_media::{encoders::*, feeds::*, filters::*, pipeline::*, sources::*, MediaError};
+use cap_media::{
+    description::{DescribedOutput, PipelineDescription, PipelineInputs, SourceKind},
+    encoders::{
+        save_replay, EncoderSettings, ReplayOptions, ReplayStart, SegmentedOutput, StreamOutput,
+    },
+    feeds::*,
+    filters::DriftTracker,
+    pipeline::*,
+    sources::*,
+    MediaError,
+};
 use serde::Deserialize;
 use serde::Serialize;
 use specta::Type;
-use std::time::{SystemTime, UNIX_EPOCH};
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use objc::rc::autoreleasepool;
 use objc::runtime::{Class, Object, Sel, BOOL, YES};
 use objc::*;
 
-use crate::RecordingOptions;
+use crate::{segment_upload::SegmentUploader, RecordingOptions};
 
 // TODO: Hacky, please fix
 pub const FPS: u32 = 30;
@@ -26,6 +37,12 @@ pub fn list_cameras() -> Vec<String> {
     CameraFeed::list_cameras()
 }
 
+#[tauri::command(async)]
+#[specta::specta]
+pub fn list_camera_formats(camera_label: String) -> Result<Vec<CameraFormatInfo>, String> {
+    CameraFeed::list_formats(&camera_label).map_err(|error| error.to_string())
+}
+
 #[derive(Serialize, Deserialize, Clone, Type)]
 pub struct MouseEvent {
     pub active_modifiers: Vec<String>,
@@ -37,20 +54,46 @@ pub struct MouseEvent {
     pub y: f64,
 }
 
+/// Which sources a recording captures.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub enum RecordingMode {
+    /// The capture target, along with the camera and microphone when they're selected.
+    #[default]
+    Screen,
+    /// The camera and microphone, e.g. for talking-head clips.
+    CameraOnly,
+    AudioOnly,
+}
+
 #[derive(Type, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct InProgressRecording {
     pub recording_dir: PathBuf,
     #[serde(skip)]
     pub pipeline: Pipeline<SynchronisedClock<()>>,
+    /// `None` unless the screen is being recorded.
+    #[serde(skip)]
+    pub display_output_path: Option<PathBuf>,
     #[serde(skip)]
-    pub display_output_path: PathBuf,
+    pub additional_display_output_paths: Vec<PathBuf>,
     #[serde(skip)]
     pub camera_output_path: Option<PathBuf>,
     #[serde(skip)]
     pub audio_output_path: Option<PathBuf>,
-    pub display_source: ScreenCaptureTarget,
+    #[serde(skip)]
+    pub audio_drift: Option<DriftTracker>,
+    /// HLS segments of the display, written alongside the display file when set.
+    #[serde(skip)]
+    pub display_segments: Option<SegmentedOutput>,
+    #[serde(skip)]
+    pub segment_uploader: Option<SegmentUploader>,
+    pub display_source: Option<ScreenCaptureTarget>,
     pub segments: Vec<f64>,
+    #[serde(skip)]
+    pub markers: Vec<cap_project::RecordingMarker>,
+    #[serde(skip)]
+    pub camera_switches: Vec<cap_project::CameraSwitch>,
     // #[serde(skip)]
     // pub mouse_moves: Arc<Mutex<Vec<MouseEvent>>>,
     // #[serde(skip)]
@@ -61,8 +104,18 @@ unsafe impl Send for InProgressRecording {}
 unsafe impl Sync for InProgressRecording {}
 
 impl InProgressRecording {
-    pub async fn stop(&mut self) {
+    pub async fn stop(&mut self) -> Result<(), cap_project::ProjectError> {
         use cap_project::*;
+
+        // Signal the mouse event tracking to stop
+        if let Err(error) = self.pipeline.shutdown().await {
+            eprintln!("Error while stopping recording: {error}");
+        }
+
+        if let Some(uploader) = self.segment_uploader.take() {
+            uploader.finish().await;
+        }
+
         let meta = RecordingMeta {
             project_path: self.recording_dir.clone(),
             sharing: None,
@@ -70,18 +123,30 @@ impl InProgressRecording {
                 "Cap {}",
                 chrono::Local::now().format("%Y-%m-%d at %H.%M.%S")
             ),
-            display: Display {
-                path: self
-                    .display_output_path
-                    .strip_prefix(&self.recording_dir)
-                    .unwrap()
-                    .to_owned(),
-            },
+            display: self.display_output_path.as_ref().map(|path| Display {
+                path: path.strip_prefix(&self.recording_dir).unwrap().to_owned(),
+            }),
+            additional_displays: self
+                .additional_display_output_paths
+                .iter()
+                .map(|path| Display {
+                    path: path.strip_prefix(&self.recording_dir).unwrap().to_owned(),
+                })
+                .collect(),
             camera: self.camera_output_path.as_ref().map(|path| CameraMeta {
                 path: path.strip_prefix(&self.recording_dir).unwrap().to_owned(),
             }),
             audio: self.audio_output_path.as_ref().map(|path| AudioMeta {
                 path: path.strip_prefix(&self.recording_dir).unwrap().to_owned(),
+                drift: self.audio_drift.as_ref().map(|tracker| {
+                    let measurement = tracker.measurement();
+                    AudioDrift {
+                        sample_rate: measurement.sample_rate,
+                        expected_duration: measurement.expected_duration(),
+                        actual_duration: measurement.actual_duration(),
+                        ppm: measurement.ppm(),
+                    }
+                }),
             }),
             segments: {
                 let relative_segments = self
@@ -107,13 +172,10 @@ impl InProgressRecording {
 
                 segments
             },
+            markers: self.markers.clone(),
+            camera_switches: self.camera_switches.clone(),
         };
 
-        // Signal the mouse event tracking to stop
-        if let Err(error) = self.pipeline.shutdown().await {
-            eprintln!("Error while stopping recording: {error}");
-        }
-
         // if flags::RECORD_MOUSE {
         //     // Save mouse events to files
         //     let mouse_moves_path = self.recording_dir.join("mousemoves.json");
@@ -129,7 +191,7 @@ impl InProgressRecording {
         //     serde_json::to_writer(&mut mouse_clicks_file, &*mouse_clicks).unwrap();
         // }
 
-        meta.save_for_project();
+        meta.save_for_project()
     }
 
     pub async fn stop_and_discard(&mut self) {
@@ -138,6 +200,10 @@ impl InProgressRecording {
             eprintln!("Error while stopping recording: {error}");
         }
 
+        if let Some(uploader) = self.segment_uploader.take() {
+            uploader.cancel();
+        }
+
         // Delete all recorded files
         if let Err(e) = std::fs::remove_dir_all(&self.recording_dir) {
             eprintln!("Failed to delete recording directory: {:?}", e);
@@ -153,75 +219,145 @@ impl InProgressRecording {
         let _ = self.pipeline.play().await;
         Ok(())
     }
+
+    /// Marks the current point in the recording, returning its time in seconds.
+    pub fn add_marker(&mut self, label: String) -> Result<f64, String> {
+        let timestamp = self
+            .pipeline
+            .add_marker(label.clone())
+            .map_err(|error| error.to_string())?;
+        let time = timestamp as f64 / 1_000_000.0;
+
+        self.markers
+            .push(cap_project::RecordingMarker { time, label });
+
+        Ok(time)
+    }
+
+    /// Notes that the camera feed has moved to another camera, for the editor.
+    pub fn add_camera_switch(&mut self, camera: String) {
+        let time = self.pipeline.elapsed().as_secs_f64();
+
+        self.camera_switches
+            .push(cap_project::CameraSwitch { time, camera });
+    }
+
+    /// The video to take screenshots and thumbnails from, if there is one.
+    pub fn preview_video_path(&self) -> Option<&PathBuf> {
+        self.display_output_path
+            .as_ref()
+            .or(self.camera_output_path.as_ref())
+    }
+
+    /// Starts capturing a recording made by `prepare`.
+    pub async fn begin(&mut self) -> Result<(), MediaError> {
+        self.pipeline.play().await?;
+        self.segments = vec![SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs_f64()];
+
+        Ok(())
+    }
 }
 
-pub async fn start(
+/// Builds and warms up a recording's pipeline without starting it, so that capture starts
+/// straight away once `begin` is called, e.g. at the end of a countdown.
+pub async fn prepare(
     recording_dir: PathBuf,
     recording_options: &RecordingOptions,
     camera_feed: Option<&CameraFeed>,
+    description: &PipelineDescription,
+    encoder_settings: &EncoderSettings,
+    display_segments: Option<&SegmentedOutput>,
+    display_stream: Option<&StreamOutput>,
 ) -> Result<InProgressRecording, MediaError> {
+    let has_source = |kind| description.sources().any(|source| source == kind);
+    let mode = recording_options.mode;
+    match mode {
+        RecordingMode::Screen if !has_source(SourceKind::Screen) => {
+            return Err(MediaError::Any(
+                "Recording profiles must include a screen capture source",
+            ));
+        }
+        RecordingMode::CameraOnly if camera_feed.is_none() || !has_source(SourceKind::Camera) => {
+            return Err(MediaError::Any("Camera-only recordings need a camera"));
+        }
+        RecordingMode::AudioOnly
+            if recording_options.audio_input_name.is_none()
+                || !has_source(SourceKind::Microphone) =>
+        {
+            return Err(MediaError::Any("Audio-only recordings need a microphone"));
+        }
+        _ => {}
+    }
+    let capture_targets: Vec<_> = match mode {
+        RecordingMode::Screen => std::iter::once(&recording_options.capture_target)
+            .chain(&recording_options.additional_capture_targets)
+            .cloned()
+            .collect(),
+        _ => vec![],
+    };
+    let camera_feed = camera_feed.filter(|_| mode != RecordingMode::AudioOnly);
+
     let content_dir = recording_dir.join("content");
 
     std::fs::create_dir_all(&content_dir).unwrap();
 
     let clock = SynchronisedClock::<()>::new();
-    let mut pipeline_builder = Pipeline::builder(clock);
-
-    let display_output_path = content_dir.join("display.mp4");
-    let mut audio_output_path = None;
-    let mut camera_output_path = None;
-
-    let screen_source = ScreenCaptureSource::init(&recording_options.capture_target, None, None);
-    let screen_config = screen_source.info();
-    let output_config = screen_config.scaled(1920, 30);
-    let screen_filter = VideoFilter::init("screen", screen_config, output_config)?;
-    let screen_encoder = H264Encoder::init(
-        "screen",
-        output_config,
-        Output::File(display_output_path.clone()),
-    )?;
-    pipeline_builder = pipeline_builder
-        .source("screen_capture", screen_source)
-        .pipe("screen_capture_filter", screen_filter)
-        .sink("screen_capture_encoder", screen_encoder);
-
-    if let Some(mic_source) = AudioInputSource::init(recording_options.audio_input_name.as_ref()) {
-        let mic_config = mic_source.info();
-        audio_output_path = Some(content_dir.join("audio-input.mp3"));
-
-        // let mic_filter = AudioFilter::init("microphone", mic_config, "aresample=async=1:min_hard_comp=0.100000:first_pts=0")?;
-        let mic_encoder = MP3Encoder::init(
-            "microphone",
-            mic_config,
-            Output::File(audio_output_path.clone().unwrap()),
-        )?;
-
-        pipeline_builder = pipeline_builder
-            .source("microphone_capture", mic_source)
-            // .pipe("microphone_filter", mic_filter)
-            .sink("microphone_encoder", mic_encoder);
-    }
-
-    if let Some(camera_source) = CameraSource::init(camera_feed) {
-        let camera_config = camera_source.info();
-        let output_config = camera_config.scaled(1920, 30);
-        camera_output_path = Some(content_dir.join("camera.mp4"));
 
-        let camera_filter = VideoFilter::init("camera", camera_config, output_config)?;
-        let camera_encoder = H264Encoder::init(
-            "camera",
-            output_config,
-            Output::File(camera_output_path.clone().unwrap()),
-        )?;
-
-        pipeline_builder = pipeline_builder
-            .source("camera_capture", camera_source)
-            .pipe("camera_filter", camera_filter)
-            .sink("camera_encoder", camera_encoder);
-    }
-
-    let mut pipeline = pipeline_builder.build().await?;
-    pipeline.play().await?;
+    let (mut pipeline, outputs) = description
+        .build(
+            clock,
+            PipelineInputs {
+                capture_targets: &capture_targets,
+                audio_input_name: recording_options.audio_input_name.as_ref(),
+                camera_feed,
+                output_dir: &content_dir,
+                encoder_settings,
+                screen_segments: display_segments,
+                screen_stream: display_stream,
+                screen_redactions: &recording_options.redactions,
+                replay: None,
+            },
+        )
+        .await?;
+
+    let output_path_for = |kind: SourceKind| {
+        outputs
+            .iter()
+            .find(|output| output.source == kind)
+            .map(|output| output.path.clone())
+    };
+    let audio_output_path = output_path_for(SourceKind::Microphone);
+    let camera_output_path = output_path_for(SourceKind::Camera);
+    let mut display_output_paths = outputs
+        .iter()
+        .filter(|output| output.source == SourceKind::Screen)
+        .map(|output| output.path.clone());
+    let display_output_path = display_output_paths.next();
+    let additional_display_output_paths = display_output_paths.collect();
+    let audio_drift = outputs
+        .iter()
+        .find(|output| output.source == SourceKind::Microphone)
+        .and_then(|output| output.drift.clone());
+    let display_segments = outputs
+        .iter()
+        .find(|output| output.source == SourceKind::Screen)
+        .and_then(|output| output.segments.clone());
+
+    // HLS copies written for uploading take up as much space again.
+    let limited_outputs = outputs
+        .iter()
+        .flat_map(|output| {
+            let segments = output
+                .segments
+                .as_ref()
+                .map(|segments| segments.dir.clone());
+            std::iter::once(output.path.clone()).chain(segments)
+        })
+        .collect();
+    pipeline.watch_limits(recording_options.limits, limited_outputs, content_dir);
 
     // Initialize mouse event tracking
     // let mouse_moves = Arc::new(Mutex::new(Vec::new()));
@@ -274,22 +410,222 @@ pub async fn start(
     // });
 
     Ok(InProgressRecording {
-        segments: vec![SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs_f64()],
+        segments: vec![],
         pipeline,
         recording_dir,
-        display_source: recording_options.capture_target.clone(),
+        display_source: capture_targets.first().cloned(),
         display_output_path,
+        additional_display_output_paths,
         audio_output_path,
+        audio_drift,
+        display_segments,
+        segment_uploader: None,
         camera_output_path,
+        markers: vec![],
+        camera_switches: vec![],
         // mouse_moves,
         // mouse_clicks,
         // stop_signal,
     })
 }
 
+/// Records into a bounded ring of segments, so the last moments can be saved as a project
+/// at any time without recording everything.
+pub struct ReplayBuffer {
+    pipeline: Pipeline<SynchronisedClock<()>>,
+    options: ReplayOptions,
+    outputs: Vec<DescribedOutput>,
+    dir: Arc<ReplayDir>,
+}
+
+/// Deletes a replay buffer's segments once neither the buffer nor a save in progress needs them.
+struct ReplayDir(PathBuf);
+
+impl Drop for ReplayDir {
+    fn drop(&mut self) {
+        if let Err(error) = std::fs::remove_dir_all(&self.0) {
+            eprintln!("Failed to delete replay buffer directory: {error}");
+        }
+    }
+}
+
+unsafe impl Send for ReplayBuffer {}
+unsafe impl Sync for ReplayBuffer {}
+
+impl ReplayBuffer {
+    /// `replay_dir` is removed when the buffer stops, so it shouldn't be shared with another
+    /// buffer that might still be saving.
+    pub async fn start(
+        replay_dir: PathBuf,
+        duration_secs: u32,
+        recording_options: &RecordingOptions,
+        camera_feed: Option<&CameraFeed>,
+        description: &PipelineDescription,
+        encoder_settings: &EncoderSettings,
+    ) -> Result<Self, MediaError> {
+        if !description.sources().any(|kind| kind == SourceKind::Screen) {
+            return Err(MediaError::Any(
+                "Recording profiles must include a screen capture source",
+            ));
+        }
+
+        let options = ReplayOptions {
+            dir: replay_dir,
+            duration_secs,
+            segment_duration_secs: encoder_settings.keyframe_interval_secs,
+        };
+
+        std::fs::create_dir_all(&options.dir)
+            .map_err(|_| MediaError::Any("Failed to create the replay buffer directory"))?;
+        let dir = Arc::new(ReplayDir(options.dir.clone()));
+
+        let (mut pipeline, outputs) = description
+            .build(
+                SynchronisedClock::<()>::new(),
+                PipelineInputs {
+                    capture_targets: std::slice::from_ref(&recording_options.capture_target),
+                    audio_input_name: recording_options.audio_input_name.as_ref(),
+                    camera_feed,
+                    output_dir: &options.dir,
+                    encoder_settings,
+                    screen_segments: None,
+                    screen_stream: None,
+                    screen_redactions: &recording_options.redactions,
+                    replay: Some(&options),
+                },
+            )
+            .await?;
+
+        pipeline.play().await?;
+
+        Ok(Self {
+            pipeline,
+            options,
+            outputs,
+            dir,
+        })
+    }
+
+    /// Stops recording. The segments are deleted once any saves in progress are done with them.
+    pub async fn stop(mut self) {
+        if let Err(error) = self.pipeline.shutdown().await {
+            eprintln!("Error while stopping replay buffer: {error}");
+        }
+    }
+
+    /// What `save` needs, so that saving can happen off the async runtime.
+    pub fn snapshot(&self) -> ReplaySnapshot {
+        ReplaySnapshot {
+            duration_secs: self.options.duration_secs,
+            outputs: self.outputs.clone(),
+            _dir: self.dir.clone(),
+        }
+    }
+}
+
+pub struct ReplaySnapshot {
+    duration_secs: u32,
+    outputs: Vec<DescribedOutput>,
+    /// Keeps the segments around until the save is done, even if the buffer stops meanwhile.
+    _dir: Arc<ReplayDir>,
+}
+
+impl ReplaySnapshot {
+    /// Saves the last moments of the buffer as a project in `recording_dir`. The screen decides
+    /// where the replay starts, and the camera and microphone are cut to match it. Nothing is
+    /// left in `recording_dir` if saving fails.
+    pub fn save(&self, recording_dir: PathBuf) -> Result<cap_project::RecordingMeta, MediaError> {
+        let result = self.save_to(recording_dir.clone());
+        if result.is_err() {
+            std::fs::remove_dir_all(&recording_dir).ok();
+        }
+
+        result
+    }
+
+    fn save_to(&self, recording_dir: PathBuf) -> Result<cap_project::RecordingMeta, MediaError> {
+        use cap_project::*;
+
+        let content_dir = recording_dir.join("content");
+        std::fs::create_dir_all(&content_dir)
+            .map_err(|_| MediaError::Any("Failed to create the recording directory"))?;
+
+        let content_path = |output: &DescribedOutput| {
+            content_dir.join(output.path.file_name().unwrap_or_default())
+        };
+        let relative_path = |path: &PathBuf| path.strip_prefix(&recording_dir).unwrap().to_owned();
+
+        let (screen, screen_segments) = self
+            .outputs
+            .iter()
+            .find(|output| output.source == SourceKind::Screen)
+            .and_then(|output| Some((output, output.segments.as_ref()?)))
+            .ok_or(MediaError::Any("Replay buffer has no screen capture"))?;
+        let display_path = content_path(screen);
+        let clip = save_replay(
+            screen_segments,
+            ReplayStart::LastSeconds(self.duration_secs as f64),
+            &display_path,
+        )?;
+
+        let mut camera_path = None;
+        let mut audio_path = None;
+        for output in &self.outputs {
+            let Some(segments) = &output.segments else {
+                continue;
+            };
+            let path = content_path(output);
+            let saved_path = match output.source {
+                SourceKind::Screen => continue,
+                SourceKind::Camera => &mut camera_path,
+                SourceKind::Microphone => &mut audio_path,
+            };
+
+            match save_replay(segments, ReplayStart::From(clip.start_secs), &path) {
+                Ok(_) => *saved_path = Some(path),
+                Err(error) => {
+                    eprintln!("Failed to save {:?} replay: {error}", output.source);
+                    std::fs::remove_file(&path).ok();
+                }
+            }
+        }
+
+        let meta = RecordingMeta {
+            project_path: recording_dir.clone(),
+            sharing: None,
+            pretty_name: format!(
+                "Cap Replay {}",
+                chrono::Local::now().format("%Y-%m-%d at %H.%M.%S")
+            ),
+            display: Some(Display {
+                path: relative_path(&display_path),
+            }),
+            additional_displays: vec![],
+            camera: camera_path.as_ref().map(|path| CameraMeta {
+                path: relative_path(path),
+            }),
+            audio: audio_path.as_ref().map(|path| AudioMeta {
+                path: relative_path(path),
+                // Drift is measured over the whole buffer, not just the replay.
+                drift: None,
+            }),
+            segments: vec![RecordingSegment {
+                start: 0.0,
+                end: clip.duration_secs,
+            }],
+            markers: vec![],
+            camera_switches: vec![],
+        };
+
+        meta.save_for_project().map_err(|error| {
+            eprintln!("{error}");
+            MediaError::Any("Failed to save the replay's recording meta")
+        })?;
+
+        Ok(meta)
+    }
+}
+
 fn get_cursor_id() -> String {
     autoreleasepool(|| {
         // Get the NSCursor class
@@ -0,0 +1,310 @@
+//! Uploads the HLS segments of a recording while it is still in progress, so that a
+//! pre-created shareable link can play the recording as soon as it stops.
+
+use std::{collections::HashSet, future::Future, path::PathBuf, time::Duration};
+
+use cap_media::encoders::SegmentedOutput;
+use tauri::AppHandle;
+use tokio::sync::oneshot;
+
+use crate::upload::{upload_segment, S3UploadMeta};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+const FINAL_UPLOAD_ATTEMPTS: u32 = 3;
+/// Doubled after every failed final attempt.
+const FINAL_RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// The server serves recordings from this prefix until the rendered video is uploaded.
+const SEGMENTS_PREFIX: &str = "combined-source";
+
+pub struct SegmentUploader {
+    stop_tx: oneshot::Sender<()>,
+    task: tauri::async_runtime::JoinHandle<()>,
+}
+
+impl SegmentUploader {
+    /// Watches the playlist written by `segments`, uploading each segment once the encoder
+    /// has closed it and lists it in the playlist.
+    pub fn spawn(app: AppHandle, segments: &SegmentedOutput, s3_config: S3UploadMeta) -> Self {
+        let (stop_tx, mut stop_rx) = oneshot::channel();
+        let mut uploads = PendingUploads::new(
+            segments,
+            format!(
+                "{}/{}/{SEGMENTS_PREFIX}",
+                s3_config.user_id(),
+                s3_config.id()
+            ),
+        );
+        let upload = move |file: String, contents: Vec<u8>, key: String| {
+            let app = app.clone();
+            let s3_config = s3_config.clone();
+            async move { upload_segment(&app, &s3_config, &file, contents, key).await }
+        };
+
+        let task = tauri::async_runtime::spawn(async move {
+            loop {
+                let stopping = tokio::select! {
+                    _ = &mut stop_rx => true,
+                    _ = tokio::time::sleep(POLL_INTERVAL) => false,
+                };
+
+                if !stopping {
+                    if let Err(error) = uploads.upload_new(&upload).await {
+                        eprintln!("Failed to upload recording segments: {error}");
+                    }
+                    continue;
+                }
+
+                for attempt in 1..=FINAL_UPLOAD_ATTEMPTS {
+                    match uploads.upload_new(&upload).await {
+                        Ok(()) => break,
+                        Err(error) => eprintln!(
+                            "Failed to upload final recording segments (attempt {attempt}/{FINAL_UPLOAD_ATTEMPTS}): {error}"
+                        ),
+                    }
+
+                    // Backs off, so that a brief network outage doesn't use up every attempt.
+                    if attempt < FINAL_UPLOAD_ATTEMPTS {
+                        tokio::time::sleep(FINAL_RETRY_DELAY * 2u32.pow(attempt - 1)).await;
+                    }
+                }
+                break;
+            }
+        });
+
+        Self { stop_tx, task }
+    }
+
+    /// Stops uploading without publishing the remaining segments, for discarded recordings.
+    pub fn cancel(self) {
+        self.task.abort();
+    }
+
+    /// Uploads the remaining segments and the finished playlist. Must be called after the
+    /// pipeline has shut down, so the encoder has written its last segment.
+    pub async fn finish(self) {
+        self.stop_tx.send(()).ok();
+        self.task.await.ok();
+    }
+}
+
+struct PendingUploads {
+    dir: PathBuf,
+    playlist_path: PathBuf,
+    /// Where the files go in the bucket.
+    key_prefix: String,
+    uploaded: HashSet<String>,
+    last_playlist: Option<String>,
+}
+
+impl PendingUploads {
+    fn new(segments: &SegmentedOutput, key_prefix: String) -> Self {
+        Self {
+            dir: segments.dir.clone(),
+            playlist_path: segments.playlist_path(),
+            key_prefix,
+            uploaded: HashSet::new(),
+            last_playlist: None,
+        }
+    }
+
+    /// Uploads the segments listed since the last call in order, followed by the playlist.
+    /// `upload` is given the file's name, contents and key. A failed upload is retried by the
+    /// next call, without uploading anything listed after it in the meantime.
+    async fn upload_new<F, Fut>(&mut self, upload: &F) -> Result<(), String>
+    where
+        F: Fn(String, Vec<u8>, String) -> Fut,
+        Fut: Future<Output = Result<(), String>>,
+    {
+        // The playlist only appears once the first segment is complete.
+        let Ok(playlist) = tokio::fs::read_to_string(&self.playlist_path).await else {
+            return Ok(());
+        };
+
+        if self.last_playlist.as_ref() == Some(&playlist) {
+            return Ok(());
+        }
+
+        for file in playlist_files(&playlist) {
+            if self.uploaded.contains(file) {
+                continue;
+            }
+
+            let contents = tokio::fs::read(self.dir.join(file))
+                .await
+                .map_err(|e| format!("Failed to read segment {file}: {e}"))?;
+            upload(file.to_string(), contents, self.file_key(file)).await?;
+            self.uploaded.insert(file.to_string());
+        }
+
+        // Only publish the playlist once every segment it lists is available. The copy that
+        // was just read is uploaded, as the encoder may have extended the file since.
+        upload(
+            SegmentedOutput::PLAYLIST_NAME.to_string(),
+            playlist.clone().into_bytes(),
+            self.file_key(SegmentedOutput::PLAYLIST_NAME),
+        )
+        .await?;
+        self.last_playlist = Some(playlist);
+
+        Ok(())
+    }
+
+    fn file_key(&self, file: &str) -> String {
+        format!("{}/{file}", self.key_prefix)
+    }
+}
+
+/// Files referenced by a media playlist: its segments and, for fMP4, the init section.
+fn playlist_files(playlist: &str) -> impl Iterator<Item = &str> {
+    playlist.lines().filter_map(|line| {
+        let line = line.trim();
+
+        if let Some(map) = line.strip_prefix("#EXT-X-MAP:") {
+            return map
+                .split(',')
+                .find_map(|attribute| attribute.strip_prefix("URI="))
+                .map(|uri| uri.trim_matches('"'));
+        }
+
+        (!line.is_empty() && !line.starts_with('#')).then_some(line)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use axum::{body::Bytes, extract::State, http::StatusCode, http::Uri};
+    use cap_media::encoders::SegmentFormat;
+    use reqwest::multipart::Form;
+
+    use super::*;
+    use crate::upload::post_segment;
+
+    /// Stands in for the storage bucket, recording the key of every upload it accepts.
+    #[derive(Clone, Default)]
+    struct Bucket {
+        uploads: Arc<Mutex<Vec<String>>>,
+        /// Keys whose next upload fails.
+        failing: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Bucket {
+        fn take_uploads(&self) -> Vec<String> {
+            std::mem::take(&mut self.uploads.lock().unwrap())
+        }
+    }
+
+    async fn receive(State(bucket): State<Bucket>, uri: Uri, body: Bytes) -> StatusCode {
+        let key = uri.path().trim_start_matches('/').to_string();
+
+        let mut failing = bucket.failing.lock().unwrap();
+        if let Some(index) = failing.iter().position(|failing| *failing == key) {
+            failing.remove(index);
+            return StatusCode::INTERNAL_SERVER_ERROR;
+        }
+
+        let file_name = key.rsplit('/').next().unwrap();
+        assert!(
+            String::from_utf8_lossy(&body).contains(&format!("filename=\"{file_name}\"")),
+            "{key} was uploaded without its file"
+        );
+        bucket.uploads.lock().unwrap().push(key);
+        StatusCode::NO_CONTENT
+    }
+
+    async fn serve(bucket: Bucket) -> String {
+        let router = axum::Router::new().fallback(receive).with_state(bucket);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            axum::serve(listener, router.into_make_service())
+                .await
+                .unwrap();
+        });
+
+        url
+    }
+
+    fn write_playlist(segments: &SegmentedOutput, files: &[&str], finished: bool) {
+        let mut playlist = "#EXTM3U\n#EXT-X-VERSION:3\n#EXT-X-TARGETDURATION:2\n".to_string();
+        for file in files {
+            std::fs::write(segments.dir.join(file), format!("contents of {file}")).unwrap();
+            playlist.push_str(&format!("#EXTINF:2.000000,\n{file}\n"));
+        }
+        if finished {
+            playlist.push_str("#EXT-X-ENDLIST\n");
+        }
+        std::fs::write(segments.playlist_path(), playlist).unwrap();
+    }
+
+    #[tokio::test]
+    async fn segments_are_uploaded_in_order_and_retried() {
+        let bucket = Bucket::default();
+        let url = serve(bucket.clone()).await;
+        let upload = |file: String, contents: Vec<u8>, key: String| {
+            let upload_url = format!("{url}/{key}");
+            async move { post_segment(&upload_url, Form::new(), &file, contents).await }
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        let segments = SegmentedOutput {
+            dir: dir.path().to_owned(),
+            format: SegmentFormat::MpegTs,
+            segment_duration_secs: 2,
+            max_segments: None,
+        };
+        let mut uploads = PendingUploads::new(&segments, "user/video/combined-source".into());
+        let key = |file: &str| format!("user/video/combined-source/{file}");
+
+        // Nothing to do until the first segment is done
+        uploads.upload_new(&upload).await.unwrap();
+        assert!(bucket.take_uploads().is_empty());
+
+        write_playlist(&segments, &["segment_00000.ts", "segment_00001.ts"], false);
+        bucket.failing.lock().unwrap().push(key("segment_00001.ts"));
+        assert!(uploads.upload_new(&upload).await.is_err());
+        assert_eq!(bucket.take_uploads(), [key("segment_00000.ts")]);
+
+        uploads.upload_new(&upload).await.unwrap();
+        assert_eq!(
+            bucket.take_uploads(),
+            [key("segment_00001.ts"), key("stream.m3u8")]
+        );
+
+        // An unchanged playlist has nothing new
+        uploads.upload_new(&upload).await.unwrap();
+        assert!(bucket.take_uploads().is_empty());
+
+        write_playlist(
+            &segments,
+            &["segment_00000.ts", "segment_00001.ts", "segment_00002.ts"],
+            true,
+        );
+        uploads.upload_new(&upload).await.unwrap();
+        assert_eq!(
+            bucket.take_uploads(),
+            [key("segment_00002.ts"), key("stream.m3u8")]
+        );
+    }
+
+    #[test]
+    fn playlists_list_segments_and_the_init_section() {
+        let playlist = "#EXTM3U
+#EXT-X-VERSION:7
+#EXT-X-MAP:URI=\"init.mp4\"
+#EXTINF:2.000000,
+segment_00000.m4s
+
+#EXTINF:1.500000,
+segment_00001.m4s
+#EXT-X-ENDLIST
+";
+
+        assert_eq!(
+            playlist_files(playlist).collect::<Vec<_>>(),
+            ["init.mp4", "segment_00000.m4s", "segment_00001.m4s"]
+        );
+    }
+}
@@ -1,7 +1,7 @@
 use crate::windows::CapWindow;
 use crate::{
-    RecordingStarted, RecordingStopped, RequestNewScreenshot, RequestOpenSettings,
-    RequestStartRecording, RequestStopRecording,
+    RecordingStarted, RecordingStopped, RequestCancelRecording, RequestNewScreenshot,
+    RequestOpenSettings, RequestStartRecording, RequestStopRecording,
 };
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
@@ -17,6 +17,7 @@ use tauri_specta::Event;
 pub enum TrayItem {
     OpenCap,
     StartNewRecording,
+    CancelPendingRecording,
     TakeScreenshot,
     PreviousRecordings,
     PreviousScreenshots,
@@ -29,6 +30,7 @@ impl From<TrayItem> for MenuId {
         match value {
             TrayItem::OpenCap => "open_cap",
             TrayItem::StartNewRecording => "new_recording",
+            TrayItem::CancelPendingRecording => "cancel_pending_recording",
             TrayItem::TakeScreenshot => "take_screenshot",
             TrayItem::PreviousRecordings => "previous_recordings",
             TrayItem::PreviousScreenshots => "previous_screenshots",
@@ -44,6 +46,7 @@ impl From<MenuId> for TrayItem {
         match value.0.as_str() {
             "open_cap" => TrayItem::OpenCap,
             "new_recording" => TrayItem::StartNewRecording,
+            "cancel_pending_recording" => TrayItem::CancelPendingRecording,
             "take_screenshot" => TrayItem::TakeScreenshot,
             "previous_recordings" => TrayItem::PreviousRecordings,
             "previous_screenshots" => TrayItem::PreviousScreenshots,
@@ -74,6 +77,13 @@ pub fn create_tray(app: &AppHandle) -> tauri::Result<()> {
                 true,
                 None::<&str>,
             )?,
+            &MenuItem::with_id(
+                app,
+                TrayItem::CancelPendingRecording,
+                "Cancel Countdown or Schedule",
+                true,
+                None::<&str>,
+            )?,
             &MenuItem::with_id(
                 app,
                 TrayItem::TakeScreenshot,
@@ -119,6 +129,9 @@ pub fn create_tray(app: &AppHandle) -> tauri::Result<()> {
 
                     let _ = RequestStartRecording.emit(&app_handle);
                 }
+                TrayItem::CancelPendingRecording => {
+                    let _ = RequestCancelRecording.emit(&app_handle);
+                }
                 TrayItem::TakeScreenshot => {
                     let _ = RequestNewScreenshot.emit(&app_handle);
                 }
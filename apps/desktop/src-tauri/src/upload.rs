@@ -135,7 +135,7 @@ pub async fn upload_video(
         },
     )?;
 
-    let (upload_url, mut form) = presigned_s3_url(app, body).await?;
+    let (upload_url, mut form) = presigned_s3_url(app, &body).await?;
 
     let file_bytes = tokio::fs::read(&file_path)
         .await
@@ -242,7 +242,7 @@ pub async fn upload_image(app: &AppHandle, file_path: PathBuf) -> Result<Uploade
         },
     };
 
-    let (upload_url, mut form) = presigned_s3_url_image(app, body).await?;
+    let (upload_url, mut form) = presigned_s3_url(app, &body).await?;
 
     let file_content = tokio::fs::read(&file_path)
         .await
@@ -310,7 +310,7 @@ pub async fn upload_audio(app: &AppHandle, file_path: PathBuf) -> Result<Uploade
         },
     )?;
 
-    let (upload_url, mut form) = presigned_s3_url_audio(&app, body).await?;
+    let (upload_url, mut form) = presigned_s3_url(&app, &body).await?;
 
     let file_content = tokio::fs::read(&file_path)
         .await
@@ -318,6 +318,10 @@ pub async fn upload_audio(app: &AppHandle, file_path: PathBuf) -> Result<Uploade
 
     let mime_type = if file_name.ends_with(".mp3") {
         "audio/mpeg"
+    } else if file_name.ends_with(".m4a") {
+        "audio/mp4"
+    } else if file_name.ends_with(".ogg") {
+        "audio/ogg"
     } else {
         "audio/wav"
     };
@@ -398,15 +402,16 @@ pub async fn get_s3_config(app: &AppHandle, is_screenshot: bool) -> Result<S3Upl
     Ok(config)
 }
 
+/// Asks the server for a presigned POST to upload a file to, described by `body`.
 async fn presigned_s3_url(
     app: &AppHandle,
-    body: S3VideoUploadBody,
+    body: &impl Serialize,
 ) -> Result<(String, Form), String> {
     let response = app
         .authed_api_request(|client| {
             client
                 .post(web_api::make_url("/api/upload/signed"))
-                .json(&serde_json::json!(body))
+                .json(body)
         })
         .await
         .map_err(|e| format!("Failed to send request to Next.js handler: {}", e))?;
@@ -440,82 +445,6 @@ async fn presigned_s3_url(
     Ok((post_url, form))
 }
 
-async fn presigned_s3_url_image(
-    app: &AppHandle,
-    body: S3ImageUploadBody,
-) -> Result<(String, Form), String> {
-    let response = app
-        .authed_api_request(|client| {
-            client
-                .post(web_api::make_url("/api/upload/signed"))
-                .json(&serde_json::json!(body))
-        })
-        .await
-        .map_err(|e| format!("Failed to send request to Next.js handler: {}", e))?;
-
-    let presigned_post_data = response
-        .json::<serde_json::Value>()
-        .await
-        .map_err(|e| format!("Failed to deserialize server response: {}", e))?;
-
-    let fields = presigned_post_data["presignedPostData"]["fields"]
-        .as_object()
-        .ok_or("Fields object is missing or not an object")?;
-    let post_url = presigned_post_data["presignedPostData"]["url"]
-        .as_str()
-        .ok_or("URL is missing or not a string")?
-        .to_string();
-
-    let mut form = Form::new();
-
-    for (key, value) in fields.iter() {
-        let value_str = value
-            .as_str()
-            .ok_or(format!("Value for key '{}' is not a string", key))?;
-        form = form.text(key.to_string(), value_str.to_owned());
-    }
-
-    Ok((post_url, form))
-}
-
-async fn presigned_s3_url_audio(
-    app: &AppHandle,
-    body: S3AudioUploadBody,
-) -> Result<(String, Form), String> {
-    let response = app
-        .authed_api_request(|client| {
-            client
-                .post(web_api::make_url("/api/upload/signed"))
-                .json(&serde_json::json!(body))
-        })
-        .await
-        .map_err(|e| format!("Failed to send request to Next.js handler: {}", e))?;
-
-    let presigned_post_data = response
-        .json::<serde_json::Value>()
-        .await
-        .map_err(|e| format!("Failed to deserialize server response: {}", e))?;
-
-    let fields = presigned_post_data["presignedPostData"]["fields"]
-        .as_object()
-        .ok_or("Fields object is missing or not an object")?;
-    let post_url = presigned_post_data["presignedPostData"]["url"]
-        .as_str()
-        .ok_or("URL is missing or not a string")?
-        .to_string();
-
-    let mut form = Form::new();
-
-    for (key, value) in fields.iter() {
-        let value_str = value
-            .as_str()
-            .ok_or(format!("Value for key '{}' is not a string", key))?;
-        form = form.text(key.to_string(), value_str.to_owned());
-    }
-
-    Ok((post_url, form))
-}
-
 fn build_video_upload_body(
     path: &PathBuf,
     base: S3UploadBody,
@@ -601,10 +530,10 @@ pub async fn upload_individual_file(
 
     let (upload_url, mut form) = if is_audio {
         let audio_body = build_audio_upload_body(&file_path, base_upload_body)?;
-        presigned_s3_url_audio(app, audio_body).await?
+        presigned_s3_url(app, &audio_body).await?
     } else {
         let video_body = build_video_upload_body(&file_path, base_upload_body)?;
-        presigned_s3_url(app, video_body).await?
+        presigned_s3_url(app, &video_body).await?
     };
 
     let file_content = tokio::fs::read(&file_path)
@@ -642,6 +571,72 @@ pub async fn upload_individual_file(
     }
 }
 
+/// Uploads one file of an HLS stream that is still being recorded: a segment, the fMP4
+/// init section or the playlist itself.
+pub async fn upload_segment(
+    app: &AppHandle,
+    s3_config: &S3UploadMeta,
+    file_name: &str,
+    file_content: Vec<u8>,
+    file_key: String,
+) -> Result<(), String> {
+    let body = S3UploadBody {
+        user_id: s3_config.user_id.clone(),
+        file_key,
+        aws_bucket: s3_config.aws_bucket.clone(),
+        aws_region: s3_config.aws_region.clone(),
+    };
+
+    let (upload_url, form) = presigned_s3_url(app, &body).await?;
+
+    post_segment(&upload_url, form, file_name, file_content).await
+}
+
+/// Posts a file of an HLS stream to a presigned upload URL, along with the fields of `form`.
+pub async fn post_segment(
+    upload_url: &str,
+    mut form: Form,
+    file_name: &str,
+    file_content: Vec<u8>,
+) -> Result<(), String> {
+    let client = reqwest::Client::new();
+
+    let mime_type = if file_name.ends_with(".m3u8") {
+        "application/x-mpegURL"
+    } else if file_name.ends_with(".ts") {
+        "video/mp2t"
+    } else {
+        "video/mp4"
+    };
+
+    let file_part = reqwest::multipart::Part::bytes(file_content)
+        .file_name(file_name.to_string())
+        .mime_str(mime_type)
+        .map_err(|e| format!("Error setting MIME type: {}", e))?;
+    form = form.part("file", file_part);
+
+    let response = client
+        .post(upload_url)
+        .multipart(form)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to send upload file request: {}", e))?;
+
+    if response.status().is_success() {
+        return Ok(());
+    }
+
+    let status = response.status();
+    let error_body = response
+        .text()
+        .await
+        .unwrap_or_else(|_| "<no response body>".to_string());
+    Err(format!(
+        "Failed to upload segment. Status: {}. Body: {}",
+        status, error_body
+    ))
+}
+
 async fn prepare_screenshot_upload(
     app: &AppHandle,
     s3_config: &S3UploadMeta,
@@ -666,7 +661,7 @@ async fn prepare_screenshot_upload(
         },
     };
 
-    let (upload_url, mut form) = presigned_s3_url_image(app, body).await?;
+    let (upload_url, mut form) = presigned_s3_url(app, &body).await?;
 
     let compressed_image = compress_image(screenshot_path).await?;
 
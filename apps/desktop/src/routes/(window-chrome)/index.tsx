@@ -26,7 +26,12 @@ import {
   getPermissions,
   createVideoDevicesQuery,
 } from "~/utils/queries";
-import { type CaptureWindow, commands, events } from "~/utils/tauri";
+import {
+  type CaptureWindow,
+  type RecordingMode,
+  commands,
+  events,
+} from "~/utils/tauri";
 import {
   MenuItem,
   MenuItemList,
@@ -327,6 +332,23 @@ export default function () {
           </PopperContent>
         </KSelect.Portal>
       </KSelect>
+      <div class="flex flex-col gap-[0.25rem] items-stretch">
+        <label class="text-gray-400 text-[0.875rem]">Record</label>
+        <SwitchTab
+          value={options.data?.mode ?? "screen"}
+          disabled={isRecording()}
+          onChange={(mode) => {
+            if (!options.data) return;
+            setOptions({ ...options.data, mode: mode as RecordingMode });
+          }}
+        >
+          <SwitchTab.List>
+            <SwitchTab.Trigger value="screen">Screen</SwitchTab.Trigger>
+            <SwitchTab.Trigger value="cameraOnly">Camera</SwitchTab.Trigger>
+            <SwitchTab.Trigger value="audioOnly">Audio</SwitchTab.Trigger>
+          </SwitchTab.List>
+        </SwitchTab>
+      </div>
       <div class="flex flex-col gap-[0.25rem] items-stretch">
         <label class="text-gray-400 text-[0.875rem]">Camera</label>
         <Show when>
@@ -8,7 +8,11 @@ import { type Component, createRoot, For, Show } from "solid-js";
 import { Dynamic } from "solid-js/web";
 import { createWritableMemo } from "@solid-primitives/memo";
 
-import type { BackgroundSource, CursorType } from "~/utils/tauri";
+import type {
+  BackgroundSource,
+  CursorType,
+  DisplayLayout,
+} from "~/utils/tauri";
 import { useEditorContext } from "./context";
 import { ComingSoonTooltip, Field, Slider, Subfield, Toggle } from "./ui";
 import { DEFAULT_GRADIENT_FROM, DEFAULT_GRADIENT_TO } from "./projectConfig";
@@ -28,6 +32,12 @@ const BACKGROUND_SOURCES_LIST = [
   "gradient",
 ] satisfies Array<BackgroundSource["type"]>;
 
+const DISPLAY_LAYOUTS = {
+  sideBySide: "Side by side",
+  stacked: "Stacked",
+  pictureInPicture: "Picture in picture",
+} satisfies Record<DisplayLayout, string>;
+
 export function ConfigSidebar() {
   const { selectedTab, setSelectedTab, project, setProject, editorInstance } =
     useEditorContext();
@@ -320,6 +330,35 @@ export function ConfigSidebar() {
             </KTabs>
           </Field>
 
+          <Show
+            when={editorInstance.recordings.additional_displays.length > 0}
+          >
+            <Field name="Screens" icon={<IconCapImage />}>
+              <KTabs
+                value={project.displayLayout ?? "sideBySide"}
+                onChange={(v) =>
+                  setProject("displayLayout", v as DisplayLayout)
+                }
+              >
+                <KTabs.List class="flex flex-row items-center rounded-[0.5rem] relative border">
+                  <For each={Object.entries(DISPLAY_LAYOUTS)}>
+                    {([value, name]) => (
+                      <KTabs.Trigger
+                        class="flex-1 text-gray-400 py-1 z-10 ui-selected:text-gray-500 peer outline-none transition-colors duration-100"
+                        value={value}
+                      >
+                        {name}
+                      </KTabs.Trigger>
+                    )}
+                  </For>
+                  <KTabs.Indicator class="absolute flex p-px inset-0 transition-transform peer-focus-visible:outline outline-2 outline-blue-300 outline-offset-2 rounded-[0.6rem] overflow-hidden">
+                    <div class="bg-gray-100 flex-1" />
+                  </KTabs.Indicator>
+                </KTabs.List>
+              </KTabs>
+            </Field>
+          </Show>
+
           <ComingSoonTooltip>
             <Field name="Background Blur" icon={<IconCapBlur />}>
               <Slider
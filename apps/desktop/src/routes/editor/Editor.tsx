@@ -321,7 +321,7 @@ function Dialogs() {
                   size: dialog().size,
                 });
 
-                const display = editorInstance.recordings.display;
+                const display = editorInstance.frameSize;
 
                 const styles = createMemo(() => {
                   return {
@@ -380,8 +380,8 @@ function Dialogs() {
                           setCrop({
                             position: { x: 0, y: 0 },
                             size: {
-                              x: editorInstance.recordings.display.width,
-                              y: editorInstance.recordings.display.height,
+                              x: display.width,
+                              y: display.height,
                             },
                           })
                         }
@@ -534,8 +534,7 @@ function Dialogs() {
                                                     clamp(
                                                       original.size.x + diff.x,
                                                       MIN_SIZE,
-                                                      editorInstance.recordings
-                                                        .display.width -
+                                                      display.width -
                                                         crop.position.x
                                                     )
                                                   )
@@ -549,9 +548,7 @@ function Dialogs() {
                                                       original.position.x +
                                                         diff.x,
                                                       0,
-                                                      editorInstance.recordings
-                                                        .display.width -
-                                                        MIN_SIZE
+                                                      display.width - MIN_SIZE
                                                     )
                                                   )
                                                 );
@@ -562,8 +559,7 @@ function Dialogs() {
                                                     clamp(
                                                       original.size.x - diff.x,
                                                       MIN_SIZE,
-                                                      editorInstance.recordings
-                                                        .display.width
+                                                      display.width
                                                     )
                                                   )
                                                 );
@@ -577,8 +573,7 @@ function Dialogs() {
                                                     clamp(
                                                       original.size.y + diff.y,
                                                       MIN_SIZE,
-                                                      editorInstance.recordings
-                                                        .display.height -
+                                                      display.height -
                                                         crop.position.y
                                                     )
                                                   )
@@ -592,9 +587,7 @@ function Dialogs() {
                                                       original.position.y +
                                                         diff.y,
                                                       0,
-                                                      editorInstance.recordings
-                                                        .display.height -
-                                                        MIN_SIZE
+                                                      display.height - MIN_SIZE
                                                     )
                                                   )
                                                 );
@@ -605,8 +598,7 @@ function Dialogs() {
                                                     clamp(
                                                       original.size.y - diff.y,
                                                       MIN_SIZE,
-                                                      editorInstance.recordings
-                                                        .display.height
+                                                      display.height
                                                     )
                                                   )
                                                 );
@@ -151,9 +151,11 @@ function ExportButton() {
 
 function ShareButton() {
   const { videoId, project, presets } = useEditorContext();
-  const [meta, metaActions] = createResource(() =>
-    commands.getRecordingMeta(videoId, "recording")
-  );
+  const [meta, metaActions] = createResource(async () => {
+    const res = await commands.getRecordingMeta(videoId, "recording");
+    if (res.status !== "ok") throw new Error(res.error);
+    return res.data;
+  });
 
   const uploadVideo = createMutation(() => ({
     mutationFn: async () => {
@@ -71,7 +71,7 @@ export function Player() {
           <EditorButton
             leftIcon={<IconCapCrop />}
             onClick={() => {
-              const display = editorInstance.recordings.display;
+              const display = editorInstance.frameSize;
               setDialog({
                 open: true,
                 type: "crop",
@@ -41,8 +41,14 @@ export const [EditorContextProvider, useEditorContext] = createContextProvider(
         () => {
           trackStore(project);
         },
-        debounce(() => {
-          commands.setProjectConfig(editorInstanceContext.videoId, project);
+        debounce(async () => {
+          const res = await commands.setProjectConfig(
+            editorInstanceContext.videoId,
+            project
+          );
+          if (res.status !== "ok") console.error(res.error);
+          // The config was invalid and has been fixed, so show what was saved
+          else if (res.data) setProject(reconcile(res.data));
         }),
         { defer: true }
       )
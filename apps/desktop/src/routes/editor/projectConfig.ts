@@ -27,6 +27,7 @@ export const DEFAULT_PROJECT_CONFIG: ProjectConfiguration = {
     shadow: 50,
     size: 30,
   },
+  displayLayout: "sideBySide",
   audio: { mute: false, improve: false },
   cursor: { hideWhenIdle: false, size: 0, type: "pointer" },
   hotkeys: { show: false },
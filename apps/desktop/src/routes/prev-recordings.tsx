@@ -109,11 +109,14 @@ export default function () {
 
                 const recordingMeta = createQuery(() => ({
                   queryKey: ["recordingMeta", fileId],
-                  queryFn: () =>
-                    commands.getRecordingMeta(
+                  queryFn: async () => {
+                    const res = await commands.getRecordingMeta(
                       fileId,
                       isRecording ? "recording" : "screenshot"
-                    ),
+                    );
+                    if (res.status !== "ok") throw new Error(res.error);
+                    return res.data;
+                  },
                   enabled: true,
                 }));
 
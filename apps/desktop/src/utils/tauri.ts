@@ -53,6 +53,54 @@ async resumeRecording() : Promise<Result<null, string>> {
     else return { status: "error", error: e  as any };
 }
 },
+async addRecordingMarker(label: string | null) : Promise<Result<number, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("add_recording_marker", { label }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async cancelPendingRecording() : Promise<Result<null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("cancel_pending_recording") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async scheduleRecording(trigger: RecordingTrigger) : Promise<Result<null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("schedule_recording", { trigger }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async startReplayBuffer() : Promise<Result<null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("start_replay_buffer") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async stopReplayBuffer() : Promise<Result<null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("stop_replay_buffer") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async saveReplay() : Promise<Result<string, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("save_replay") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
 async takeScreenshot() : Promise<Result<null, string>> {
     try {
     return { status: "ok", data: await TAURI_INVOKE("take_screenshot") };
@@ -64,6 +112,14 @@ async takeScreenshot() : Promise<Result<null, string>> {
 async listCameras() : Promise<string[]> {
     return await TAURI_INVOKE("list_cameras");
 },
+async listCameraFormats(cameraLabel: string) : Promise<Result<CameraFormatInfo[], string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("list_camera_formats", { cameraLabel }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
 async listCaptureWindows() : Promise<CaptureWindow[]> {
     return await TAURI_INVOKE("list_capture_windows");
 },
@@ -179,8 +235,13 @@ async setPlayheadPosition(videoId: string, frameNumber: number) : Promise<void>
 async openInFinder(path: string) : Promise<void> {
     await TAURI_INVOKE("open_in_finder", { path });
 },
-async setProjectConfig(videoId: string, config: ProjectConfiguration) : Promise<void> {
-    await TAURI_INVOKE("set_project_config", { videoId, config });
+async setProjectConfig(videoId: string, config: ProjectConfiguration) : Promise<Result<ProjectConfiguration | null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("set_project_config", { videoId, config }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
 },
 async openEditor(id: string) : Promise<void> {
     await TAURI_INVOKE("open_editor", { id });
@@ -213,8 +274,13 @@ async uploadScreenshot(screenshotPath: string) : Promise<Result<UploadResult, st
     else return { status: "error", error: e  as any };
 }
 },
-async getRecordingMeta(id: string, fileType: string) : Promise<RecordingMeta> {
-    return await TAURI_INVOKE("get_recording_meta", { id, fileType });
+async getRecordingMeta(id: string, fileType: string) : Promise<Result<RecordingMeta, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("get_recording_meta", { id, fileType }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
 },
 async openFeedbackWindow() : Promise<void> {
     await TAURI_INVOKE("open_feedback_window");
@@ -317,34 +383,56 @@ async isCameraWindowOpen() : Promise<boolean> {
 
 
 export const events = __makeEvents__<{
+audioInputLevelChanged: AudioInputLevelChanged,
+audioInputSilent: AudioInputSilent,
+audioInputStatusChanged: AudioInputStatusChanged,
+cameraFeedChanged: CameraFeedChanged,
 currentRecordingChanged: CurrentRecordingChanged,
 editorStateChanged: EditorStateChanged,
+liveStreamStatusChanged: LiveStreamStatusChanged,
 newRecordingAdded: NewRecordingAdded,
 newScreenshotAdded: NewScreenshotAdded,
 recordingMetaChanged: RecordingMetaChanged,
+recordingCountdown: RecordingCountdown,
+recordingCountdownCancelled: RecordingCountdownCancelled,
 recordingOptionsChanged: RecordingOptionsChanged,
+recordingScheduleChanged: RecordingScheduleChanged,
 recordingStarted: RecordingStarted,
 recordingStopped: RecordingStopped,
 renderFrameEvent: RenderFrameEvent,
+replayBufferChanged: ReplayBufferChanged,
+requestCancelRecording: RequestCancelRecording,
 requestNewScreenshot: RequestNewScreenshot,
 requestOpenSettings: RequestOpenSettings,
 requestRestartRecording: RequestRestartRecording,
+requestSaveReplay: RequestSaveReplay,
 requestStartRecording: RequestStartRecording,
 requestStopRecording: RequestStopRecording,
 showCapturesPanel: ShowCapturesPanel
 }>({
+audioInputLevelChanged: "audio-input-level-changed",
+audioInputSilent: "audio-input-silent",
+audioInputStatusChanged: "audio-input-status-changed",
+cameraFeedChanged: "camera-feed-changed",
 currentRecordingChanged: "current-recording-changed",
 editorStateChanged: "editor-state-changed",
+liveStreamStatusChanged: "live-stream-status-changed",
 newRecordingAdded: "new-recording-added",
 newScreenshotAdded: "new-screenshot-added",
 recordingMetaChanged: "recording-meta-changed",
+recordingCountdown: "recording-countdown",
+recordingCountdownCancelled: "recording-countdown-cancelled",
 recordingOptionsChanged: "recording-options-changed",
+recordingScheduleChanged: "recording-schedule-changed",
 recordingStarted: "recording-started",
 recordingStopped: "recording-stopped",
 renderFrameEvent: "render-frame-event",
+replayBufferChanged: "replay-buffer-changed",
+requestCancelRecording: "request-cancel-recording",
 requestNewScreenshot: "request-new-screenshot",
 requestOpenSettings: "request-open-settings",
 requestRestartRecording: "request-restart-recording",
+requestSaveReplay: "request-save-replay",
 requestStartRecording: "request-start-recording",
 requestStopRecording: "request-stop-recording",
 showCapturesPanel: "show-captures-panel"
@@ -358,31 +446,49 @@ showCapturesPanel: "show-captures-panel"
 
 export type AspectRatio = "wide" | "vertical" | "square" | "classic" | "tall"
 export type Audio = { duration: number; sample_rate: number; channels: number }
+export type AudioCodec = "mp3" | "aac" | "opus" | "pcm"
 export type AudioConfiguration = { mute: boolean; improve: boolean }
-export type AudioMeta = { path: string }
+export type AudioDrift = { sample_rate: number; expected_duration: number; actual_duration: number; ppm: number }
+export type AudioInputLevelChanged = { levels: AudioLevels }
+export type AudioInputSilent = { device: string }
+export type AudioInputStatus = { state: "disconnected"; device: string } | { state: "reconnected"; device: string } | { state: "fellBack"; from: string; to: string }
+export type AudioInputStatusChanged = { status: AudioInputStatus }
+export type AudioLevels = { rms: number; peak: number }
+export type AudioMeta = { path: string; drift?: AudioDrift | null }
 export type AuthStore = { token: string; expires: number; plan: Plan | null }
 export type BackgroundConfiguration = { source: BackgroundSource; blur: number; padding: number; rounding: number; inset: number; crop: Crop | null }
 export type BackgroundSource = { type: "wallpaper"; id: number } | { type: "image"; path: string | null } | { type: "color"; value: [number, number, number] } | { type: "gradient"; from: [number, number, number]; to: [number, number, number]; angle?: number }
 export type Bounds = { x: number; y: number; width: number; height: number }
 export type CameraConfiguration = { hide: boolean; mirror: boolean; position: CameraPosition; rounding: number; shadow: number; size: number }
+export type CameraFeedChanged = { event: CameraFeedEvent }
+export type CameraFeedEvent = { state: "disconnected"; camera: string } | { state: "reconnected"; camera: string }
+export type CameraFormatInfo = { width: number; height: number; frameFormat: CameraFrameFormat; frameRate: number }
+export type CameraFormatPreference = { kind: "default" } | { kind: "highestResolution" } | { kind: "bestForFrameRate"; fps: number } | { kind: "exact"; format: CameraFormatInfo }
+export type CameraFrameFormat = "mjpeg" | "yuyv" | "nv12" | "gray" | "rawRgb"
 export type CameraMeta = { path: string }
 export type CameraPosition = { x: CameraXPosition; y: CameraYPosition }
 export type CameraXPosition = "left" | "center" | "right"
 export type CameraYPosition = "top" | "bottom"
+export type CameraSwitch = { time: number; camera: string }
 export type CaptureWindow = { id: number; name: string; bounds: Bounds }
 export type Crop = { position: XY<number>; size: XY<number> }
 export type CurrentRecordingChanged = JsonValue<InProgressRecording | null>
 export type CursorConfiguration = { hideWhenIdle: boolean; size: number; type: CursorType }
 export type CursorType = "pointer" | "circle"
 export type Display = { path: string }
+export type DisplayLayout = "sideBySide" | "stacked" | "pictureInPicture"
 export type EditorStateChanged = { playhead_position: number }
-export type GeneralSettingsStore = { upload_individual_files: boolean; open_editor_after_recording: boolean; hide_dock_icon?: boolean; auto_create_shareable_link?: boolean }
+export type EncoderPreset = "fastest" | "fast" | "balanced" | "quality"
+export type EncoderSettings = { codec?: VideoCodec; preset?: EncoderPreset; rateControl?: RateControl; keyframeIntervalSecs?: number; threads?: number | null }
+export type FrameSize = { width: number; height: number }
+export type GeneralSettingsStore = { upload_individual_files: boolean; open_editor_after_recording: boolean; hide_dock_icon?: boolean; auto_create_shareable_link?: boolean; encoder_settings?: EncoderSettings; audio_codec?: AudioCodec; live_stream?: StreamOutput | null; recording_countdown_secs?: number | null; replay_duration_secs?: number | null }
 export type Hotkey = { code: string; meta: boolean; ctrl: boolean; alt: boolean; shift: boolean }
-export type HotkeyAction = "startRecording" | "stopRecording" | "restartRecording" | "takeScreenshot"
+export type HotkeyAction = "startRecording" | "stopRecording" | "restartRecording" | "takeScreenshot" | "saveReplay" | "cancelRecording"
 export type HotkeysConfiguration = { show: boolean }
 export type HotkeysStore = { hotkeys: { [key in HotkeyAction]: Hotkey } }
-export type InProgressRecording = { recordingDir: string; displaySource: ScreenCaptureTarget; segments: number[] }
+export type InProgressRecording = { recordingDir: string; displaySource: ScreenCaptureTarget | null; segments: number[] }
 export type JsonValue<T> = [T]
+export type LiveStreamStatusChanged = { status: StreamStatus }
 export type NewRecordingAdded = { path: string }
 export type NewScreenshotAdded = { path: string }
 export type OSPermission = "screenRecording" | "camera" | "microphone" | "accessibility"
@@ -390,31 +496,48 @@ export type OSPermissionStatus = "notNeeded" | "empty" | "granted" | "denied"
 export type OSPermissionsCheck = { screenRecording: OSPermissionStatus; microphone: OSPermissionStatus; camera: OSPermissionStatus; accessibility: OSPermissionStatus }
 export type Plan = { upgraded: boolean; last_checked: number }
 export type PreCreatedVideo = { id: string; link: string; config: S3UploadMeta }
-export type ProjectConfiguration = { aspectRatio: AspectRatio | null; background: BackgroundConfiguration; camera: CameraConfiguration; audio: AudioConfiguration; cursor: CursorConfiguration; hotkeys: HotkeysConfiguration; timeline?: TimelineConfiguration | null }
-export type ProjectRecordings = { display: Video; camera: Video | null; audio: Audio | null }
-export type RecordingMeta = { pretty_name: string; sharing?: SharingMeta | null; display: Display; camera?: CameraMeta | null; audio?: AudioMeta | null; segments?: RecordingSegment[] }
+export type ProjectConfiguration = { aspectRatio: AspectRatio | null; background: BackgroundConfiguration; camera: CameraConfiguration; displayLayout?: DisplayLayout; audio: AudioConfiguration; cursor: CursorConfiguration; hotkeys: HotkeysConfiguration; timeline?: TimelineConfiguration | null }
+export type ProjectRecordings = { display: Video | null; additional_displays: Video[]; camera: Video | null; audio: Audio | null }
+export type RateControl = { mode: "codecDefault" } | { mode: "crf"; value: number } | { mode: "bitrate"; kbps: number }
+export type ReconnectPolicy = { initialDelayMs: number; maxDelayMs: number; maxAttempts?: number | null }
+export type RecordingCountdown = { secs_left: number }
+export type RecordingCountdownCancelled = null
+export type RecordingLimits = { maxDurationSecs?: number | null; maxSizeMb?: number | null; minFreeDiskMb?: number | null }
+export type RecordingMarker = { time: number; label: string }
+export type RecordingMeta = { pretty_name: string; sharing?: SharingMeta | null; display?: Display | null; additional_displays?: Display[]; camera?: CameraMeta | null; audio?: AudioMeta | null; segments?: RecordingSegment[]; markers?: RecordingMarker[]; camera_switches?: CameraSwitch[] }
 export type RecordingMetaChanged = { id: string }
-export type RecordingOptions = { captureTarget: ScreenCaptureTarget; cameraLabel: string | null; audioInputName: string | null }
+export type RecordingMode = "screen" | "cameraOnly" | "audioOnly"
+export type RecordingOptions = { mode?: RecordingMode; captureTarget: ScreenCaptureTarget; additionalCaptureTargets?: ScreenCaptureTarget[]; cameraLabel: string | null; cameraFormat?: CameraFormatPreference; audioInputName: string | null; redactions?: RedactionRegion[]; limits?: RecordingLimits }
 export type RecordingOptionsChanged = null
+export type RecordingScheduleChanged = { trigger: RecordingTrigger | null }
 export type RecordingSegment = { start: number; end: number }
 export type RecordingStarted = null
 export type RecordingStopped = { path: string }
+export type RecordingTrigger = { kind: "at"; unixTimeMs: number } | { kind: "windowAppears"; name: string }
+export type RedactionRegion = { bounds: Bounds; style?: RedactionStyle }
+export type RedactionStyle = { kind: "pixelate"; blockSize: number } | { kind: "fill"; color: [number, number, number] }
 export type RenderFrameEvent = { frame_number: number }
 export type RenderProgress = { type: "Starting"; total_frames: number } | { type: "EstimatedTotalFrames"; total_frames: number } | { type: "FrameRendered"; current_frame: number }
+export type ReplayBufferChanged = { running: boolean }
+export type RequestCancelRecording = null
 export type RequestNewScreenshot = null
 export type RequestOpenSettings = { page: string }
 export type RequestRestartRecording = null
+export type RequestSaveReplay = null
 export type RequestStartRecording = null
 export type RequestStopRecording = null
 export type S3UploadMeta = { id: string; user_id: string; aws_region: string; aws_bucket: string }
 export type ScreenCaptureTarget = ({ variant: "window" } & CaptureWindow) | { variant: "screen" }
-export type SerializedEditorInstance = { framesSocketUrl: string; recordingDuration: number; savedProjectConfig: ProjectConfiguration; recordings: ProjectRecordings; path: string; prettyName: string }
+export type SerializedEditorInstance = { framesSocketUrl: string; recordingDuration: number; savedProjectConfig: ProjectConfiguration; recordings: ProjectRecordings; frameSize: FrameSize; path: string; prettyName: string }
 export type SharingMeta = { id: string; link: string }
 export type ShowCapturesPanel = null
+export type StreamOutput = { url: string; maxBitrateKbps?: number | null; reconnect?: ReconnectPolicy }
+export type StreamStatus = { state: "connecting"; attempt: number } | { state: "connected" } | { state: "disconnected"; reason: string; retryInMs: number } | { state: "gaveUp"; reason: string }
 export type TimelineConfiguration = { segments: TimelineSegment[] }
 export type TimelineSegment = { timescale: number; start: number; end: number }
 export type UploadResult = { Success: string } | "NotAuthenticated" | "PlanCheckFailed" | "UpgradeRequired"
 export type Video = { duration: number; width: number; height: number; fps: number }
+export type VideoCodec = "h264" | "h265" | "vp9" | "av1" | "lossless"
 export type VideoType = "screen" | "output"
 export type XY<T> = { x: T; y: T }
 
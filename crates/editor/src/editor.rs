@@ -1,7 +1,7 @@
 use std::{sync::Arc, time::Instant};
 
 use cap_project::{BackgroundSource, ProjectConfiguration};
-use cap_rendering::{decoder::DecodedFrame, produce_frame, ProjectUniforms, RenderVideoConstants};
+use cap_rendering::{produce_frame, ProjectUniforms, RecordingFrames, RenderVideoConstants};
 use tokio::{
     sync::{mpsc, oneshot},
     task::JoinHandle,
@@ -99,8 +99,7 @@ impl EditorHandle {
 
 pub enum RendererMessage {
     RenderFrame {
-        screen_frame: DecodedFrame,
-        camera_frame: Option<DecodedFrame>,
+        frames: RecordingFrames,
         background: BackgroundSource,
         uniforms: ProjectUniforms,
         finished: oneshot::Sender<()>,
@@ -145,8 +144,7 @@ impl Renderer {
             while let Some(msg) = self.rx.recv().await {
                 match msg {
                     RendererMessage::RenderFrame {
-                        screen_frame,
-                        camera_frame,
+                        frames,
                         background,
                         uniforms,
                         finished,
@@ -166,8 +164,7 @@ impl Renderer {
                             let time = Instant::now();
                             let frame = produce_frame(
                                 &render_constants,
-                                &screen_frame,
-                                &camera_frame,
+                                &frames,
                                 cap_rendering::Background::from(background),
                                 &uniforms,
                             )
@@ -208,16 +205,14 @@ impl RendererHandle {
 
     pub async fn render_frame(
         &self,
-        screen_frame: DecodedFrame,
-        camera_frame: Option<DecodedFrame>,
+        frames: RecordingFrames,
         background: BackgroundSource,
         uniforms: ProjectUniforms,
     ) {
         let (finished_tx, finished_rx) = oneshot::channel();
 
         self.send(RendererMessage::RenderFrame {
-            screen_frame,
-            camera_frame,
+            frames,
             background,
             uniforms,
             finished: finished_tx,
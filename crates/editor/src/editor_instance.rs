@@ -3,9 +3,11 @@ use crate::editor;
 use crate::playback::{self, PlaybackHandle};
 use crate::project_recordings::ProjectRecordings;
 use cap_ffmpeg::FFmpeg;
-use cap_project::{ProjectConfiguration, RecordingMeta};
+use cap_project::{LoadResult, ProjectConfiguration, ProjectError, RecordingMeta};
 use cap_rendering::decoder::AsyncVideoDecoder;
-use cap_rendering::{ProjectUniforms, RecordingDecoders, RenderOptions, RenderVideoConstants};
+use cap_rendering::{
+    ProjectUniforms, RecordingDecoders, RenderOptions, RenderVideoConstants, Waveform,
+};
 use std::ops::Deref;
 use std::sync::Mutex as StdMutex;
 use std::{path::PathBuf, sync::Arc};
@@ -37,7 +39,7 @@ impl EditorInstance {
         projects_path: PathBuf,
         video_id: String,
         on_state_change: impl Fn(&EditorState) + Send + Sync + 'static,
-    ) -> Arc<Self> {
+    ) -> Result<Arc<Self>, ProjectError> {
         let project_path = projects_path.join(format!(
             "{}{}",
             video_id,
@@ -50,21 +52,36 @@ impl EditorInstance {
 
         if !project_path.exists() {
             println!("Video path {} not found!", project_path.display());
-            // return Err(format!("Video path {} not found!", path.display()));
-            panic!("Video path {} not found!", project_path.display());
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("Video path {} not found", project_path.display()),
+            )
+            .into());
         }
 
-        let meta = cap_project::RecordingMeta::load_for_project(&project_path).unwrap();
+        let meta = cap_project::RecordingMeta::load_for_project(&project_path)?;
 
         let recordings = ProjectRecordings::new(&meta);
 
         let render_options = RenderOptions {
-            screen_size: (recordings.display.width, recordings.display.height),
+            screen_size: recordings.display.as_ref().map(|d| (d.width, d.height)),
+            additional_screen_sizes: recordings
+                .additional_displays
+                .iter()
+                .map(|d| (d.width, d.height))
+                .collect(),
             camera_size: recordings.camera.as_ref().map(|c| (c.width, c.height)),
         };
 
-        let screen_decoder =
-            AsyncVideoDecoder::spawn(project_path.join(&meta.display.path).clone());
+        let screen_decoder = meta
+            .display
+            .as_ref()
+            .map(|display| AsyncVideoDecoder::spawn(project_path.join(&display.path).clone()));
+        let additional_screen_decoders = meta
+            .additional_displays
+            .iter()
+            .map(|display| AsyncVideoDecoder::spawn(project_path.join(&display.path).clone()))
+            .collect();
         let camera_decoder = meta
             .camera
             .as_ref()
@@ -102,6 +119,15 @@ impl EditorInstance {
                 }
             });
 
+        // Audio-only projects show the audio instead
+        let waveform = audio
+            .as_ref()
+            .zip(recordings.audio)
+            .filter(|_| screen_decoder.is_none() && camera_decoder.is_none())
+            .map(|(audio, recording)| {
+                Waveform::new(audio.buffer.clone(), recording.channels, audio.sample_rate)
+            });
+
         let (frame_tx, frame_rx) = tokio::sync::mpsc::unbounded_channel();
 
         let (ws_port, ws_shutdown) = create_frames_ws(frame_rx).await;
@@ -112,15 +138,32 @@ impl EditorInstance {
 
         let (preview_tx, preview_rx) = watch::channel(None);
 
-        let project_config = std::fs::read_to_string(project_path.join("project-config.json"))
-            .ok()
-            .and_then(|s| serde_json::from_str(&s).ok())
-            .unwrap_or_default();
+        let project_config = match ProjectConfiguration::load(&project_path) {
+            LoadResult::Loaded(config) => config,
+            LoadResult::Migrated {
+                value,
+                from_version,
+            } => {
+                println!("Migrated project config from version {from_version}");
+                value
+            }
+            LoadResult::Missing => ProjectConfiguration::default(),
+            // The original is backed up, so editing can start over from the defaults.
+            LoadResult::Corrupt(error) => {
+                eprintln!("Project config is unreadable, using the defaults: {error}");
+                ProjectConfiguration::default()
+            }
+        };
 
         let this = Arc::new(Self {
             id: video_id,
             project_path,
-            decoders: RecordingDecoders::new(screen_decoder, camera_decoder),
+            decoders: RecordingDecoders::new(
+                screen_decoder,
+                additional_screen_decoders,
+                camera_decoder,
+                waveform,
+            ),
             recordings,
             ws_port,
             renderer,
@@ -140,11 +183,11 @@ impl EditorInstance {
         this.state.lock().await.preview_task =
             Some(this.clone().spawn_preview_renderer(preview_rx));
 
-        this
+        Ok(this)
     }
 
-    pub fn meta(&self) -> RecordingMeta {
-        RecordingMeta::load_for_project(&self.project_path).unwrap()
+    pub fn meta(&self) -> Result<RecordingMeta, ProjectError> {
+        RecordingMeta::load_for_project(&self.project_path)
     }
 
     pub async fn dispose(&self) {
@@ -212,7 +255,7 @@ impl EditorInstance {
                 renderer: self.renderer.clone(),
                 render_constants: self.render_constants.clone(),
                 decoders: self.decoders.clone(),
-                recordings: self.recordings,
+                recordings: self.recordings.clone(),
                 start_frame_number,
                 project: self.project_config.0.subscribe(),
             }
@@ -271,16 +314,14 @@ impl EditorInstance {
                     continue;
                 };
 
-                let Some((screen_frame, camera_frame)) =
-                    self.decoders.get_frames((time * FPS as f64) as u32).await
+                let Some(frames) = self.decoders.get_frames((time * FPS as f64) as u32).await
                 else {
                     continue;
                 };
 
                 self.renderer
                     .render_frame(
-                        screen_frame,
-                        camera_frame,
+                        frames,
                         project.background.source.clone(),
                         ProjectUniforms::new(&self.render_constants, &project),
                     )
@@ -92,15 +92,14 @@ impl Playback {
                     _ = stop_rx.changed() => {
                        break;
                     },
-                    Some((screen_frame, camera_frame)) = self.decoders.get_frames((time * FPS as f64) as u32) => {
+                    Some(frames) = self.decoders.get_frames((time * FPS as f64) as u32) => {
                         // println!("decoded frame in {:?}", debug.elapsed());
                         let uniforms = ProjectUniforms::new(&self.render_constants, &project);
 
                         self
                             .renderer
                             .render_frame(
-                                screen_frame,
-                                camera_frame,
+                                frames,
                                 project.background.source.clone(),
                                 uniforms.clone()
                             )
@@ -1,6 +1,7 @@
 use std::path::PathBuf;
 
-use cap_project::RecordingMeta;
+use cap_project::{RecordingMeta, RecordingProperties, XY};
+use cap_rendering::WAVEFORM_SIZE;
 use serde::Serialize;
 use specta::Type;
 
@@ -60,16 +61,26 @@ impl Audio {
     }
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Type)]
+#[derive(Debug, Clone, Serialize, Type)]
 pub struct ProjectRecordings {
-    pub display: Video,
+    /// `None` for camera-only and audio-only recordings.
+    pub display: Option<Video>,
+    pub additional_displays: Vec<Video>,
     pub camera: Option<Video>,
     pub audio: Option<Audio>,
 }
 
 impl ProjectRecordings {
     pub fn new(meta: &RecordingMeta) -> Self {
-        let display = Video::new(&meta.project_path.join(&meta.display.path));
+        let display = meta
+            .display
+            .as_ref()
+            .map(|display| Video::new(&meta.project_path.join(&display.path)));
+        let additional_displays = meta
+            .additional_displays
+            .iter()
+            .map(|display| Video::new(&meta.project_path.join(&display.path)))
+            .collect();
         let camera = meta
             .camera
             .as_ref()
@@ -81,6 +92,7 @@ impl ProjectRecordings {
 
         ProjectRecordings {
             display,
+            additional_displays,
             camera,
             audio,
         }
@@ -88,7 +100,7 @@ impl ProjectRecordings {
 
     pub fn duration(&self) -> f64 {
         let mut duration_ns = [
-            Some(self.display.duration),
+            self.display.as_ref().map(|s| s.duration),
             self.camera.as_ref().map(|s| s.duration),
             self.audio.as_ref().map(|s| s.duration),
         ]
@@ -96,6 +108,23 @@ impl ProjectRecordings {
         .flatten()
         .collect::<Vec<_>>();
         duration_ns.sort_by(|a, b| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
-        duration_ns[0]
+        duration_ns.first().copied().unwrap_or_default()
+    }
+}
+
+impl RecordingProperties for ProjectRecordings {
+    /// Matches `RenderOptions::frame_size`.
+    fn frame_size(&self) -> XY<u32> {
+        let (x, y) = self
+            .display
+            .or(self.camera)
+            .map(|video| (video.width, video.height))
+            .unwrap_or(WAVEFORM_SIZE);
+
+        XY { x, y }
+    }
+
+    fn duration(&self) -> f64 {
+        ProjectRecordings::duration(self)
     }
 }
@@ -66,6 +66,21 @@ impl AudioInfo {
         }
     }
 
+    /// Describes what `decoder` produces. Frames are timestamped in microseconds like any other
+    /// source's.
+    pub fn from_decoder(decoder: &ffmpeg::decoder::Audio) -> Self {
+        Self {
+            sample_format: decoder.format(),
+            sample_rate: decoder.rate(),
+            channels: decoder.channels().into(),
+            time_base: FFRational(1, 1_000_000),
+            buffer_size: match decoder.frame_size() {
+                0 => 1024,
+                frame_size => frame_size,
+            },
+        }
+    }
+
     pub fn sample_size(&self) -> usize {
         self.sample_format.bytes() * self.channels
     }
@@ -74,6 +89,17 @@ impl AudioInfo {
         self.sample_rate.try_into().unwrap()
     }
 
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    pub fn with_rate(&self, sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            ..*self
+        }
+    }
+
     pub fn channel_layout(&self) -> ChannelLayout {
         // TODO: Something other than panic. Pretty much all mics I know are either mono or stereo though.
         // Also need to test the audio data capture with a stereo mic at some point.
@@ -124,6 +150,18 @@ impl VideoInfo {
         }
     }
 
+    /// Describes what `decoder` produces, with the stream's average frame rate. Frames are
+    /// timestamped in microseconds like any other source's.
+    pub fn from_decoder(decoder: &ffmpeg::decoder::Video, frame_rate: FFRational) -> Self {
+        Self {
+            pixel_format: decoder.format(),
+            width: decoder.width(),
+            height: decoder.height(),
+            time_base: FFRational(1, 1_000_000),
+            frame_rate,
+        }
+    }
+
     pub fn scaled(&self, width: u32, fps: u32) -> Self {
         let (width, height) = match self.width <= width {
             true => (self.width, self.height),
@@ -0,0 +1,1113 @@
+//! Serializable descriptions of recording pipelines.
+//!
+//! A [`PipelineDescription`] names the sources, filters and encoders of a pipeline along with
+//! the edges between them, so that recording profiles can be loaded from a file instead of being
+//! assembled in code. The pipeline builder only supports linear paths, so every source must lead
+//! through zero or more filters into exactly one encoder.
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+use crate::{
+    data::{AudioInfo, FFVideo, Pixel, VideoInfo},
+    encoders::{
+        AudioCodec, AudioEncoder, EncoderSettings, H264Encoder, LiveStream, Output, ReplayOptions,
+        SegmentedOutput, StreamOutput,
+    },
+    feeds::CameraFeed,
+    filters::{
+        AudioDriftCorrector, AudioFilter, DriftTracker, RedactionRegion, VideoFilter,
+        VideoFilterOp, VideoRedactor,
+    },
+    pipeline::{
+        builder::PipelineBuilder,
+        clock::{CloneFrom, TimeSource},
+        task::PipelineSourceTask,
+        Pipeline, SynchronisedClock,
+    },
+    sources::{AudioInputSource, CameraSource, ScreenCaptureSource, ScreenCaptureTarget},
+    MediaError,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PipelineDescription {
+    pub nodes: Vec<NodeDescription>,
+    #[serde(default)]
+    pub edges: Vec<EdgeDescription>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeDescription {
+    pub name: String,
+    #[serde(flatten)]
+    pub kind: NodeKind,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase",
+    tag = "kind"
+)]
+pub enum NodeKind {
+    ScreenCapture {
+        #[serde(default)]
+        fps: Option<u32>,
+    },
+    Microphone,
+    Camera,
+    /// Scales video down to at most `max_width` (keeping the aspect ratio) and converts it to `fps`.
+    ScaleVideo {
+        max_width: u32,
+        fps: u32,
+    },
+    /// Applies a list of operations, e.g. to crop or rotate a source. The result is always
+    /// converted to YUV 4:2:0 for the encoder.
+    VideoFilter {
+        ops: Vec<VideoFilterOp>,
+    },
+    ResampleAudio {
+        sample_rate: u32,
+    },
+    /// A raw FFmpeg audio filter spec. The filter must not change the sample format or rate.
+    AudioFilter {
+        spec: String,
+    },
+    /// Measures how far the audio device drifts from the pipeline clock and resamples
+    /// the stream to compensate.
+    CorrectDrift,
+    H264Encoder {
+        output: PathBuf,
+    },
+    /// Shorthand for an `audioEncoder` using MP3.
+    Mp3Encoder {
+        output: PathBuf,
+    },
+    AudioEncoder {
+        #[serde(default)]
+        codec: AudioCodec,
+        output: PathBuf,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EdgeDescription {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SourceKind {
+    Screen,
+    Microphone,
+    Camera,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MediaKind {
+    Video,
+    Audio,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Role {
+    Source,
+    Filter,
+    Encoder,
+}
+
+impl NodeKind {
+    fn role(&self) -> Role {
+        match self {
+            Self::ScreenCapture { .. } | Self::Microphone | Self::Camera => Role::Source,
+            Self::ScaleVideo { .. }
+            | Self::VideoFilter { .. }
+            | Self::ResampleAudio { .. }
+            | Self::AudioFilter { .. }
+            | Self::CorrectDrift => Role::Filter,
+            Self::H264Encoder { .. } | Self::Mp3Encoder { .. } | Self::AudioEncoder { .. } => {
+                Role::Encoder
+            }
+        }
+    }
+
+    fn media(&self) -> MediaKind {
+        match self {
+            Self::ScreenCapture { .. }
+            | Self::Camera
+            | Self::ScaleVideo { .. }
+            | Self::VideoFilter { .. } => MediaKind::Video,
+            Self::H264Encoder { .. } => MediaKind::Video,
+            Self::Microphone
+            | Self::ResampleAudio { .. }
+            | Self::AudioFilter { .. }
+            | Self::CorrectDrift => MediaKind::Audio,
+            Self::Mp3Encoder { .. } | Self::AudioEncoder { .. } => MediaKind::Audio,
+        }
+    }
+
+    fn source_kind(&self) -> Option<SourceKind> {
+        match self {
+            Self::ScreenCapture { .. } => Some(SourceKind::Screen),
+            Self::Microphone => Some(SourceKind::Microphone),
+            Self::Camera => Some(SourceKind::Camera),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum DescriptionError {
+    #[error("Could not read the file: {0}")]
+    Io(std::io::Error),
+
+    #[error("{0}")]
+    Parse(serde_json::Error),
+
+    #[error("The pipeline description does not contain any sources")]
+    NoSources,
+
+    #[error("Node {0} is defined more than once")]
+    DuplicateNode(String),
+
+    #[error("Only one {0:?} source can be used in a pipeline")]
+    DuplicateSource(SourceKind),
+
+    #[error("Edge refers to unknown node {0}")]
+    UnknownNode(String),
+
+    #[error("{0} is a source and cannot receive input")]
+    InputToSource(String),
+
+    #[error("{0} is an encoder and cannot produce output")]
+    OutputFromEncoder(String),
+
+    #[error("{0} has more than one input")]
+    MultipleInputs(String),
+
+    #[error("{0} has more than one output")]
+    MultipleOutputs(String),
+
+    #[error("{0} does not lead into an encoder")]
+    Unterminated(String),
+
+    #[error("{0} is not connected to any source")]
+    Disconnected(String),
+
+    #[error("Cannot connect {from} to {to}: they handle different kinds of media")]
+    MediaMismatch { from: String, to: String },
+
+    #[error("{0} must pass through a scaleVideo or videoFilter node before its encoder")]
+    UnconvertedVideo(String),
+}
+
+/// Everything a description needs from the outside world to instantiate its sources.
+pub struct PipelineInputs<'a> {
+    /// Each target gets its own instance of the screen capture path, written next to the
+    /// description's output with a number appended, e.g. `display-2.mp4`. The first is the
+    /// main screen, which is the only one redacted, segmented and streamed. Empty for
+    /// recordings without the screen, e.g. camera-only ones.
+    pub capture_targets: &'a [ScreenCaptureTarget],
+    pub audio_input_name: Option<&'a String>,
+    pub camera_feed: Option<&'a CameraFeed>,
+    /// Relative encoder outputs are resolved against this directory.
+    pub output_dir: &'a Path,
+    /// Applied to every video encoder in the pipeline.
+    pub encoder_settings: &'a EncoderSettings,
+    /// When set, the screen capture is also written as HLS segments so it can be uploaded
+    /// while recording.
+    pub screen_segments: Option<&'a SegmentedOutput>,
+    /// When set, the screen capture is also streamed live to an RTMP or SRT server, along
+    /// with the microphone if the stream can carry its codec.
+    pub screen_stream: Option<&'a StreamOutput>,
+    /// Regions of the screen capture to hide before any filter or encoder sees them. Only
+    /// allowed with a single capture target.
+    pub screen_redactions: &'a [RedactionRegion],
+    /// When set, the pipeline runs as a replay buffer: every encoder only writes a ring of
+    /// segments, and the screen segments and stream above are ignored.
+    pub replay: Option<&'a ReplayOptions>,
+}
+
+impl PipelineInputs<'_> {
+    fn check(&self) -> Result<(), MediaError> {
+        if self.capture_targets.len() > ADDITIONAL_SCREEN_TAGS.len() + 1 {
+            return Err(MediaError::Any(
+                "At most four screens can be recorded at once",
+            ));
+        }
+        // Regions are relative to the main screen, so the others would be recorded as they are.
+        if self.capture_targets.len() > 1 && !self.screen_redactions.is_empty() {
+            return Err(MediaError::Any(
+                "Redactions can only be used when recording a single screen or window",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Outputs of screen capture paths are listed in the order of their capture targets.
+#[derive(Debug, Clone)]
+pub struct DescribedOutput {
+    pub source: SourceKind,
+    pub path: PathBuf,
+    /// Set when the path contains a `correctDrift` node.
+    pub drift: Option<DriftTracker>,
+    /// Set when the encoder also writes HLS segments. For replay buffers, these are the only
+    /// thing written, and `path` is where a saved replay would usually go.
+    pub segments: Option<SegmentedOutput>,
+}
+
+struct Chain<'a> {
+    source: &'a NodeDescription,
+    filters: Vec<&'a NodeDescription>,
+    encoder: &'a NodeDescription,
+}
+
+impl PipelineDescription {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, MediaError> {
+        let contents = std::fs::read_to_string(path).map_err(DescriptionError::Io)?;
+
+        let description = serde_json::from_str(&contents).map_err(DescriptionError::Parse)?;
+
+        Ok(description)
+    }
+
+    pub fn sources(&self) -> impl Iterator<Item = SourceKind> + '_ {
+        self.nodes.iter().filter_map(|node| node.kind.source_kind())
+    }
+
+    /// Checks that the description forms a set of linear source -> filters -> encoder paths.
+    pub fn validate(&self) -> Result<(), DescriptionError> {
+        self.chains().map(|_| ())
+    }
+
+    fn chains(&self) -> Result<Vec<Chain<'_>>, DescriptionError> {
+        let mut nodes = IndexMap::new();
+        for node in &self.nodes {
+            if nodes.insert(node.name.as_str(), node).is_some() {
+                return Err(DescriptionError::DuplicateNode(node.name.clone()));
+            }
+        }
+
+        let mut next: IndexMap<&str, &NodeDescription> = IndexMap::new();
+        let mut has_input = HashSet::new();
+
+        for edge in &self.edges {
+            let from = *nodes
+                .get(edge.from.as_str())
+                .ok_or_else(|| DescriptionError::UnknownNode(edge.from.clone()))?;
+            let to = *nodes
+                .get(edge.to.as_str())
+                .ok_or_else(|| DescriptionError::UnknownNode(edge.to.clone()))?;
+
+            if from.kind.role() == Role::Encoder {
+                return Err(DescriptionError::OutputFromEncoder(from.name.clone()));
+            }
+            if to.kind.role() == Role::Source {
+                return Err(DescriptionError::InputToSource(to.name.clone()));
+            }
+            if from.kind.media() != to.kind.media() {
+                return Err(DescriptionError::MediaMismatch {
+                    from: from.name.clone(),
+                    to: to.name.clone(),
+                });
+            }
+            if next.insert(from.name.as_str(), to).is_some() {
+                return Err(DescriptionError::MultipleOutputs(from.name.clone()));
+            }
+            if !has_input.insert(to.name.as_str()) {
+                return Err(DescriptionError::MultipleInputs(to.name.clone()));
+            }
+        }
+
+        let mut chains = vec![];
+        let mut visited = HashSet::new();
+        let mut sources = vec![];
+
+        for source in nodes
+            .values()
+            .copied()
+            .filter(|n| n.kind.role() == Role::Source)
+        {
+            let kind = source.kind.source_kind().unwrap();
+            if sources.contains(&kind) {
+                return Err(DescriptionError::DuplicateSource(kind));
+            }
+            sources.push(kind);
+            visited.insert(source.name.as_str());
+
+            let mut filters = vec![];
+            let mut current = source;
+            let encoder = loop {
+                let Some(node) = next.get(current.name.as_str()).copied() else {
+                    return Err(DescriptionError::Unterminated(source.name.clone()));
+                };
+                visited.insert(node.name.as_str());
+
+                match node.kind.role() {
+                    Role::Encoder => break node,
+                    _ => {
+                        filters.push(node);
+                        current = node;
+                    }
+                }
+            };
+
+            chains.push(Chain {
+                source,
+                filters,
+                encoder,
+            });
+        }
+
+        if chains.is_empty() {
+            return Err(DescriptionError::NoSources);
+        }
+
+        if let Some(name) = nodes.keys().find(|name| !visited.contains(*name)) {
+            return Err(DescriptionError::Disconnected(name.to_string()));
+        }
+
+        // Sources produce raw frames timestamped in microseconds, while encoders need
+        // YUV frames timestamped in frames. Every video filter converts to that.
+        if let Some(chain) = chains
+            .iter()
+            .find(|chain| chain.source.kind.media() == MediaKind::Video && chain.filters.is_empty())
+        {
+            return Err(DescriptionError::UnconvertedVideo(
+                chain.source.name.clone(),
+            ));
+        }
+
+        Ok(chains)
+    }
+
+    /// Validates the description and launches a pipeline for it. Paths whose source is not
+    /// available (no capture targets, no microphone selected, no camera feed running) are
+    /// skipped.
+    pub async fn build<S: TimeSource>(
+        &self,
+        clock: SynchronisedClock<(), S>,
+        inputs: PipelineInputs<'_>,
+    ) -> Result<(Pipeline<SynchronisedClock<(), S>>, Vec<DescribedOutput>), MediaError> {
+        let chains = self.chains()?;
+        inputs.check()?;
+
+        let mut builder = Pipeline::builder(clock);
+        let mut outputs = vec![];
+        let live_stream = match (inputs.replay, inputs.screen_stream) {
+            (None, Some(stream)) if !inputs.capture_targets.is_empty() => {
+                Some(LiveStream::new(stream.clone())?)
+            }
+            _ => None,
+        };
+
+        for chain in chains {
+            let output = inputs.output_dir.join(match &chain.encoder.kind {
+                NodeKind::H264Encoder { output }
+                | NodeKind::Mp3Encoder { output }
+                | NodeKind::AudioEncoder { output, .. } => output,
+                _ => unreachable!("Chains always end with an encoder"),
+            });
+
+            let added;
+            (builder, added) = add_chain(builder, &chain, &inputs, live_stream.as_ref(), output)?;
+            outputs.extend(added);
+        }
+
+        // Every encoder has added its track by now
+        if let Some(live_stream) = live_stream {
+            live_stream.start()?;
+        }
+
+        let pipeline = builder.build().await?;
+
+        Ok((pipeline, outputs))
+    }
+}
+
+impl Default for PipelineDescription {
+    fn default() -> Self {
+        Self::with_audio_codec(AudioCodec::default())
+    }
+}
+
+impl PipelineDescription {
+    /// The built-in recording profile: 1080p 30 fps screen and camera, with the microphone
+    /// encoded using `audio_codec`.
+    pub fn with_audio_codec(audio_codec: AudioCodec) -> Self {
+        let node = |name: &str, kind: NodeKind| NodeDescription {
+            name: name.to_string(),
+            kind,
+        };
+        let edge = |from: &str, to: &str| EdgeDescription {
+            from: from.to_string(),
+            to: to.to_string(),
+        };
+
+        Self {
+            nodes: vec![
+                node("screen_capture", NodeKind::ScreenCapture { fps: None }),
+                node(
+                    "screen_capture_filter",
+                    NodeKind::ScaleVideo {
+                        max_width: 1920,
+                        fps: 30,
+                    },
+                ),
+                node(
+                    "screen_capture_encoder",
+                    NodeKind::H264Encoder {
+                        output: "display.mp4".into(),
+                    },
+                ),
+                node("microphone_capture", NodeKind::Microphone),
+                node("microphone_drift", NodeKind::CorrectDrift),
+                node(
+                    "microphone_encoder",
+                    NodeKind::AudioEncoder {
+                        codec: audio_codec,
+                        output: format!("audio-input.{}", audio_codec.extension()).into(),
+                    },
+                ),
+                node("camera_capture", NodeKind::Camera),
+                node(
+                    "camera_filter",
+                    NodeKind::ScaleVideo {
+                        max_width: 1920,
+                        fps: 30,
+                    },
+                ),
+                node(
+                    "camera_encoder",
+                    NodeKind::H264Encoder {
+                        output: "camera.mp4".into(),
+                    },
+                ),
+            ],
+            edges: vec![
+                edge("screen_capture", "screen_capture_filter"),
+                edge("screen_capture_filter", "screen_capture_encoder"),
+                edge("microphone_capture", "microphone_drift"),
+                edge("microphone_drift", "microphone_encoder"),
+                edge("camera_capture", "camera_filter"),
+                edge("camera_filter", "camera_encoder"),
+            ],
+        }
+    }
+}
+
+type Builder<S> = PipelineBuilder<SynchronisedClock<(), S>>;
+
+/// Tags of the screens recorded after the main one, which also name their replay segments.
+const ADDITIONAL_SCREEN_TAGS: [&str; 3] = ["screen_2", "screen_3", "screen_4"];
+
+/// Adds the tasks for one path to the builder, returning its outputs if the source was
+/// available.
+fn add_chain<S: TimeSource>(
+    mut builder: Builder<S>,
+    chain: &Chain<'_>,
+    inputs: &PipelineInputs<'_>,
+    live_stream: Option<&LiveStream>,
+    output: PathBuf,
+) -> Result<(Builder<S>, Vec<DescribedOutput>), MediaError> {
+    match &chain.source.kind {
+        NodeKind::ScreenCapture { fps } => {
+            let mut outputs = vec![];
+            for (index, capture_target) in inputs.capture_targets.iter().enumerate() {
+                let source = ScreenCaptureSource::<S>::init(capture_target, *fps, None);
+                let info = source.info();
+                let branch = match index {
+                    0 => VideoBranch {
+                        tag: "screen",
+                        suffix: String::new(),
+                        output: output.clone(),
+                        main_screen: Some(capture_target),
+                    },
+                    _ => VideoBranch {
+                        tag: ADDITIONAL_SCREEN_TAGS[index - 1],
+                        suffix: format!("_{}", index + 1),
+                        output: numbered_output(&output, index + 1),
+                        main_screen: None,
+                    },
+                };
+
+                let added;
+                (builder, added) =
+                    add_video_chain(builder, chain, inputs, live_stream, branch, source, info)?;
+                outputs.push(added);
+            }
+
+            Ok((builder, outputs))
+        }
+        NodeKind::Camera => match CameraSource::<S>::init(inputs.camera_feed) {
+            Some(source) => {
+                let info = source.info();
+                let branch = VideoBranch {
+                    tag: "camera",
+                    suffix: String::new(),
+                    output,
+                    main_screen: None,
+                };
+                let (builder, added) =
+                    add_video_chain(builder, chain, inputs, None, branch, source, info)?;
+                Ok((builder, vec![added]))
+            }
+            None => Ok((builder, vec![])),
+        },
+        NodeKind::Microphone => match AudioInputSource::<S>::init(inputs.audio_input_name) {
+            Some(source) => {
+                let (builder, added) = add_audio_chain(
+                    builder,
+                    chain,
+                    inputs,
+                    live_stream,
+                    "microphone",
+                    source,
+                    output,
+                )?;
+                Ok((builder, vec![added]))
+            }
+            None => Ok((builder, vec![])),
+        },
+        _ => unreachable!("Chains always start with a source"),
+    }
+}
+
+/// `display.mp4` becomes `display-2.mp4` for the second screen.
+fn numbered_output(output: &Path, number: usize) -> PathBuf {
+    let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+    let file_name = match output.extension() {
+        Some(extension) => format!("{stem}-{number}.{}", extension.to_string_lossy()),
+        None => format!("{stem}-{number}"),
+    };
+
+    output.with_file_name(file_name)
+}
+
+/// One instance of a video path, as screen capture paths are instantiated once per target.
+struct VideoBranch<'a> {
+    tag: &'static str,
+    /// Appended to the description's node names, so that every instance has unique tasks.
+    suffix: String,
+    output: PathBuf,
+    /// Set for the main screen's path, which is the only one redacted, segmented and streamed.
+    main_screen: Option<&'a ScreenCaptureTarget>,
+}
+
+fn add_video_chain<S: TimeSource, C: CloneFrom<SynchronisedClock<(), S>> + Send + 'static>(
+    builder: Builder<S>,
+    chain: &Chain<'_>,
+    inputs: &PipelineInputs<'_>,
+    live_stream: Option<&LiveStream>,
+    branch: VideoBranch<'_>,
+    source: impl PipelineSourceTask<Output = FFVideo, Clock = C> + 'static,
+    source_info: VideoInfo,
+) -> Result<(Builder<S>, DescribedOutput), MediaError> {
+    let VideoBranch {
+        tag,
+        suffix,
+        output,
+        main_screen,
+    } = branch;
+
+    let mut info = source_info;
+    let mut filters = vec![];
+    for node in &chain.filters {
+        let (filter, output_info) = video_filter(tag, node, info)?;
+        filters.push((format!("{}{suffix}", node.name), filter));
+        info = output_info;
+    }
+    let (encoder_output, segments) = match (inputs.replay, main_screen) {
+        (Some(replay), _) => {
+            let segments = replay.segments_for(tag);
+            (Output::Segmented(segments.clone()), Some(segments))
+        }
+        (None, Some(_)) => {
+            let segments = inputs.screen_segments.cloned();
+            let mut encoder_outputs = vec![Output::File(output.clone())];
+            encoder_outputs.extend(segments.clone().map(Output::Segmented));
+            encoder_outputs.extend(live_stream.cloned().map(Output::Stream));
+            let encoder_output = match encoder_outputs.len() {
+                1 => encoder_outputs.remove(0),
+                _ => Output::Tee(encoder_outputs),
+            };
+            (encoder_output, segments)
+        }
+        (None, _) => (Output::File(output.clone()), None),
+    };
+    let encoder = H264Encoder::init(tag, info, encoder_output, inputs.encoder_settings)?;
+
+    let redactor = match main_screen {
+        Some(capture_target) if !inputs.screen_redactions.is_empty() => Some(VideoRedactor::init(
+            tag,
+            source_info,
+            capture_target,
+            inputs.screen_redactions.to_vec(),
+        )?),
+        _ => None,
+    };
+
+    let mut path = builder.source(format!("{}{suffix}", chain.source.name), source);
+    if let Some(redactor) = redactor {
+        path = path.pipe(format!("{}_redaction", chain.source.name), redactor);
+    }
+    for (name, filter) in filters {
+        path = path.pipe(name, filter);
+    }
+
+    Ok((
+        path.sink(format!("{}{suffix}", chain.encoder.name), encoder),
+        DescribedOutput {
+            source: chain.source.kind.source_kind().unwrap(),
+            path: output,
+            drift: None,
+            segments,
+        },
+    ))
+}
+
+fn add_audio_chain<S: TimeSource>(
+    builder: Builder<S>,
+    chain: &Chain<'_>,
+    inputs: &PipelineInputs<'_>,
+    live_stream: Option<&LiveStream>,
+    tag: &'static str,
+    source: AudioInputSource<S>,
+    output: PathBuf,
+) -> Result<(Builder<S>, DescribedOutput), MediaError> {
+    let mut info = source.info();
+    let mut drift = None;
+    let mut filters = vec![];
+    for node in &chain.filters {
+        let (filter, output_info) = audio_filter(tag, node, info)?;
+        if let AudioStage::CorrectDrift(corrector) = &filter {
+            drift = Some(corrector.tracker());
+        }
+        filters.push((&node.name, filter));
+        info = output_info;
+    }
+    let codec = match &chain.encoder.kind {
+        NodeKind::AudioEncoder { codec, .. } => *codec,
+        _ => AudioCodec::Mp3,
+    };
+    // Replay segments are MPEG-TS, which can't carry PCM.
+    let (codec, output) = match (codec, inputs.replay) {
+        (AudioCodec::Pcm, Some(_)) => {
+            eprintln!("Replay buffers can't keep {codec:?} audio, using AAC instead");
+            let codec = AudioCodec::Aac;
+            (codec, output.with_extension(codec.extension()))
+        }
+        _ => (codec, output),
+    };
+    let segments = inputs.replay.map(|replay| replay.segments_for(tag));
+    let encoder_output = match (&segments, live_stream) {
+        (Some(segments), _) => Output::Segmented(segments.clone()),
+        (None, Some(stream)) if stream.accepts(codec) => Output::Tee(vec![
+            Output::File(output.clone()),
+            Output::Stream(stream.clone()),
+        ]),
+        (None, Some(_)) => {
+            eprintln!("The live stream can't carry {codec:?} audio, streaming without it");
+            Output::File(output.clone())
+        }
+        (None, None) => Output::File(output.clone()),
+    };
+    let encoder = AudioEncoder::init(tag, info, encoder_output, codec)?;
+
+    let mut path = builder.source(&chain.source.name, source);
+    for (name, filter) in filters {
+        path = match filter {
+            AudioStage::Filter(filter) => path.pipe(name, filter),
+            AudioStage::CorrectDrift(corrector) => path.pipe(name, corrector),
+        };
+    }
+
+    Ok((
+        path.sink(&chain.encoder.name, encoder),
+        DescribedOutput {
+            source: chain.source.kind.source_kind().unwrap(),
+            path: output,
+            drift,
+            segments,
+        },
+    ))
+}
+
+fn video_filter(
+    tag: &'static str,
+    node: &NodeDescription,
+    input: VideoInfo,
+) -> Result<(VideoFilter, VideoInfo), MediaError> {
+    match &node.kind {
+        NodeKind::ScaleVideo { max_width, fps } => {
+            let filter = VideoFilter::init(tag, input, input.scaled(*max_width, *fps))?;
+            let output = filter.output_info();
+            Ok((filter, output))
+        }
+        NodeKind::VideoFilter { ops } => {
+            let filter = VideoFilter::with_ops(tag, input, ops, Pixel::YUV420P)?;
+            let output = filter.output_info();
+            Ok((filter, output))
+        }
+        _ => unreachable!("Edges are validated to connect matching media"),
+    }
+}
+
+enum AudioStage {
+    Filter(AudioFilter),
+    CorrectDrift(AudioDriftCorrector),
+}
+
+fn audio_filter(
+    tag: &'static str,
+    node: &NodeDescription,
+    input: AudioInfo,
+) -> Result<(AudioStage, AudioInfo), MediaError> {
+    match &node.kind {
+        NodeKind::ResampleAudio { sample_rate } => {
+            let spec = format!("aresample={sample_rate}");
+            Ok((
+                AudioStage::Filter(AudioFilter::init(tag, input, &spec)?),
+                input.with_rate(*sample_rate),
+            ))
+        }
+        NodeKind::AudioFilter { spec } => Ok((
+            AudioStage::Filter(AudioFilter::init(tag, input, spec)?),
+            input,
+        )),
+        NodeKind::CorrectDrift => Ok((
+            AudioStage::CorrectDrift(AudioDriftCorrector::init(tag, input)?),
+            input,
+        )),
+        _ => unreachable!("Edges are validated to connect matching media"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::Bounds;
+
+    #[test]
+    fn redactions_need_a_single_screen() {
+        let redactions = [RedactionRegion {
+            bounds: Bounds {
+                x: 0.0,
+                y: 0.0,
+                width: 100.0,
+                height: 100.0,
+            },
+            style: Default::default(),
+        }];
+        let encoder_settings = EncoderSettings::default();
+        let inputs = |capture_targets, screen_redactions| PipelineInputs {
+            capture_targets,
+            audio_input_name: None,
+            camera_feed: None,
+            output_dir: Path::new("."),
+            encoder_settings: &encoder_settings,
+            screen_segments: None,
+            screen_stream: None,
+            screen_redactions,
+            replay: None,
+        };
+        let one = [ScreenCaptureTarget::Screen];
+        let two = [ScreenCaptureTarget::Screen, ScreenCaptureTarget::Screen];
+        let five = [
+            ScreenCaptureTarget::Screen,
+            ScreenCaptureTarget::Screen,
+            ScreenCaptureTarget::Screen,
+            ScreenCaptureTarget::Screen,
+            ScreenCaptureTarget::Screen,
+        ];
+
+        assert!(inputs(&one, &redactions).check().is_ok());
+        assert!(inputs(&two, &[]).check().is_ok());
+        assert!(inputs(&[], &redactions).check().is_ok());
+        assert!(inputs(&two, &redactions).check().is_err());
+        assert!(inputs(&five, &[]).check().is_err());
+    }
+
+    fn description(nodes: &[(&str, NodeKind)], edges: &[(&str, &str)]) -> PipelineDescription {
+        PipelineDescription {
+            nodes: nodes
+                .iter()
+                .map(|(name, kind)| NodeDescription {
+                    name: name.to_string(),
+                    kind: kind.clone(),
+                })
+                .collect(),
+            edges: edges
+                .iter()
+                .map(|(from, to)| EdgeDescription {
+                    from: from.to_string(),
+                    to: to.to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn default_description_has_a_chain_per_source() {
+        let description = PipelineDescription::default();
+        let chains = description.chains().unwrap();
+
+        assert_eq!(
+            chains
+                .iter()
+                .map(|chain| (
+                    chain.source.name.as_str(),
+                    chain.filters.len(),
+                    chain.encoder.name.as_str()
+                ))
+                .collect::<Vec<_>>(),
+            [
+                ("screen_capture", 1, "screen_capture_encoder"),
+                ("microphone_capture", 1, "microphone_encoder"),
+                ("camera_capture", 1, "camera_encoder"),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_descriptions_are_rejected() {
+        let screen = NodeKind::ScreenCapture { fps: None };
+        let microphone = NodeKind::Microphone;
+        let scale = NodeKind::ScaleVideo {
+            max_width: 1920,
+            fps: 30,
+        };
+        let video_encoder = NodeKind::H264Encoder {
+            output: "display.mp4".into(),
+        };
+        let audio_encoder = NodeKind::Mp3Encoder {
+            output: "audio.mp3".into(),
+        };
+
+        let cases = [
+            (
+                "no sources",
+                description(
+                    &[
+                        ("filter", scale.clone()),
+                        ("encoder", video_encoder.clone()),
+                    ],
+                    &[("filter", "encoder")],
+                ),
+                "The pipeline description does not contain any sources",
+            ),
+            (
+                "duplicate node",
+                description(
+                    &[("screen", screen.clone()), ("screen", screen.clone())],
+                    &[],
+                ),
+                "Node screen is defined more than once",
+            ),
+            (
+                "duplicate source",
+                description(
+                    &[
+                        ("screen", screen.clone()),
+                        ("screen_encoder", video_encoder.clone()),
+                        ("other_screen", screen.clone()),
+                        ("other_encoder", video_encoder.clone()),
+                    ],
+                    &[
+                        ("screen", "screen_encoder"),
+                        ("other_screen", "other_encoder"),
+                    ],
+                ),
+                "Only one Screen source can be used in a pipeline",
+            ),
+            (
+                "missing node",
+                description(
+                    &[
+                        ("screen", screen.clone()),
+                        ("encoder", video_encoder.clone()),
+                    ],
+                    &[("screen", "encoder"), ("screen", "filter")],
+                ),
+                "Edge refers to unknown node filter",
+            ),
+            (
+                "input to source",
+                description(
+                    &[("screen", screen.clone()), ("filter", scale.clone())],
+                    &[("filter", "screen")],
+                ),
+                "screen is a source and cannot receive input",
+            ),
+            (
+                "output from encoder",
+                description(
+                    &[
+                        ("encoder", video_encoder.clone()),
+                        ("filter", scale.clone()),
+                    ],
+                    &[("encoder", "filter")],
+                ),
+                "encoder is an encoder and cannot produce output",
+            ),
+            (
+                "multiple outputs",
+                description(
+                    &[
+                        ("screen", screen.clone()),
+                        ("encoder", video_encoder.clone()),
+                        ("other_encoder", video_encoder.clone()),
+                    ],
+                    &[("screen", "encoder"), ("screen", "other_encoder")],
+                ),
+                "screen has more than one output",
+            ),
+            (
+                "source without an encoder",
+                description(
+                    &[("screen", screen.clone()), ("filter", scale.clone())],
+                    &[("screen", "filter")],
+                ),
+                "screen does not lead into an encoder",
+            ),
+            (
+                "dangling input",
+                description(
+                    &[
+                        ("screen", screen.clone()),
+                        ("encoder", video_encoder.clone()),
+                        ("filter", scale.clone()),
+                        ("other_encoder", video_encoder.clone()),
+                    ],
+                    &[("screen", "encoder"), ("filter", "other_encoder")],
+                ),
+                "filter is not connected to any source",
+            ),
+            (
+                "cycle",
+                description(
+                    &[
+                        ("screen", screen.clone()),
+                        ("encoder", video_encoder.clone()),
+                        ("a", scale.clone()),
+                        ("b", scale.clone()),
+                    ],
+                    &[("screen", "encoder"), ("a", "b"), ("b", "a")],
+                ),
+                "a is not connected to any source",
+            ),
+            (
+                "cycle on a source's path",
+                description(
+                    &[
+                        ("screen", screen.clone()),
+                        ("a", scale.clone()),
+                        ("b", scale.clone()),
+                    ],
+                    &[("screen", "a"), ("a", "b"), ("b", "a")],
+                ),
+                "a has more than one input",
+            ),
+            (
+                "video without a filter",
+                description(
+                    &[
+                        ("screen", screen.clone()),
+                        ("encoder", video_encoder.clone()),
+                    ],
+                    &[("screen", "encoder")],
+                ),
+                "screen must pass through a scaleVideo or videoFilter node before its encoder",
+            ),
+            (
+                "mismatched media",
+                description(
+                    &[
+                        ("microphone", microphone.clone()),
+                        ("encoder", video_encoder),
+                    ],
+                    &[("microphone", "encoder")],
+                ),
+                "Cannot connect microphone to encoder: they handle different kinds of media",
+            ),
+            (
+                "mismatched filter",
+                description(
+                    &[
+                        ("microphone", microphone),
+                        ("filter", scale),
+                        ("encoder", audio_encoder),
+                    ],
+                    &[("microphone", "filter"), ("filter", "encoder")],
+                ),
+                "Cannot connect microphone to filter: they handle different kinds of media",
+            ),
+        ];
+
+        for (case, description, error) in cases {
+            match description.chains() {
+                Ok(_) => panic!("{case}: expected an error"),
+                Err(actual) => assert_eq!(actual.to_string(), error, "{case}"),
+            }
+        }
+    }
+
+    #[test]
+    fn parse_errors_say_what_is_wrong() {
+        let path = tempfile::NamedTempFile::new().unwrap().into_temp_path();
+        std::fs::write(
+            &path,
+            r#"{ "nodes": [{ "name": "screen", "kind": "webcam" }] }"#,
+        )
+        .unwrap();
+
+        let error = PipelineDescription::load(&path).unwrap_err().to_string();
+        assert!(
+            error.starts_with("Invalid pipeline description: "),
+            "{error}"
+        );
+        assert!(error.contains("webcam"), "{error}");
+    }
+
+    #[test]
+    fn wav_microphone_profile_is_valid() {
+        let description: PipelineDescription = serde_json::from_str(
+            r#"{
+                "nodes": [
+                    { "name": "mic", "kind": "microphone" },
+                    { "name": "resample", "kind": "resampleAudio", "sampleRate": 48000 },
+                    { "name": "wav", "kind": "audioEncoder", "codec": "pcm", "output": "mic.wav" }
+                ],
+                "edges": [
+                    { "from": "mic", "to": "resample" },
+                    { "from": "resample", "to": "wav" }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        description.validate().unwrap();
+    }
+
+    #[test]
+    fn read_errors_are_kept() {
+        let dir = tempfile::tempdir().unwrap();
+
+        match PipelineDescription::load(dir.path().join("missing.json")) {
+            Err(MediaError::InvalidDescription(DescriptionError::Io(error))) => {
+                assert_eq!(error.kind(), std::io::ErrorKind::NotFound)
+            }
+            other => panic!("expected a read error, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,466 @@
+use ffmpeg::{
+    codec::{capabilities::Capabilities, codec::Codec, context, encoder, Parameters},
+    format::Sample,
+    software::resampling,
+    threading::Config,
+};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+use crate::{
+    data::{AudioInfo, FFAudio, FFPacket, FFRational},
+    pipeline::{control::PipelineMessages, task::PipelineSinkTask},
+    MediaError,
+};
+
+use super::{Muxer, Output};
+
+/// Used when an encoder accepts any frame size.
+const DEFAULT_FRAME_SIZE: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub enum AudioCodec {
+    #[default]
+    Mp3,
+    /// AAC, which unlike MP3 can be muxed directly into MP4.
+    Aac,
+    Opus,
+    /// Uncompressed 16-bit PCM in a WAV file.
+    Pcm,
+}
+
+impl AudioCodec {
+    /// File extension of the container this codec is usually stored in.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Mp3 => "mp3",
+            Self::Aac => "m4a",
+            Self::Opus => "ogg",
+            Self::Pcm => "wav",
+        }
+    }
+
+    fn find(&self) -> Option<Codec> {
+        match self {
+            Self::Mp3 => encoder::find(ffmpeg::codec::Id::MP3),
+            Self::Aac => encoder::find(ffmpeg::codec::Id::AAC),
+            // FFmpeg's own Opus encoder is still experimental.
+            Self::Opus => encoder::find_by_name("libopus"),
+            Self::Pcm => encoder::find(ffmpeg::codec::Id::PCM_S16LE),
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self {
+            Self::Mp3 => "MP3 audio",
+            Self::Aac => "AAC audio",
+            Self::Opus => "Opus audio",
+            Self::Pcm => "PCM audio",
+        }
+    }
+
+    /// Not used for PCM, whose bitrate follows from the sample rate.
+    fn bit_rate(&self) -> usize {
+        match self {
+            Self::Mp3 | Self::Aac => 128 * 1000,
+            Self::Opus => 96 * 1000,
+            Self::Pcm => 0,
+        }
+    }
+}
+
+/// A first-in first-out queue of audio samples, kept per plane so that it works for both
+/// packed and planar sample formats. Lets encoders receive frames of exactly the size they
+/// need, regardless of how the input is chunked.
+pub struct AudioFifo {
+    format: Sample,
+    planes: Vec<Vec<u8>>,
+    plane_sample_size: usize,
+}
+
+impl AudioFifo {
+    pub fn new(format: Sample, channels: usize) -> Self {
+        let (plane_count, plane_sample_size) = match format.is_planar() {
+            true => (channels, format.bytes()),
+            false => (1, format.bytes() * channels),
+        };
+
+        Self {
+            format,
+            planes: vec![vec![]; plane_count],
+            plane_sample_size,
+        }
+    }
+
+    /// Number of samples (per channel) in the queue.
+    pub fn len(&self) -> usize {
+        self.planes[0].len() / self.plane_sample_size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn push(&mut self, frame: &FFAudio) {
+        let size = frame.samples() * self.plane_sample_size;
+
+        for (index, plane) in self.planes.iter_mut().enumerate() {
+            plane.extend_from_slice(&frame.data(index)[..size]);
+        }
+    }
+
+    /// Moves up to `frame.samples()` samples into `frame`, filling whatever is left over
+    /// with silence. Returns the number of samples taken from the queue.
+    pub fn pop_into(&mut self, frame: &mut FFAudio) -> usize {
+        let samples = self.len().min(frame.samples());
+        let size = samples * self.plane_sample_size;
+        let frame_size = frame.samples() * self.plane_sample_size;
+        // Unsigned 8-bit samples are centred around 128 rather than 0.
+        let silence = match self.format {
+            Sample::U8(_) => 0x80,
+            _ => 0,
+        };
+
+        for (index, plane) in self.planes.iter_mut().enumerate() {
+            let data = &mut frame.data_mut(index)[..frame_size];
+            data[..size].copy_from_slice(&plane[..size]);
+            data[size..].fill(silence);
+            plane.drain(..size);
+        }
+
+        samples
+    }
+}
+
+/// Encodes audio with any of the supported [`AudioCodec`]s, converting the input to a
+/// sample format and rate the codec accepts.
+pub struct AudioEncoder {
+    tag: &'static str,
+    encoder: encoder::Audio,
+    muxer: Muxer,
+    resampler: Option<resampling::Context>,
+    fifo: AudioFifo,
+    frame_size: usize,
+    // Whether the final frame must be padded to `frame_size`, or may be sent short.
+    pad_last_frame: bool,
+    next_pts: i64,
+}
+
+impl AudioEncoder {
+    pub fn init(
+        tag: &'static str,
+        config: AudioInfo,
+        output: Output,
+        codec: AudioCodec,
+    ) -> Result<Self, MediaError> {
+        let mut muxer = Muxer::open(output)?;
+
+        let audio_codec = codec
+            .find()
+            .ok_or(MediaError::MissingCodec(codec.description()))?;
+        let sample_format = pick_sample_format(audio_codec, config.sample_format)?;
+        let sample_rate = pick_sample_rate(audio_codec, config.rate())?;
+        let channel_layout = config.channel_layout();
+
+        let mut encoder_ctx = context::Context::new_with_codec(audio_codec);
+        encoder_ctx.set_threading(Config::count(4));
+        let mut encoder = encoder_ctx.encoder().audio()?;
+
+        encoder.set_bit_rate(codec.bit_rate());
+        encoder.set_rate(sample_rate);
+        encoder.set_format(sample_format);
+        encoder.set_channel_layout(channel_layout);
+        encoder.set_time_base(FFRational(1, sample_rate));
+
+        if muxer.needs_global_header() {
+            encoder.set_flags(ffmpeg::codec::Flags::GLOBAL_HEADER);
+        }
+
+        let audio_encoder = encoder.open()?;
+
+        muxer.add_stream(
+            audio_codec,
+            FFRational(1, sample_rate),
+            Parameters::from(&audio_encoder),
+        )?;
+        muxer.write_header()?;
+
+        let resampler = match (sample_format, sample_rate) {
+            (format, rate) if format == config.sample_format && rate == config.rate() => None,
+            _ => Some(ffmpeg::software::resampler(
+                (config.sample_format, channel_layout, config.rate() as u32),
+                (sample_format, channel_layout, sample_rate as u32),
+            )?),
+        };
+
+        let frame_size = match audio_encoder.frame_size() {
+            0 => DEFAULT_FRAME_SIZE,
+            size => size as usize,
+        };
+        let pad_last_frame = !audio_codec
+            .capabilities()
+            .intersects(Capabilities::SMALL_LAST_FRAME | Capabilities::VARIABLE_FRAME_SIZE);
+
+        Ok(Self {
+            tag,
+            fifo: AudioFifo::new(sample_format, channel_layout.channels() as usize),
+            encoder: audio_encoder,
+            muxer,
+            resampler,
+            frame_size,
+            pad_last_frame,
+            next_pts: 0,
+        })
+    }
+
+    fn queue_frame(&mut self, frame: FFAudio) -> Result<(), MediaError> {
+        match &mut self.resampler {
+            Some(resampler) => {
+                let output_rate = resampler.output().rate as usize;
+                let input_rate = resampler.input().rate as usize;
+                // Leave room for whatever the resampler has buffered from earlier frames.
+                let capacity = frame.samples() * output_rate / input_rate + self.frame_size;
+                let mut resampled = new_frame(&self.encoder, capacity);
+                resampler.run(&frame, &mut resampled)?;
+                self.fifo.push(&resampled);
+            }
+            None => self.fifo.push(&frame),
+        }
+
+        self.send_frames(false)
+    }
+
+    /// Sends full frames from the FIFO to the encoder. When `flush` is set, also sends the
+    /// final partial frame, padded with silence if the codec requires it.
+    fn send_frames(&mut self, flush: bool) -> Result<(), MediaError> {
+        while self.fifo.len() >= self.frame_size || (flush && !self.fifo.is_empty()) {
+            let samples = match self.fifo.len() < self.frame_size && !self.pad_last_frame {
+                true => self.fifo.len(),
+                false => self.frame_size,
+            };
+
+            let mut frame = new_frame(&self.encoder, samples);
+            self.fifo.pop_into(&mut frame);
+            frame.set_pts(Some(self.next_pts));
+            self.next_pts += samples as i64;
+
+            self.encoder.send_frame(&frame)?;
+            self.process_packets()?;
+        }
+
+        Ok(())
+    }
+
+    fn process_packets(&mut self) -> Result<(), MediaError> {
+        let mut encoded_packet = FFPacket::empty();
+
+        while self.encoder.receive_packet(&mut encoded_packet).is_ok() {
+            self.muxer
+                .write_packet(&encoded_packet, self.encoder.time_base())?;
+        }
+
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), MediaError> {
+        // The trailers are written even if the last samples aren't, so that the outputs that
+        // are still fine end up playable.
+        let flushed = self.flush();
+        let finished = self.muxer.write_trailer();
+
+        flushed.and(finished)
+    }
+
+    fn flush(&mut self) -> Result<(), MediaError> {
+        if let Some(resampler) = self.resampler.as_mut() {
+            loop {
+                let mut resampled = new_frame(&self.encoder, self.frame_size);
+                resampler.flush(&mut resampled)?;
+
+                if resampled.samples() == 0 {
+                    break;
+                }
+                self.fifo.push(&resampled);
+            }
+        }
+
+        self.send_frames(true)?;
+        self.encoder.send_eof()?;
+        self.process_packets()
+    }
+}
+
+impl PipelineSinkTask for AudioEncoder {
+    type Input = FFAudio;
+
+    fn run(
+        &mut self,
+        ready_signal: crate::pipeline::task::PipelineReadySignal,
+        messages: PipelineMessages,
+        input: flume::Receiver<Self::Input>,
+    ) {
+        println!("Starting {} audio encoding thread", self.tag);
+        self.muxer.set_status_reporter(messages.reporter());
+        ready_signal.send(Ok(())).unwrap();
+
+        while let Ok(frame) = input.recv() {
+            if let Err(error) = self.queue_frame(frame) {
+                eprintln!("Failed to encode {} audio: {error}", self.tag);
+            }
+        }
+
+        println!("Received last {} sample. Finishing up encoding.", self.tag);
+        if let Err(error) = self.finish() {
+            eprintln!("Failed to finish {} audio encoding: {error}", self.tag);
+        }
+
+        println!("Shutting down {} audio encoding thread", self.tag);
+    }
+}
+
+fn new_frame(encoder: &encoder::Audio, samples: usize) -> FFAudio {
+    let mut frame = FFAudio::new(encoder.format(), samples, encoder.channel_layout());
+    frame.set_rate(encoder.rate());
+    frame
+}
+
+fn pick_sample_format(codec: Codec, input: Sample) -> Result<Sample, MediaError> {
+    let formats = codec.audio()?.formats();
+
+    Ok(match formats {
+        Some(formats) => {
+            let formats = formats.collect::<Vec<_>>();
+            match formats.contains(&input) {
+                true => input,
+                false => *formats.first().ok_or(MediaError::Any(
+                    "Audio codec has no supported sample formats",
+                ))?,
+            }
+        }
+        None => input,
+    })
+}
+
+fn pick_sample_rate(codec: Codec, input: i32) -> Result<i32, MediaError> {
+    let rates = codec.audio()?.rates();
+
+    Ok(match rates {
+        Some(rates) => {
+            let rates = rates.collect::<Vec<_>>();
+            match (rates.contains(&input), rates.contains(&48_000)) {
+                (true, _) => input,
+                (false, true) => 48_000,
+                (false, false) => *rates
+                    .first()
+                    .ok_or(MediaError::Any("Audio codec has no supported sample rates"))?,
+            }
+        }
+        None => input,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::RawAudioFormat;
+
+    #[test]
+    fn fifo_keeps_sample_order_and_pads_with_silence() {
+        let info = AudioInfo::from_raw(RawAudioFormat::I16, 48_000, 1, 1024);
+        let mut fifo = AudioFifo::new(info.sample_format, 1);
+
+        let bytes = (1..=6i16).flat_map(i16::to_ne_bytes).collect::<Vec<_>>();
+        fifo.push(&info.wrap_frame(&bytes[..4], 0));
+        fifo.push(&info.wrap_frame(&bytes[4..], 0));
+        assert_eq!(fifo.len(), 6);
+
+        let mut frame = FFAudio::new(info.sample_format, 4, info.channel_layout());
+        assert_eq!(fifo.pop_into(&mut frame), 4);
+        assert_eq!(frame.data(0)[..8], bytes[..8]);
+
+        assert_eq!(fifo.pop_into(&mut frame), 2);
+        assert_eq!(frame.data(0)[..4], bytes[8..]);
+        assert_eq!(frame.data(0)[4..8], [0; 4]);
+        assert!(fifo.is_empty());
+    }
+
+    fn decoded_samples(path: &std::path::Path) -> usize {
+        let mut input = ffmpeg::format::input(&path).unwrap();
+        let stream = input.streams().best(ffmpeg::media::Type::Audio).unwrap();
+        let index = stream.index();
+        let mut decoder = context::Context::from_parameters(stream.parameters())
+            .unwrap()
+            .decoder()
+            .audio()
+            .unwrap();
+
+        let mut samples = 0;
+        let mut frame = FFAudio::empty();
+        for (stream, packet) in input.packets() {
+            if stream.index() == index {
+                decoder.send_packet(&packet).unwrap();
+                while decoder.receive_frame(&mut frame).is_ok() {
+                    samples += frame.samples();
+                }
+            }
+        }
+        decoder.send_eof().unwrap();
+        while decoder.receive_frame(&mut frame).is_ok() {
+            samples += frame.samples();
+        }
+
+        samples
+    }
+
+    fn assert_duration_matches_input(codec: AudioCodec) {
+        ffmpeg::init().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(format!("audio.{}", codec.extension()));
+        let info = AudioInfo::from_raw(RawAudioFormat::F32, 48_000, 2, 1024);
+
+        let mut encoder =
+            AudioEncoder::init("test", info, Output::File(path.clone()), codec).unwrap();
+        let frame_size = encoder.frame_size;
+
+        // Uneven chunks, so that the input never lines up with the encoder's frame size.
+        let mut input_samples = 0;
+        for chunk in [480, 1000, 333, 4096, 7].into_iter().cycle().take(41) {
+            let data = vec![0u8; chunk * info.sample_size()];
+            encoder.queue_frame(info.wrap_frame(&data, 0)).unwrap();
+            input_samples += chunk;
+        }
+        encoder.finish().unwrap();
+
+        // Not every container trims the silence added to the last frame, but nothing may be lost.
+        let padding = match input_samples % frame_size {
+            0 => 0,
+            remainder => frame_size - remainder,
+        };
+        let decoded = decoded_samples(&path);
+        assert!(
+            decoded >= input_samples && decoded <= input_samples + padding,
+            "{codec:?}: encoded {decoded} samples from {input_samples} input samples"
+        );
+    }
+
+    #[test]
+    fn mp3_duration_matches_input() {
+        assert_duration_matches_input(AudioCodec::Mp3);
+    }
+
+    #[test]
+    fn aac_duration_matches_input() {
+        assert_duration_matches_input(AudioCodec::Aac);
+    }
+
+    #[test]
+    fn opus_duration_matches_input() {
+        assert_duration_matches_input(AudioCodec::Opus);
+    }
+
+    #[test]
+    fn pcm_duration_matches_input() {
+        assert_duration_matches_input(AudioCodec::Pcm);
+    }
+}
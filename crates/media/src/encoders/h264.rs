@@ -1,83 +1,141 @@
 use ffmpeg::{
-    codec::{codec::Codec, context, encoder},
-    format::{self, pixel::Pixel},
+    codec::{context, encoder, Parameters},
+    format::pixel::Pixel,
+    picture,
     threading::Config,
-    Dictionary,
 };
 
 use crate::{
     data::{FFPacket, FFVideo, VideoInfo},
-    pipeline::task::PipelineSinkTask,
+    pipeline::{
+        control::{PipelineMessage, PipelineMessages},
+        task::PipelineSinkTask,
+    },
     MediaError,
 };
 
-use super::Output;
+use super::{EncoderSettings, Muxer, Output, VideoCodec};
 
+/// Encodes video with the codec selected in its [`EncoderSettings`], H.264 by default.
 pub struct H264Encoder {
     tag: &'static str,
     encoder: encoder::Video,
-    output_ctx: format::context::Output,
+    muxer: Muxer,
+    force_keyframe: bool,
 }
 
 impl H264Encoder {
-    pub fn init(tag: &'static str, config: VideoInfo, output: Output) -> Result<Self, MediaError> {
-        let destination = match output {
-            Output::File(path) => path,
-        };
-        let mut output_ctx = format::output(&destination)?;
+    pub fn init(
+        tag: &'static str,
+        config: VideoInfo,
+        output: Output,
+        settings: &EncoderSettings,
+    ) -> Result<Self, MediaError> {
+        let mut muxer = Muxer::open(output)?;
 
-        let (codec, options) = get_codec_and_options(&config)?;
+        let (settings, (codec, mut options)) = match settings.codec_and_options(&config) {
+            Ok(codec_and_options) => (*settings, codec_and_options),
+            // Only the codec falls back, so that the rest of the settings still apply.
+            Err(MediaError::MissingCodec(name)) if settings.codec != VideoCodec::default() => {
+                eprintln!("{name} encoder is not available, falling back to H264");
+                let settings = EncoderSettings {
+                    codec: VideoCodec::default(),
+                    ..*settings
+                };
+                (settings, settings.codec_and_options(&config)?)
+            }
+            Err(error) => return Err(error),
+        };
 
         let mut encoder_ctx = context::Context::new_with_codec(codec);
-        // TODO: Configure this per system
-        encoder_ctx.set_threading(Config::count(4));
+        encoder_ctx.set_threading(Config::count(settings.thread_count()));
         let mut encoder = encoder_ctx.encoder().video()?;
 
+        // Live streams cap the bitrate, which is meaningless for lossless output.
+        let max_bit_rate = muxer
+            .max_bit_rate()
+            .filter(|_| settings.codec != VideoCodec::Lossless);
+
+        if let Some(bit_rate) = settings.bit_rate() {
+            encoder.set_bit_rate(max_bit_rate.map_or(bit_rate, |max| bit_rate.min(max)));
+        }
+        if let Some(max_bit_rate) = max_bit_rate {
+            encoder.set_max_bit_rate(max_bit_rate);
+            options.set("bufsize", &(max_bit_rate * 2).to_string());
+        }
         encoder.set_width(config.width);
         encoder.set_height(config.height);
         encoder.set_format(config.pixel_format);
         encoder.set_time_base(config.frame_rate.invert());
 
+        if muxer.needs_global_header() {
+            encoder.set_flags(ffmpeg::codec::Flags::GLOBAL_HEADER);
+        }
+
         let video_encoder = encoder.open_with(options)?;
 
-        let mut output_stream = output_ctx.add_stream(codec)?;
-        output_stream.set_time_base(config.frame_rate.invert());
-        output_stream.set_parameters(&video_encoder);
+        muxer.add_stream(
+            codec,
+            config.frame_rate.invert(),
+            Parameters::from(&video_encoder),
+        )?;
         // TODO: Move this to after pipeline start maybe?
-        output_ctx.write_header()?;
+        muxer.write_header()?;
 
         Ok(Self {
             tag,
             encoder: video_encoder,
-            output_ctx,
+            muxer,
+            force_keyframe: false,
         })
     }
 
-    fn queue_frame(&mut self, frame: FFVideo) {
-        self.encoder.send_frame(&frame).unwrap();
+    fn handle_messages(&mut self, messages: &PipelineMessages) {
+        for message in messages.pending() {
+            if message == PipelineMessage::RequestKeyframe {
+                self.force_keyframe = true;
+            }
+        }
     }
 
-    fn process_frame(&mut self) {
+    fn queue_frame(&mut self, mut frame: FFVideo) -> Result<(), MediaError> {
+        if self.force_keyframe {
+            frame.set_kind(picture::Type::I);
+            self.force_keyframe = false;
+        }
+        self.encoder.send_frame(&frame)?;
+
+        Ok(())
+    }
+
+    fn process_frame(&mut self) -> Result<(), MediaError> {
+        if self.muxer.take_keyframe_request() {
+            self.force_keyframe = true;
+        }
+
         let mut encoded_packet = FFPacket::empty();
 
         // TODO: Handle errors that are not EGAIN/"needs more data"
         while self.encoder.receive_packet(&mut encoded_packet).is_ok() {
-            encoded_packet.set_stream(0);
-            encoded_packet.rescale_ts(
-                self.encoder.time_base(),
-                self.output_ctx.stream(0).unwrap().time_base(),
-            );
             // TODO: Possibly move writing to disk to its own file, to increase encoding throughput?
-            encoded_packet
-                .write_interleaved(&mut self.output_ctx)
-                .unwrap();
+            self.muxer
+                .write_packet(&encoded_packet, self.encoder.time_base())?;
         }
+
+        Ok(())
     }
 
-    fn finish(&mut self) {
-        self.encoder.send_eof().unwrap();
-        self.process_frame();
-        self.output_ctx.write_trailer().unwrap();
+    fn finish(&mut self) -> Result<(), MediaError> {
+        // The trailers are written even if the last frames aren't, so that the outputs that
+        // are still fine end up playable.
+        let flushed = self
+            .encoder
+            .send_eof()
+            .map_err(MediaError::from)
+            .and_then(|_| self.process_frame());
+        let finished = self.muxer.write_trailer();
+
+        flushed.and(finished)
     }
 }
 
@@ -87,41 +145,113 @@ impl PipelineSinkTask for H264Encoder {
     fn run(
         &mut self,
         ready_signal: crate::pipeline::task::PipelineReadySignal,
+        messages: PipelineMessages,
         input: flume::Receiver<Self::Input>,
     ) {
         println!("Starting {} video encoding thread", self.tag);
+        self.muxer.set_status_reporter(messages.reporter());
         ready_signal.send(Ok(())).unwrap();
 
         while let Ok(frame) = input.recv() {
-            self.queue_frame(frame);
-            self.process_frame();
+            self.handle_messages(&messages);
+
+            if let Err(error) = self.queue_frame(frame).and_then(|_| self.process_frame()) {
+                eprintln!("Failed to encode {} frame: {error}", self.tag);
+            }
         }
 
         println!("Received last {} frame. Finishing up encoding.", self.tag);
-        self.finish();
+        if let Err(error) = self.finish() {
+            eprintln!("Failed to finish {} encoding: {error}", self.tag);
+        }
 
         println!("Shutting down {} video encoding thread", self.tag);
     }
 }
 
-fn get_codec_and_options(config: &VideoInfo) -> Result<(Codec, Dictionary), MediaError> {
-    if let Some(codec) = encoder::find_by_name("libx264") {
-        let mut options = Dictionary::new();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        data::RawVideoFormat,
+        encoders::{LiveStream, ReconnectPolicy, StreamOutput},
+        filters::{VideoFilter, VideoFilterOp},
+    };
 
-        let keyframe_interval_secs = 2;
-        let keyframe_interval = keyframe_interval_secs * config.frame_rate.numerator();
-        let keyframe_interval_str = keyframe_interval.to_string();
+    #[test]
+    fn rotated_video_keeps_its_duration() {
+        ffmpeg::init().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rotated.mp4");
+        let input = VideoInfo::from_raw(RawVideoFormat::Bgra, 64, 48, 30);
 
-        options.set("preset", "ultrafast");
-        options.set("tune", "zerolatency");
-        options.set("vsync", "1");
-        options.set("g", &keyframe_interval_str);
-        options.set("keyint_min", &keyframe_interval_str);
-        // TODO: Is it worth limiting quality? Maybe make this configurable
-        // options.set("crf", "23");
+        // Only rotates, so the frame rate is never converted by an `fps` operation.
+        let mut filter = VideoFilter::with_ops(
+            "test",
+            input,
+            &[VideoFilterOp::Rotate { degrees: 90.0 }],
+            Pixel::YUV420P,
+        )
+        .unwrap();
+        let mut encoder = H264Encoder::init(
+            "test",
+            filter.output_info(),
+            Output::File(path.clone()),
+            &EncoderSettings::default(),
+        )
+        .unwrap();
 
-        return Ok((codec, options));
+        let (tx, rx) = flume::unbounded();
+        for index in 0..30 {
+            let frame = input.wrap_frame(&[], index * 1_000_000 / 30);
+            filter.queue_frame(frame).unwrap();
+            filter.process_frame(&tx).unwrap();
+        }
+        filter.finish(&tx).unwrap();
+        drop(tx);
+
+        for frame in rx.iter() {
+            encoder.queue_frame(frame).unwrap();
+            encoder.process_frame().unwrap();
+        }
+        encoder.finish().unwrap();
+
+        let duration = ffmpeg::format::input(&path).unwrap().duration();
+        assert!((900_000..=1_100_000).contains(&duration), "{duration}");
     }
 
-    Err(MediaError::MissingCodec("H264 video"))
+    /// Streams a few seconds of video to a local server, e.g.
+    /// `CAP_TEST_STREAM_URL=rtmp://localhost/live/test cargo test -- --ignored`
+    #[test]
+    #[ignore = "needs a running RTMP or SRT server"]
+    fn streams_to_local_server() {
+        ffmpeg::init().unwrap();
+        let url = std::env::var("CAP_TEST_STREAM_URL").unwrap();
+        let info = VideoInfo::from_raw(RawVideoFormat::Gray, 640, 360, 30).scaled(640, 30);
+        let stream = LiveStream::new(StreamOutput {
+            url,
+            max_bitrate_kbps: Some(1_000),
+            reconnect: ReconnectPolicy::default(),
+        })
+        .unwrap();
+
+        let mut encoder = H264Encoder::init(
+            "test",
+            info,
+            Output::Stream(stream.clone()),
+            &EncoderSettings::default(),
+        )
+        .unwrap();
+        stream.start().unwrap();
+        drop(stream);
+
+        for index in 0..90 {
+            let mut frame = FFVideo::new(info.pixel_format, info.width, info.height);
+            frame.data_mut(0).fill((index * 2) as u8);
+            frame.set_pts(Some(index));
+            encoder.queue_frame(frame).unwrap();
+            encoder.process_frame().unwrap();
+        }
+        encoder.finish().unwrap();
+    }
 }
@@ -1,11 +1,13 @@
-use std::path::PathBuf;
-
+mod audio;
 mod h264;
-mod mp3;
+mod output;
+mod replay;
+mod settings;
+mod stream;
 
+pub use audio::*;
 pub use h264::*;
-pub use mp3::*;
-
-pub enum Output {
-    File(PathBuf),
-}
+pub use output::*;
+pub use replay::*;
+pub use settings::*;
+pub use stream::*;
@@ -0,0 +1,365 @@
+use std::path::PathBuf;
+
+use ffmpeg::{
+    codec::{codec::Codec, Parameters},
+    format, Dictionary,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    data::{FFPacket, FFRational},
+    pipeline::control::StatusReporter,
+    MediaError,
+};
+
+use super::{LiveStream, StreamTrack};
+
+pub enum Output {
+    File(PathBuf),
+    /// Rolling HLS segments and a `stream.m3u8` playlist, written to a directory.
+    Segmented(SegmentedOutput),
+    /// A track of a live stream to an RTMP or SRT server, which may be shared with other
+    /// encoders.
+    Stream(LiveStream),
+    /// Writes the same encoded stream to several outputs, e.g. a file for editing and
+    /// segments for uploading while recording.
+    Tee(Vec<Output>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SegmentFormat {
+    #[default]
+    MpegTs,
+    Fmp4,
+}
+
+#[derive(Debug, Clone)]
+pub struct SegmentedOutput {
+    pub dir: PathBuf,
+    pub format: SegmentFormat,
+    /// Target length of each segment. Segments can only be cut on keyframes, so this should
+    /// be a multiple of the encoder's keyframe interval.
+    pub segment_duration_secs: u32,
+    /// Keeps only this many of the newest segments, deleting older ones, so the directory
+    /// works as a ring buffer. Every segment is kept if unset.
+    pub max_segments: Option<u32>,
+}
+
+impl SegmentedOutput {
+    pub const PLAYLIST_NAME: &'static str = "stream.m3u8";
+
+    pub fn playlist_path(&self) -> PathBuf {
+        self.dir.join(Self::PLAYLIST_NAME)
+    }
+
+    fn open(&self) -> Result<(format::context::Output, Dictionary<'static>), MediaError> {
+        std::fs::create_dir_all(&self.dir)
+            .map_err(|_| MediaError::Any("Failed to create the segment directory"))?;
+
+        let segment_extension = match self.format {
+            SegmentFormat::MpegTs => "ts",
+            SegmentFormat::Fmp4 => "m4s",
+        };
+        let segment_pattern = self.dir.join(format!("segment_%05d.{segment_extension}"));
+
+        let mut options = Dictionary::new();
+        options.set("hls_time", &self.segment_duration_secs.to_string());
+        // Only list segments once they're complete.
+        match self.max_segments {
+            Some(max_segments) => {
+                options.set("hls_list_size", &max_segments.to_string());
+                options.set(
+                    "hls_flags",
+                    "independent_segments+temp_file+delete_segments",
+                );
+            }
+            None => {
+                options.set("hls_list_size", "0");
+                options.set("hls_playlist_type", "event");
+                options.set("hls_flags", "independent_segments+temp_file");
+            }
+        }
+        options.set("hls_segment_filename", &segment_pattern.to_string_lossy());
+        match self.format {
+            SegmentFormat::MpegTs => options.set("hls_segment_type", "mpegts"),
+            SegmentFormat::Fmp4 => {
+                options.set("hls_segment_type", "fmp4");
+                options.set("hls_fmp4_init_filename", "init.mp4");
+            }
+        }
+
+        let output_ctx = format::output_as(&self.playlist_path(), "hls")?;
+
+        Ok((output_ctx, options))
+    }
+}
+
+/// The containers an encoder writes its packets to.
+pub(super) struct Muxer {
+    outputs: Vec<(format::context::Output, Dictionary<'static>)>,
+    streams: Vec<LiveStream>,
+    tracks: Vec<StreamTrack>,
+}
+
+impl Muxer {
+    pub fn open(output: Output) -> Result<Self, MediaError> {
+        let mut muxer = Self {
+            outputs: vec![],
+            streams: vec![],
+            tracks: vec![],
+        };
+        muxer.open_into(output)?;
+
+        if muxer.outputs.is_empty() && muxer.streams.is_empty() {
+            return Err(MediaError::Any("Encoders need at least one output"));
+        }
+
+        Ok(muxer)
+    }
+
+    fn open_into(&mut self, output: Output) -> Result<(), MediaError> {
+        match output {
+            Output::File(path) => self
+                .outputs
+                .push((format::output(&path)?, Dictionary::new())),
+            Output::Segmented(segmented) => self.outputs.push(segmented.open()?),
+            Output::Stream(stream) => self.streams.push(stream),
+            Output::Tee(tee) => {
+                for output in tee {
+                    self.open_into(output)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn needs_global_header(&self) -> bool {
+        self.outputs.iter().any(|(output_ctx, _)| {
+            output_ctx
+                .format()
+                .flags()
+                .contains(format::Flags::GLOBAL_HEADER)
+        }) || self.streams.iter().any(LiveStream::needs_global_header)
+    }
+
+    /// The lowest bitrate cap of any live stream, in bits per second.
+    pub fn max_bit_rate(&self) -> Option<usize> {
+        self.streams
+            .iter()
+            .filter_map(LiveStream::max_bit_rate)
+            .min()
+    }
+
+    /// Live streams report their connection status through the pipeline once it's running.
+    pub fn set_status_reporter(&mut self, reporter: StatusReporter) {
+        for stream in &self.streams {
+            stream.set_status_reporter(reporter.clone());
+        }
+    }
+
+    /// Whether a live stream has just (re)connected and is waiting for a keyframe.
+    pub fn take_keyframe_request(&mut self) -> bool {
+        self.streams.iter().fold(false, |requested, stream| {
+            stream.take_keyframe_request() | requested
+        })
+    }
+
+    pub fn add_stream(
+        &mut self,
+        codec: Codec,
+        time_base: FFRational,
+        parameters: Parameters,
+    ) -> Result<(), MediaError> {
+        for (output_ctx, _) in self.outputs.iter_mut() {
+            let mut output_stream = output_ctx.add_stream(codec)?;
+            output_stream.set_time_base(time_base);
+            output_stream.set_parameters(parameters.clone());
+        }
+        for stream in &self.streams {
+            self.tracks
+                .push(stream.add_track(codec, time_base, parameters.clone())?);
+        }
+
+        Ok(())
+    }
+
+    pub fn write_header(&mut self) -> Result<(), MediaError> {
+        for (output_ctx, options) in self.outputs.iter_mut() {
+            output_ctx.write_header_with(std::mem::replace(options, Dictionary::new()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the packet to every output. An output that fails, e.g. because the disk is full,
+    /// is closed and the error returned, while the others carry on: FFmpeg's muxers don't
+    /// recover from failed writes.
+    pub fn write_packet(
+        &mut self,
+        packet: &FFPacket,
+        time_base: FFRational,
+    ) -> Result<(), MediaError> {
+        let mut result = Ok(());
+
+        self.outputs.retain_mut(|(output_ctx, _)| {
+            let mut packet = packet.clone();
+            packet.set_stream(0);
+            packet.rescale_ts(time_base, output_ctx.stream(0).unwrap().time_base());
+
+            match packet.write_interleaved(output_ctx) {
+                Ok(()) => true,
+                Err(error) => {
+                    if result.is_ok() {
+                        result = Err(error.into());
+                    }
+                    false
+                }
+            }
+        });
+        for track in &self.tracks {
+            track.write_packet(packet, time_base);
+        }
+
+        result
+    }
+
+    /// Finishes every output, even if some of them fail to, returning the first error.
+    pub fn write_trailer(&mut self) -> Result<(), MediaError> {
+        let mut result = Ok(());
+
+        for (mut output_ctx, _) in self.outputs.drain(..) {
+            if let Err(error) = output_ctx.write_trailer() {
+                if result.is_ok() {
+                    result = Err(error.into());
+                }
+            }
+        }
+        // Live streams end once every encoder writing to them is done.
+        self.streams.clear();
+        self.tracks.clear();
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ffmpeg::{codec, encoder, format::Pixel};
+
+    use super::*;
+    use crate::{data::FFVideo, encoders::replay::parse_playlist};
+
+    /// Writes `seconds` of 10 fps video with a keyframe every second to `segments`.
+    fn write_segments(segments: &SegmentedOutput, seconds: u32) {
+        ffmpeg::init().unwrap();
+        let time_base = FFRational(1, 10);
+
+        let codec = encoder::find(codec::Id::MPEG4).unwrap();
+        let mut encoder = codec::context::Context::new_with_codec(codec)
+            .encoder()
+            .video()
+            .unwrap();
+        encoder.set_width(64);
+        encoder.set_height(48);
+        encoder.set_format(Pixel::YUV420P);
+        encoder.set_time_base(time_base);
+        encoder.set_gop(10);
+        let mut encoder = encoder.open().unwrap();
+
+        let mut muxer = Muxer::open(Output::Segmented(segments.clone())).unwrap();
+        muxer
+            .add_stream(codec, time_base, Parameters::from(&encoder))
+            .unwrap();
+        muxer.write_header().unwrap();
+
+        let mut write_packets = |encoder: &mut encoder::Video| {
+            let mut packet = FFPacket::empty();
+            while encoder.receive_packet(&mut packet).is_ok() {
+                muxer.write_packet(&packet, time_base).unwrap();
+            }
+        };
+        for index in 0..seconds as i64 * 10 {
+            let mut frame = FFVideo::new(Pixel::YUV420P, 64, 48);
+            for plane in 0..frame.planes() {
+                frame.data_mut(plane).fill((index * 4) as u8);
+            }
+            frame.set_pts(Some(index));
+            encoder.send_frame(&frame).unwrap();
+            write_packets(&mut encoder);
+        }
+        encoder.send_eof().unwrap();
+        write_packets(&mut encoder);
+
+        muxer.write_trailer().unwrap();
+    }
+
+    fn segments_in(dir: &std::path::Path) -> Vec<String> {
+        let mut files = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|name| name.ends_with(".ts"))
+            .collect::<Vec<_>>();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn segments_are_listed_once_written() {
+        let dir = tempfile::tempdir().unwrap();
+        let segments = SegmentedOutput {
+            dir: dir.path().join("segments"),
+            format: SegmentFormat::MpegTs,
+            segment_duration_secs: 1,
+            max_segments: None,
+        };
+        write_segments(&segments, 3);
+
+        let playlist = std::fs::read_to_string(segments.playlist_path()).unwrap();
+        assert!(playlist.contains("#EXT-X-ENDLIST"), "{playlist}");
+
+        let entries = parse_playlist(&playlist);
+        assert_eq!(
+            entries
+                .iter()
+                .map(|entry| entry.file.as_str())
+                .collect::<Vec<_>>(),
+            ["segment_00000.ts", "segment_00001.ts", "segment_00002.ts"]
+        );
+        let duration_secs = entries.iter().map(|entry| entry.duration_secs).sum::<f64>();
+        assert!((duration_secs - 3.0).abs() < 0.2, "{duration_secs}");
+        assert_eq!(
+            segments_in(&segments.dir),
+            ["segment_00000.ts", "segment_00001.ts", "segment_00002.ts"]
+        );
+    }
+
+    #[test]
+    fn ring_of_segments_deletes_the_oldest() {
+        let dir = tempfile::tempdir().unwrap();
+        let segments = SegmentedOutput {
+            dir: dir.path().to_owned(),
+            format: SegmentFormat::MpegTs,
+            segment_duration_secs: 1,
+            max_segments: Some(2),
+        };
+        write_segments(&segments, 6);
+
+        let playlist = std::fs::read_to_string(segments.playlist_path()).unwrap();
+        let files = parse_playlist(&playlist)
+            .into_iter()
+            .map(|entry| entry.file)
+            .collect::<Vec<_>>();
+        assert_eq!(files, ["segment_00004.ts", "segment_00005.ts"]);
+
+        // FFmpeg keeps one segment past the playlist around for players still fetching it
+        let on_disk = segments_in(&segments.dir);
+        assert!(
+            !on_disk.contains(&"segment_00000.ts".to_string()),
+            "{on_disk:?}"
+        );
+        assert!(on_disk.len() <= 3, "{on_disk:?}");
+        assert!(files.iter().all(|file| on_disk.contains(file)));
+    }
+}
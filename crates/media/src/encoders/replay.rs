@@ -0,0 +1,234 @@
+use ffmpeg::{codec, encoder, format};
+use std::path::{Path, PathBuf};
+
+use crate::{data::FFRational, MediaError};
+
+use super::{SegmentFormat, SegmentedOutput};
+
+/// Where and how much of a replay buffer is kept. Each source's encoder writes a ring of
+/// segments to its own subdirectory, from which the last moments can be saved on demand.
+#[derive(Debug, Clone)]
+pub struct ReplayOptions {
+    pub dir: PathBuf,
+    /// How far back a replay can reach.
+    pub duration_secs: u32,
+    /// Target length of each segment. Replays start on a segment boundary, and the segment
+    /// being written when a replay is saved isn't part of it.
+    pub segment_duration_secs: u32,
+}
+
+impl ReplayOptions {
+    /// The ring of segments the encoder for `tag` writes to.
+    pub fn segments_for(&self, tag: &str) -> SegmentedOutput {
+        let segment_duration_secs = self.segment_duration_secs.max(1);
+
+        SegmentedOutput {
+            dir: self.dir.join(tag),
+            format: SegmentFormat::MpegTs,
+            segment_duration_secs,
+            // One more than needed, as the oldest segment rarely starts exactly on time.
+            max_segments: Some(self.duration_secs.div_ceil(segment_duration_secs) + 1),
+        }
+    }
+}
+
+/// Which part of a replay buffer to save.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplayStart {
+    /// The newest segments covering at least this many seconds.
+    LastSeconds(f64),
+    /// Everything from this stream time onwards, e.g. to line the microphone up with a
+    /// replay of the screen.
+    From(f64),
+}
+
+/// The part of a replay buffer that was saved, in seconds of stream time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReplayClip {
+    pub start_secs: f64,
+    pub duration_secs: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct PlaylistEntry {
+    pub duration_secs: f64,
+    pub file: String,
+}
+
+/// Reads the segments listed in an HLS media playlist, oldest first.
+pub(super) fn parse_playlist(contents: &str) -> Vec<PlaylistEntry> {
+    let mut entries = vec![];
+    let mut duration_secs = None;
+
+    for line in contents.lines().map(str::trim) {
+        if let Some(info) = line.strip_prefix("#EXTINF:") {
+            duration_secs = info.split(',').next().and_then(|value| value.parse().ok());
+        } else if !line.is_empty() && !line.starts_with('#') {
+            if let Some(duration_secs) = duration_secs.take() {
+                entries.push(PlaylistEntry {
+                    duration_secs,
+                    file: line.to_string(),
+                });
+            }
+        }
+    }
+
+    entries
+}
+
+/// The newest entries that together cover at least `seconds`, or all of them if they don't.
+fn last_entries(entries: &[PlaylistEntry], seconds: f64) -> &[PlaylistEntry] {
+    let mut first = entries.len();
+    let mut covered = 0.0;
+
+    while first > 0 && covered < seconds {
+        first -= 1;
+        covered += entries[first].duration_secs;
+    }
+
+    &entries[first..]
+}
+
+/// Remuxes part of a replay buffer into a single file, without re-encoding it. The saved
+/// clip starts at zero, whatever stream time it was taken from.
+pub fn save_replay(
+    segments: &SegmentedOutput,
+    start: ReplayStart,
+    output: &Path,
+) -> Result<ReplayClip, MediaError> {
+    let playlist = std::fs::read_to_string(segments.playlist_path())
+        .map_err(|_| MediaError::Any("The replay buffer has no segments yet"))?;
+    let entries = parse_playlist(&playlist);
+    let entries = match start {
+        ReplayStart::LastSeconds(seconds) => last_entries(&entries, seconds),
+        ReplayStart::From(_) => &entries[..],
+    };
+    if entries.is_empty() {
+        return Err(MediaError::Any("The replay buffer has no segments yet"));
+    }
+
+    let mut output_ctx = format::output(&output)?;
+    // Set up from the first segment, as every segment of a ring holds the same stream.
+    let mut time_bases: Option<(FFRational, FFRational)> = None;
+    let mut offset = None;
+    let mut end = None;
+
+    for entry in entries {
+        let path = segments.dir.join(&entry.file);
+        let mut input = match format::input(&path) {
+            Ok(input) => input,
+            // The oldest segments are deleted as new ones are written, which can happen
+            // between reading the playlist and getting to them.
+            Err(_) if !path.exists() => continue,
+            Err(error) => return Err(error.into()),
+        };
+        let stream = input
+            .streams()
+            .next()
+            .ok_or(MediaError::Any("Replay segment has no streams"))?;
+        let stream_index = stream.index();
+
+        let (input_time_base, output_time_base) = match time_bases {
+            Some(time_bases) => time_bases,
+            None => {
+                let mut output_stream = output_ctx.add_stream(encoder::find(codec::Id::None))?;
+                output_stream.set_parameters(stream.parameters());
+                // MPEG-TS codec tags mean nothing to other containers.
+                unsafe {
+                    (*output_stream.parameters().as_mut_ptr()).codec_tag = 0;
+                }
+                output_ctx.write_header()?;
+
+                let output_time_base = output_ctx.stream(0).unwrap().time_base();
+                *time_bases.insert((stream.time_base(), output_time_base))
+            }
+        };
+        let to_timestamp = |secs: f64| {
+            (secs * input_time_base.denominator() as f64 / input_time_base.numerator() as f64)
+                .round() as i64
+        };
+
+        for (stream, mut packet) in input.packets() {
+            if stream.index() != stream_index {
+                continue;
+            }
+            let Some(pts) = packet.pts() else {
+                continue;
+            };
+
+            let offset = *offset.get_or_insert(match start {
+                ReplayStart::LastSeconds(_) => packet.dts().unwrap_or(pts),
+                ReplayStart::From(secs) => to_timestamp(secs),
+            });
+            if pts < offset {
+                continue;
+            }
+            end = end.max(Some(pts + packet.duration()));
+
+            packet.set_pts(Some(pts - offset));
+            packet.set_dts(packet.dts().map(|dts| dts - offset));
+            packet.set_position(-1);
+            packet.set_stream(0);
+            packet.rescale_ts(input_time_base, output_time_base);
+            packet.write_interleaved(&mut output_ctx)?;
+        }
+    }
+
+    let Some((input_time_base, _)) = time_bases else {
+        return Err(MediaError::Any("The replay buffer has no segments yet"));
+    };
+    output_ctx.write_trailer()?;
+
+    let to_secs = |timestamp: i64| timestamp as f64 * f64::from(input_time_base);
+    let offset = offset.unwrap_or_default();
+
+    Ok(ReplayClip {
+        start_secs: to_secs(offset),
+        duration_secs: to_secs(end.unwrap_or(offset) - offset),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAYLIST: &str = "#EXTM3U
+#EXT-X-VERSION:3
+#EXT-X-TARGETDURATION:2
+#EXT-X-MEDIA-SEQUENCE:14
+#EXT-X-INDEPENDENT-SEGMENTS
+#EXTINF:2.000000,
+segment_00014.ts
+#EXTINF:2.033333,
+segment_00015.ts
+#EXTINF:1.966667,
+segment_00016.ts
+";
+
+    #[test]
+    fn replays_cover_at_least_the_requested_time() {
+        let entries = parse_playlist(PLAYLIST);
+        assert_eq!(
+            entries,
+            vec![
+                PlaylistEntry {
+                    duration_secs: 2.0,
+                    file: "segment_00014.ts".into()
+                },
+                PlaylistEntry {
+                    duration_secs: 2.033333,
+                    file: "segment_00015.ts".into()
+                },
+                PlaylistEntry {
+                    duration_secs: 1.966667,
+                    file: "segment_00016.ts".into()
+                },
+            ]
+        );
+
+        assert_eq!(last_entries(&entries, 1.0), &entries[2..]);
+        assert_eq!(last_entries(&entries, 3.0), &entries[1..]);
+        assert_eq!(last_entries(&entries, 60.0), &entries[..]);
+        assert!(last_entries(&[], 5.0).is_empty());
+    }
+}
@@ -0,0 +1,210 @@
+use ffmpeg::{
+    codec::{codec::Codec, encoder},
+    Dictionary,
+};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+use crate::{
+    data::{FFRational, VideoInfo},
+    MediaError,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub enum VideoCodec {
+    #[default]
+    H264,
+    H265,
+    Vp9,
+    Av1,
+    /// Lossless H.264. Produces very large files.
+    Lossless,
+}
+
+impl VideoCodec {
+    fn encoder_name(&self) -> &'static str {
+        match self {
+            Self::H264 | Self::Lossless => "libx264",
+            Self::H265 => "libx265",
+            Self::Vp9 => "libvpx-vp9",
+            Self::Av1 => "libsvtav1",
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self {
+            Self::H264 => "H264 video",
+            Self::H265 => "H265 video",
+            Self::Vp9 => "VP9 video",
+            Self::Av1 => "AV1 video",
+            Self::Lossless => "lossless H264 video",
+        }
+    }
+}
+
+/// Trades encoding speed for compression. Mapped to the closest equivalent for each codec.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub enum EncoderPreset {
+    #[default]
+    Fastest,
+    Fast,
+    Balanced,
+    Quality,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase", tag = "mode")]
+pub enum RateControl {
+    /// Whatever the codec does when left alone.
+    #[default]
+    CodecDefault,
+    /// Constant quality. Lower values mean higher quality.
+    Crf { value: u8 },
+    /// Average bitrate in kilobits per second.
+    Bitrate { kbps: u32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct EncoderSettings {
+    #[serde(default)]
+    pub codec: VideoCodec,
+    #[serde(default)]
+    pub preset: EncoderPreset,
+    #[serde(default)]
+    pub rate_control: RateControl,
+    #[serde(default = "default_keyframe_interval")]
+    pub keyframe_interval_secs: u32,
+    /// Encoder threads. Uses every available core if unset.
+    #[serde(default)]
+    pub threads: Option<u32>,
+}
+
+fn default_keyframe_interval() -> u32 {
+    2
+}
+
+impl Default for EncoderSettings {
+    fn default() -> Self {
+        Self {
+            codec: VideoCodec::default(),
+            preset: EncoderPreset::default(),
+            rate_control: RateControl::default(),
+            keyframe_interval_secs: default_keyframe_interval(),
+            threads: None,
+        }
+    }
+}
+
+impl EncoderSettings {
+    pub fn thread_count(&self) -> usize {
+        match self.threads {
+            Some(threads) => threads.max(1) as usize,
+            None => std::thread::available_parallelism()
+                .map(usize::from)
+                .unwrap_or(4),
+        }
+    }
+
+    /// Average bitrate to configure on the encoder context, if any.
+    pub fn bit_rate(&self) -> Option<usize> {
+        match self.rate_control {
+            RateControl::Bitrate { kbps } if self.codec != VideoCodec::Lossless => {
+                Some(kbps as usize * 1000)
+            }
+            _ => None,
+        }
+    }
+
+    /// Frames between keyframes. Rounded, as frame rates like 30000/1001 aren't whole.
+    fn keyframe_interval(&self, frame_rate: FFRational) -> u32 {
+        let frames = self.keyframe_interval_secs.max(1) as f64 * f64::from(frame_rate);
+
+        frames.round().max(1.0) as u32
+    }
+
+    pub(super) fn codec_and_options(
+        &self,
+        config: &VideoInfo,
+    ) -> Result<(Codec, Dictionary<'static>), MediaError> {
+        let codec = encoder::find_by_name(self.codec.encoder_name())
+            .ok_or(MediaError::MissingCodec(self.codec.description()))?;
+
+        let mut options = Dictionary::new();
+
+        let keyframe_interval_str = self.keyframe_interval(config.frame_rate).to_string();
+        options.set("g", &keyframe_interval_str);
+
+        match self.codec {
+            VideoCodec::H264 | VideoCodec::H265 | VideoCodec::Lossless => {
+                options.set(
+                    "preset",
+                    match self.preset {
+                        EncoderPreset::Fastest => "ultrafast",
+                        EncoderPreset::Fast => "veryfast",
+                        EncoderPreset::Balanced => "medium",
+                        EncoderPreset::Quality => "slow",
+                    },
+                );
+                options.set("tune", "zerolatency");
+                options.set("keyint_min", &keyframe_interval_str);
+
+                if self.codec == VideoCodec::H264 {
+                    options.set("vsync", "1");
+                }
+            }
+            VideoCodec::Vp9 => {
+                let (deadline, cpu_used) = match self.preset {
+                    EncoderPreset::Fastest => ("realtime", "8"),
+                    EncoderPreset::Fast => ("realtime", "6"),
+                    EncoderPreset::Balanced => ("good", "4"),
+                    EncoderPreset::Quality => ("good", "1"),
+                };
+                options.set("deadline", deadline);
+                options.set("cpu-used", cpu_used);
+                options.set("row-mt", "1");
+            }
+            VideoCodec::Av1 => {
+                options.set(
+                    "preset",
+                    match self.preset {
+                        EncoderPreset::Fastest => "12",
+                        EncoderPreset::Fast => "10",
+                        EncoderPreset::Balanced => "8",
+                        EncoderPreset::Quality => "5",
+                    },
+                );
+            }
+        }
+
+        match (self.codec, self.rate_control) {
+            (VideoCodec::Lossless, _) => options.set("qp", "0"),
+            (_, RateControl::Crf { value }) => options.set("crf", &value.to_string()),
+            _ => {}
+        }
+
+        Ok((codec, options))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keyframe_interval_follows_fractional_frame_rates() {
+        let settings = EncoderSettings::default();
+        assert_eq!(settings.keyframe_interval(FFRational(30, 1)), 60);
+        assert_eq!(settings.keyframe_interval(FFRational(30_000, 1_001)), 60);
+        assert_eq!(settings.keyframe_interval(FFRational(60_000, 1_001)), 120);
+
+        let settings = EncoderSettings {
+            keyframe_interval_secs: 0,
+            ..settings
+        };
+        assert_eq!(settings.keyframe_interval(FFRational(24_000, 1_001)), 24);
+        assert_eq!(settings.keyframe_interval(FFRational(1, 10)), 1);
+    }
+}
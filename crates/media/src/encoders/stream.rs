@@ -0,0 +1,518 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use ffmpeg::{
+    codec::{codec::Codec, Parameters},
+    format, Dictionary, Rescale,
+};
+use flume::{Receiver, RecvTimeoutError, Sender};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+use crate::{
+    data::{FFPacket, FFRational},
+    pipeline::control::{PipelineStatus, StatusReporter},
+    MediaError,
+};
+
+use super::AudioCodec;
+
+/// How long a network read or write may stall before the connection is considered lost,
+/// in microseconds.
+const IO_TIMEOUT_MICROS: &str = "5000000";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub enum StreamProtocol {
+    Rtmp,
+    Srt,
+}
+
+impl StreamProtocol {
+    pub fn from_url(url: &str) -> Option<Self> {
+        let (scheme, _) = url.split_once("://")?;
+
+        match scheme.to_ascii_lowercase().as_str() {
+            "rtmp" | "rtmps" => Some(Self::Rtmp),
+            "srt" => Some(Self::Srt),
+            _ => None,
+        }
+    }
+
+    fn format_name(&self) -> &'static str {
+        match self {
+            Self::Rtmp => "flv",
+            Self::Srt => "mpegts",
+        }
+    }
+}
+
+/// Exponential backoff between attempts to reconnect a dropped stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct ReconnectPolicy {
+    pub initial_delay_ms: u32,
+    pub max_delay_ms: u32,
+    /// Gives up after this many failed attempts in a row. Retries forever if unset.
+    #[serde(default)]
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay_ms: 500,
+            max_delay_ms: 30_000,
+            max_attempts: None,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// How long to wait before the given attempt, counting from 1.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 1u64 << attempt.saturating_sub(1).min(32);
+        let delay_ms = (self.initial_delay_ms as u64)
+            .saturating_mul(factor)
+            .min(self.max_delay_ms as u64);
+
+        Duration::from_millis(delay_ms)
+    }
+
+    fn gives_up_after(&self, attempt: u32) -> bool {
+        self.max_attempts.is_some_and(|max| attempt >= max)
+    }
+}
+
+/// Where to send a [`LiveStream`]: an RTMP or SRT ingest server.
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamOutput {
+    /// e.g. `rtmp://localhost/live/key` or `srt://localhost:9000?streamid=publish:key`
+    pub url: String,
+    /// Caps the video bitrate so that the stream fits the uplink, in kilobits per second.
+    #[serde(default)]
+    pub max_bitrate_kbps: Option<u32>,
+    #[serde(default)]
+    pub reconnect: ReconnectPolicy,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Type)]
+#[serde(
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase",
+    tag = "state"
+)]
+pub enum StreamStatus {
+    Connecting {
+        attempt: u32,
+    },
+    Connected,
+    /// The connection was lost. Packets are dropped until it's re-established.
+    Disconnected {
+        reason: String,
+        retry_in_ms: u32,
+    },
+    /// The reconnect policy's attempts have been used up. Nothing more will be sent.
+    GaveUp {
+        reason: String,
+    },
+}
+
+/// How many packets can wait for the connection before new ones are dropped.
+const PACKET_QUEUE_SIZE: usize = 512;
+
+enum StreamEvent {
+    Packet {
+        track: usize,
+        time_base: FFRational,
+        packet: FFPacket,
+    },
+    Reporter(StatusReporter),
+}
+
+/// A live stream shared by the encoders of a recording, so that the screen and the
+/// microphone go out over a single connection. Each encoder adds its track, after which
+/// [`LiveStream::start`] connects. Writing and reconnecting happen on a thread of their own,
+/// so a stalled or dropped connection never holds up encoding: packets are dropped instead.
+#[derive(Clone)]
+pub struct LiveStream {
+    protocol: StreamProtocol,
+    max_bitrate_kbps: Option<u32>,
+    /// Taken by `start`, once every track has been added.
+    sink: Arc<Mutex<Option<StreamSink>>>,
+    events: Sender<StreamEvent>,
+    keyframe_requested: Arc<AtomicBool>,
+}
+
+impl LiveStream {
+    pub fn new(output: StreamOutput) -> Result<Self, MediaError> {
+        let protocol = StreamProtocol::from_url(&output.url).ok_or(MediaError::Any(
+            "Unsupported streaming protocol. Use an rtmp:// or srt:// URL",
+        ))?;
+        let (events, events_rx) = flume::bounded(PACKET_QUEUE_SIZE);
+        let keyframe_requested = Arc::new(AtomicBool::new(false));
+
+        Ok(Self {
+            protocol,
+            max_bitrate_kbps: output.max_bitrate_kbps,
+            sink: Arc::new(Mutex::new(Some(StreamSink {
+                output,
+                protocol,
+                streams: vec![],
+                events: events_rx,
+                connection: None,
+                attempt: 0,
+                retry_at: None,
+                gave_up: false,
+                ts_offset_micros: None,
+                waiting_for_keyframe: vec![],
+                keyframe_requested: keyframe_requested.clone(),
+                reporter: None,
+            }))),
+            events,
+            keyframe_requested,
+        })
+    }
+
+    pub fn needs_global_header(&self) -> bool {
+        self.protocol == StreamProtocol::Rtmp
+    }
+
+    /// Caps the video bitrate, in bits per second.
+    pub fn max_bit_rate(&self) -> Option<usize> {
+        self.max_bitrate_kbps.map(|kbps| kbps as usize * 1000)
+    }
+
+    /// Whether the stream's container can carry audio in `codec`.
+    pub fn accepts(&self, codec: AudioCodec) -> bool {
+        match self.protocol {
+            StreamProtocol::Rtmp => !matches!(codec, AudioCodec::Opus | AudioCodec::Pcm),
+            StreamProtocol::Srt => codec != AudioCodec::Pcm,
+        }
+    }
+
+    /// Adds an encoder's stream to the connection. Only possible before it's started.
+    pub fn add_track(
+        &self,
+        codec: Codec,
+        time_base: FFRational,
+        parameters: Parameters,
+    ) -> Result<StreamTrack, MediaError> {
+        let mut sink = self.sink.lock().unwrap();
+        let sink = sink
+            .as_mut()
+            .ok_or(MediaError::Any("Live stream has already started"))?;
+        sink.streams.push((codec, time_base, parameters));
+
+        Ok(StreamTrack {
+            index: sink.streams.len() - 1,
+            events: self.events.clone(),
+        })
+    }
+
+    /// Opens the connection and hands it to its own thread, which carries on until every
+    /// track is gone. Failing here fails the recording, since the destination is most likely
+    /// misconfigured.
+    pub fn start(&self) -> Result<(), MediaError> {
+        let mut sink = self
+            .sink
+            .lock()
+            .unwrap()
+            .take()
+            .ok_or(MediaError::Any("Live stream has already started"))?;
+        sink.connect()?;
+        println!("Connected stream to {}", sink.output.url);
+
+        std::thread::spawn(move || sink.run());
+
+        Ok(())
+    }
+
+    /// Live streams report their connection status through the pipeline once it's running.
+    pub fn set_status_reporter(&self, reporter: StatusReporter) {
+        let _ = self.events.send(StreamEvent::Reporter(reporter));
+    }
+
+    /// Whether the stream has just (re)connected and is waiting for a keyframe.
+    pub fn take_keyframe_request(&self) -> bool {
+        self.keyframe_requested.swap(false, Ordering::AcqRel)
+    }
+}
+
+/// An encoder's way into a [`LiveStream`].
+pub struct StreamTrack {
+    index: usize,
+    events: Sender<StreamEvent>,
+}
+
+impl StreamTrack {
+    /// Queues the packet for the connection, dropping it if the queue is full.
+    pub fn write_packet(&self, packet: &FFPacket, time_base: FFRational) {
+        let _ = self.events.try_send(StreamEvent::Packet {
+            track: self.index,
+            time_base,
+            packet: packet.clone(),
+        });
+    }
+}
+
+/// The connection side of a [`LiveStream`]. Unlike files, a failed write doesn't fail the
+/// recording: the connection is dropped and retried with backoff while packets are discarded.
+struct StreamSink {
+    output: StreamOutput,
+    protocol: StreamProtocol,
+    streams: Vec<(Codec, FFRational, Parameters)>,
+    events: Receiver<StreamEvent>,
+    connection: Option<format::context::Output>,
+    /// Failed attempts since the last successful connection.
+    attempt: u32,
+    retry_at: Option<Instant>,
+    gave_up: bool,
+    /// Timestamps are rebased so that every connection starts at zero. Kept in microseconds,
+    /// as the tracks each have a time base of their own.
+    ts_offset_micros: Option<i64>,
+    /// Per track, as a fresh connection can't use anything until it receives a keyframe.
+    waiting_for_keyframe: Vec<bool>,
+    keyframe_requested: Arc<AtomicBool>,
+    reporter: Option<StatusReporter>,
+}
+
+impl StreamSink {
+    fn connect(&mut self) -> Result<(), MediaError> {
+        let mut io_options = Dictionary::new();
+        io_options.set("rw_timeout", IO_TIMEOUT_MICROS);
+
+        let mut output_ctx =
+            format::output_as_with(&self.output.url, self.protocol.format_name(), io_options)?;
+
+        for (codec, time_base, parameters) in &self.streams {
+            let mut output_stream = output_ctx.add_stream(*codec)?;
+            output_stream.set_time_base(*time_base);
+            output_stream.set_parameters(parameters.clone());
+        }
+        output_ctx.write_header()?;
+
+        self.connection = Some(output_ctx);
+        self.attempt = 0;
+        self.retry_at = None;
+        self.ts_offset_micros = None;
+        self.waiting_for_keyframe = vec![true; self.streams.len()];
+        self.keyframe_requested.store(true, Ordering::Release);
+
+        Ok(())
+    }
+
+    fn run(mut self) {
+        loop {
+            // Wakes up for the next reconnect attempt even if no packets arrive.
+            let event = match self.retry_at.filter(|_| self.connection.is_none()) {
+                Some(retry_at) => match self.events.recv_deadline(retry_at) {
+                    Ok(event) => Some(event),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                },
+                None => match self.events.recv() {
+                    Ok(event) => Some(event),
+                    Err(_) => break,
+                },
+            };
+
+            if self.connection.is_none() {
+                self.try_reconnect();
+            }
+
+            match event {
+                Some(StreamEvent::Packet {
+                    track,
+                    time_base,
+                    packet,
+                }) => self.write_packet(track, packet, time_base),
+                Some(StreamEvent::Reporter(reporter)) => {
+                    if self.connection.is_some() {
+                        reporter.report(PipelineStatus::Stream(StreamStatus::Connected));
+                    }
+                    self.reporter = Some(reporter);
+                }
+                None => {}
+            }
+        }
+
+        self.finish();
+    }
+
+    fn write_packet(&mut self, track: usize, mut packet: FFPacket, time_base: FFRational) {
+        let Some(output_ctx) = self.connection.as_mut() else {
+            return;
+        };
+
+        if self.waiting_for_keyframe[track] {
+            if !packet.is_key() {
+                return;
+            }
+            self.waiting_for_keyframe[track] = false;
+        }
+
+        let micros = FFRational(1, 1_000_000);
+        let Some(timestamp) = packet.dts().or(packet.pts()) else {
+            return;
+        };
+        let offset_micros = *self
+            .ts_offset_micros
+            .get_or_insert(timestamp.rescale(time_base, micros));
+        let offset = offset_micros.rescale(micros, time_base);
+        // Other tracks may have started the connection a little later than this packet.
+        if timestamp < offset {
+            return;
+        }
+
+        packet.set_pts(packet.pts().map(|pts| pts - offset));
+        packet.set_dts(packet.dts().map(|dts| dts - offset));
+        packet.set_stream(track);
+        packet.rescale_ts(time_base, output_ctx.stream(track).unwrap().time_base());
+
+        if let Err(error) = packet.write_interleaved(output_ctx) {
+            self.disconnect(error.to_string());
+        }
+    }
+
+    fn finish(&mut self) {
+        if let Some(mut output_ctx) = self.connection.take() {
+            if let Err(error) = output_ctx.write_trailer() {
+                eprintln!("Failed to end stream to {}: {error}", self.output.url);
+            }
+        }
+    }
+
+    fn try_reconnect(&mut self) {
+        if self.gave_up
+            || self
+                .retry_at
+                .is_some_and(|retry_at| Instant::now() < retry_at)
+        {
+            return;
+        }
+
+        self.report(StreamStatus::Connecting {
+            attempt: self.attempt,
+        });
+
+        // Blocks for at most the IO timeout, during which packets are dropped once the
+        // queue fills up.
+        match self.connect() {
+            Ok(()) => {
+                println!("Reconnected stream to {}", self.output.url);
+                self.report(StreamStatus::Connected);
+            }
+            Err(error) => self.disconnect(error.to_string()),
+        }
+    }
+
+    fn disconnect(&mut self, reason: String) {
+        eprintln!("Stream to {} failed: {reason}", self.output.url);
+        self.connection = None;
+        self.attempt += 1;
+
+        if self.output.reconnect.gives_up_after(self.attempt) {
+            self.gave_up = true;
+            // Nothing left to wake up for, so `run` goes back to waiting for packets.
+            self.retry_at = None;
+            self.report(StreamStatus::GaveUp { reason });
+            return;
+        }
+
+        let delay = self.output.reconnect.delay(self.attempt);
+        self.retry_at = Some(Instant::now() + delay);
+        self.report(StreamStatus::Disconnected {
+            reason,
+            retry_in_ms: delay.as_millis().try_into().unwrap_or(u32::MAX),
+        });
+    }
+
+    fn report(&self, status: StreamStatus) {
+        if let Some(reporter) = &self.reporter {
+            reporter.report(PipelineStatus::Stream(status));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_the_limit() {
+        let policy = ReconnectPolicy {
+            initial_delay_ms: 500,
+            max_delay_ms: 3_000,
+            max_attempts: Some(10),
+        };
+
+        let delays = (1..=5)
+            .map(|attempt| policy.delay(attempt).as_millis())
+            .collect::<Vec<_>>();
+        assert_eq!(delays, [500, 1_000, 2_000, 3_000, 3_000]);
+        assert_eq!(policy.delay(u32::MAX), Duration::from_millis(3_000));
+
+        assert!(!policy.gives_up_after(9));
+        assert!(policy.gives_up_after(10));
+        assert!(!ReconnectPolicy::default().gives_up_after(u32::MAX));
+    }
+
+    #[test]
+    fn giving_up_stops_reconnecting() {
+        let stream = LiveStream::new(StreamOutput {
+            url: "rtmp://127.0.0.1:1/live/test".to_string(),
+            max_bitrate_kbps: None,
+            reconnect: ReconnectPolicy {
+                initial_delay_ms: 1,
+                max_delay_ms: 1,
+                max_attempts: Some(2),
+            },
+        })
+        .unwrap();
+        let mut sink = stream.sink.lock().unwrap().take().unwrap();
+
+        sink.disconnect("first".to_string());
+        assert!(!sink.gave_up);
+        assert!(sink.retry_at.is_some());
+
+        sink.disconnect("second".to_string());
+        assert!(sink.gave_up);
+        assert!(sink.retry_at.is_none());
+
+        // Blocks until every track is gone, rather than waking up for an expired retry.
+        let (done_tx, done_rx) = flume::bounded(1);
+        std::thread::spawn(move || {
+            sink.run();
+            done_tx.send(()).unwrap();
+        });
+        assert!(done_rx.recv_timeout(Duration::from_millis(50)).is_err());
+
+        drop(stream);
+        done_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    }
+
+    #[test]
+    fn protocol_is_picked_from_the_url() {
+        assert_eq!(
+            StreamProtocol::from_url("rtmp://localhost/live/key"),
+            Some(StreamProtocol::Rtmp)
+        );
+        assert_eq!(
+            StreamProtocol::from_url("RTMPS://example.com/app"),
+            Some(StreamProtocol::Rtmp)
+        );
+        assert_eq!(
+            StreamProtocol::from_url("srt://localhost:9000?streamid=publish:key"),
+            Some(StreamProtocol::Srt)
+        );
+        assert_eq!(StreamProtocol::from_url("https://example.com"), None);
+        assert_eq!(StreamProtocol::from_url("localhost/live"), None);
+    }
+}
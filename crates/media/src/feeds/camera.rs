@@ -1,25 +1,149 @@
-use ffmpeg::software::scaling;
+use ffmpeg::{codec, software::scaling};
 use flume::{Receiver, Sender, TryRecvError};
 use nokhwa::{pixel_format::*, utils::*, Camera};
+use serde::{Deserialize, Serialize};
+use specta::Type;
 use std::{
+    cmp::Reverse,
     thread::{self, JoinHandle},
-    time::Instant,
+    time::{Duration, Instant},
 };
 use tracing::{error, info, warn};
 
 use crate::{
-    data::{FFVideo, RawVideoFormat, VideoInfo},
+    data::{FFPacket, FFVideo, Pixel, RawVideoFormat, VideoInfo},
     MediaError,
 };
 
-type CameraSwitchResult = Result<(CameraInfo, VideoInfo), MediaError>;
+type CameraSwitchResult = Result<(CameraInfo, CameraFormatInfo, VideoInfo), MediaError>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub enum CameraFrameFormat {
+    Mjpeg,
+    Yuyv,
+    Nv12,
+    Gray,
+    RawRgb,
+}
+
+impl From<FrameFormat> for CameraFrameFormat {
+    fn from(value: FrameFormat) -> Self {
+        match value {
+            FrameFormat::MJPEG => Self::Mjpeg,
+            FrameFormat::YUYV => Self::Yuyv,
+            FrameFormat::NV12 => Self::Nv12,
+            FrameFormat::GRAY => Self::Gray,
+            FrameFormat::RAWRGB => Self::RawRgb,
+        }
+    }
+}
+
+impl From<CameraFrameFormat> for FrameFormat {
+    fn from(value: CameraFrameFormat) -> Self {
+        match value {
+            CameraFrameFormat::Mjpeg => Self::MJPEG,
+            CameraFrameFormat::Yuyv => Self::YUYV,
+            CameraFrameFormat::Nv12 => Self::NV12,
+            CameraFrameFormat::Gray => Self::GRAY,
+            CameraFrameFormat::RawRgb => Self::RAWRGB,
+        }
+    }
+}
+
+/// A resolution, frame format and frame rate that a camera can capture at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct CameraFormatInfo {
+    pub width: u32,
+    pub height: u32,
+    pub frame_format: CameraFrameFormat,
+    pub frame_rate: u32,
+}
+
+impl From<CameraFormat> for CameraFormatInfo {
+    fn from(value: CameraFormat) -> Self {
+        Self {
+            width: value.width(),
+            height: value.height(),
+            frame_format: value.format().into(),
+            frame_rate: value.frame_rate(),
+        }
+    }
+}
+
+impl From<CameraFormatInfo> for CameraFormat {
+    fn from(value: CameraFormatInfo) -> Self {
+        CameraFormat::new(
+            Resolution {
+                width_x: value.width,
+                height_y: value.height,
+            },
+            value.frame_format.into(),
+            value.frame_rate,
+        )
+    }
+}
+
+/// Which of a camera's formats to capture with. Cameras that can't provide the exact format
+/// requested fall back to the closest one they support.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, Type)]
+#[serde(
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase",
+    tag = "kind"
+)]
+pub enum CameraFormatPreference {
+    /// 1080p YUYV at 30 fps, or the closest available.
+    #[default]
+    Default,
+    HighestResolution,
+    /// The highest resolution that can be captured at `fps`.
+    BestForFrameRate {
+        fps: u32,
+    },
+    Exact {
+        format: CameraFormatInfo,
+    },
+}
+
+impl CameraFormatPreference {
+    fn requested_format(&self) -> RequestedFormat<'static> {
+        let format_type = match *self {
+            Self::Default => RequestedFormatType::Closest(CameraFormat::new(
+                Resolution {
+                    width_x: 1920,
+                    height_y: 1080,
+                },
+                FrameFormat::YUYV,
+                30,
+            )),
+            Self::HighestResolution => RequestedFormatType::AbsoluteHighestResolution,
+            Self::BestForFrameRate { fps } => RequestedFormatType::HighestFrameRate(fps),
+            Self::Exact { format } => RequestedFormatType::Closest(format.into()),
+        };
+
+        RequestedFormat::new::<RgbAFormat>(format_type)
+    }
+}
 
 enum CameraControl {
-    Switch(String, Sender<CameraSwitchResult>),
+    Switch(String, CameraFormatPreference, Sender<CameraSwitchResult>),
     AttachRawConsumer(Sender<RawCameraFrame>),
     Shutdown,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase",
+    tag = "state"
+)]
+pub enum CameraFeedEvent {
+    Disconnected { camera: String },
+    Reconnected { camera: String },
+}
+
 pub type CameraFrameSender = Sender<Vec<u8>>;
 pub type CameraFrameReceiver = Receiver<Vec<u8>>;
 
@@ -45,8 +169,11 @@ impl CameraConnection {
 
 pub struct CameraFeed {
     camera_info: CameraInfo,
+    format_preference: CameraFormatPreference,
+    camera_format: CameraFormatInfo,
     video_info: VideoInfo,
     control: Sender<CameraControl>,
+    events: Receiver<CameraFeedEvent>,
     join_handle: JoinHandle<()>,
 }
 
@@ -57,20 +184,31 @@ impl CameraFeed {
 
     pub async fn init(
         selected_camera: &String,
+        format_preference: CameraFormatPreference,
         rgba_data: Sender<Vec<u8>>,
     ) -> Result<CameraFeed, MediaError> {
         println!("Selected camera: {:?}", selected_camera);
 
         let camera_info = find_camera(&selected_camera)?;
         let (control, control_receiver) = flume::bounded(1);
-
-        let (video_info, join_handle) =
-            start_capturing(camera_info.clone(), control_receiver, rgba_data).await?;
+        let (events_sender, events) = flume::unbounded();
+
+        let (camera_format, video_info, join_handle) = start_capturing(
+            camera_info.clone(),
+            format_preference,
+            control_receiver,
+            rgba_data,
+            events_sender,
+        )
+        .await?;
 
         let camera_feed = Self {
             camera_info,
+            format_preference,
+            camera_format,
             video_info,
             control,
+            events,
             join_handle,
         };
 
@@ -85,32 +223,76 @@ impl CameraFeed {
             .collect()
     }
 
+    /// Every format the camera supports, largest and fastest first.
+    pub fn list_formats(camera_name: &String) -> Result<Vec<CameraFormatInfo>, MediaError> {
+        let info = find_camera(camera_name)?;
+        let mut camera = Camera::new(
+            info.index().clone(),
+            RequestedFormat::new::<RgbAFormat>(RequestedFormatType::None),
+        )?;
+
+        let mut formats = camera
+            .compatible_camera_formats()?
+            .into_iter()
+            .map(CameraFormatInfo::from)
+            .collect::<Vec<_>>();
+        formats.sort_by_key(|format| Reverse((format.width * format.height, format.frame_rate)));
+        formats.dedup();
+
+        Ok(formats)
+    }
+
     pub fn video_info(&self) -> VideoInfo {
         self.video_info
     }
 
-    pub async fn switch_cameras(&mut self, camera_name: &String) -> Result<(), MediaError> {
+    /// The format the camera is actually capturing with, which may differ from the preference.
+    pub fn camera_format(&self) -> CameraFormatInfo {
+        self.camera_format
+    }
+
+    /// Switches to another camera, or reopens the current one if only the format changed.
+    ///
+    /// While a pipeline is attached, the new camera's frames are scaled to the feed's current
+    /// `VideoInfo`, so the recording carries on uninterrupted.
+    pub async fn switch_cameras(
+        &mut self,
+        camera_name: &String,
+        format_preference: CameraFormatPreference,
+    ) -> Result<(), MediaError> {
         let current_camera_name = self.camera_info.human_name();
-        if camera_name != &current_camera_name {
+        if camera_name != &current_camera_name || format_preference != self.format_preference {
             let (result_tx, result_rx) = flume::bounded::<CameraSwitchResult>(1);
 
             let _ = self
                 .control
-                .send_async(CameraControl::Switch(camera_name.clone(), result_tx))
+                .send_async(CameraControl::Switch(
+                    camera_name.clone(),
+                    format_preference,
+                    result_tx,
+                ))
                 .await;
 
-            let (camera_info, video_info) = result_rx
+            let (camera_info, camera_format, video_info) = result_rx
                 .recv_async()
                 .await
                 .map_err(|_| MediaError::Any("Failed to prepare camera feed"))??;
 
             self.camera_info = camera_info;
+            self.format_preference = format_preference;
+            self.camera_format = camera_format;
             self.video_info = video_info;
         }
 
         Ok(())
     }
 
+    /// Reports the camera being unplugged and plugged back in. While it's gone, attached
+    /// pipelines receive copies of the last frame, or black frames, at the feed's frame rate.
+    pub fn events(&self) -> Receiver<CameraFeedEvent> {
+        self.events.clone()
+    }
+
     pub fn create_connection(&self) -> CameraConnection {
         CameraConnection {
             control: self.control.clone(),
@@ -133,58 +315,76 @@ fn find_camera(selected_camera: &String) -> Result<CameraInfo, MediaError> {
         .ok_or(MediaError::DeviceUnreachable(selected_camera.clone()))
 }
 
-fn create_camera(info: &CameraInfo) -> Result<Camera, MediaError> {
-    // TODO: Make selected format more flexible
-    // let format = RequestedFormat::new::<RgbAFormat>(RequestedFormatType::AbsoluteHighestResolution);
-    let format =
-        RequestedFormat::new::<RgbAFormat>(RequestedFormatType::Closest(CameraFormat::new(
-            Resolution {
-                width_x: 1920,
-                height_y: 1080,
-            },
-            FrameFormat::YUYV,
-            30,
-        )));
+fn create_camera(
+    info: &CameraInfo,
+    format_preference: CameraFormatPreference,
+) -> Result<Camera, MediaError> {
     let index = info.index().clone();
-    Ok(Camera::new(index, format)?)
+    Ok(Camera::new(index, format_preference.requested_format())?)
 }
 
-fn find_and_create_camera(selected_camera: &String) -> Result<(CameraInfo, Camera), MediaError> {
+fn find_and_create_camera(
+    selected_camera: &String,
+    format_preference: CameraFormatPreference,
+) -> Result<(CameraInfo, Camera), MediaError> {
     let info = find_camera(selected_camera)?;
-    let camera = create_camera(&info)?;
+    let camera = create_camera(&info, format_preference)?;
 
     Ok((info, camera))
 }
 
+type CameraReadyResult = Result<(CameraFormatInfo, VideoInfo), MediaError>;
+
 async fn start_capturing(
     camera_info: CameraInfo,
+    format_preference: CameraFormatPreference,
     control: Receiver<CameraControl>,
     rgba_data: Sender<Vec<u8>>,
-) -> Result<(VideoInfo, JoinHandle<()>), MediaError> {
-    let (ready_tx, ready_rx) = flume::bounded::<Result<VideoInfo, MediaError>>(1);
+    events: Sender<CameraFeedEvent>,
+) -> Result<(CameraFormatInfo, VideoInfo, JoinHandle<()>), MediaError> {
+    let (ready_tx, ready_rx) = flume::bounded::<CameraReadyResult>(1);
 
     let join_handle = thread::spawn(move || {
-        run_camera_feed(camera_info, control, rgba_data, ready_tx);
+        run_camera_feed(
+            camera_info,
+            format_preference,
+            control,
+            rgba_data,
+            events,
+            ready_tx,
+        );
     });
 
-    let video_info = ready_rx
+    let (camera_format, video_info) = ready_rx
         .recv_async()
         .await
         .map_err(|_| MediaError::Any("Failed to prepare camera feed"))??;
 
-    Ok((video_info, join_handle))
+    Ok((camera_format, video_info, join_handle))
+}
+
+/// The camera is treated as disconnected once it's failed to produce a frame for this long.
+const DISCONNECT_TIMEOUT: Duration = Duration::from_millis(1000);
+/// How often a disconnected camera is looked for again.
+const RECONNECT_INTERVAL: Duration = Duration::from_millis(1000);
+
+struct Disconnection {
+    last_attempt: Instant,
+    next_placeholder: Instant,
 }
 
 // #[tracing::instrument(skip_all)]
 fn run_camera_feed(
-    camera_info: CameraInfo,
+    mut camera_info: CameraInfo,
+    mut format_preference: CameraFormatPreference,
     control: Receiver<CameraControl>,
     rgba_data: Sender<Vec<u8>>,
-    ready_signal: Sender<Result<VideoInfo, MediaError>>,
+    events: Sender<CameraFeedEvent>,
+    ready_signal: Sender<CameraReadyResult>,
 ) {
     let mut maybe_raw_data: Option<Sender<RawCameraFrame>> = None;
 
-    let mut camera = match create_camera(&camera_info) {
+    let mut camera = match create_camera(&camera_info, format_preference) {
         Ok(cam) => cam,
         Err(error) => {
             error!("Failed to create camera: {:?}", error);
@@ -193,7 +393,14 @@ fn run_camera_feed(
         }
     };
 
-    let mut converter = FrameConverter::build(camera.camera_format());
+    let mut converter = match FrameConverter::build(camera.camera_format()) {
+        Ok(converter) => converter,
+        Err(error) => {
+            error!("Failed to prepare camera frame conversion: {:?}", error);
+            ready_signal.send(Err(error)).unwrap();
+            return;
+        }
+    };
 
     if let Err(error) = camera.open_stream() {
         error!("Failed to open camera stream: {:?}", error);
@@ -201,10 +408,21 @@ fn run_camera_feed(
         return;
     }
 
-    info!("Camera stream opened successfully");
-    ready_signal.send(Ok(converter.video_info)).unwrap();
+    info!(
+        "Camera stream opened successfully with {:?}",
+        converter.camera_format
+    );
+    ready_signal
+        .send(Ok((converter.camera_format, converter.video_info)))
+        .unwrap();
     println!("Launched camera feed");
 
+    let mut failing_since: Option<Instant> = None;
+    let mut disconnection: Option<Disconnection> = None;
+    // Repeated while the camera is disconnected, so that the camera track stays as long as
+    // the screen track.
+    let mut last_frame: Option<FFVideo> = None;
+
     loop {
         match control.try_recv() {
             Err(TryRecvError::Disconnected) => {
@@ -222,70 +440,169 @@ fn run_camera_feed(
                 eprintln!("Attaching to a new pipeline consumer. Any previously attached consumer will be dropped");
                 maybe_raw_data = Some(rgba_sender);
             }
-            Ok(CameraControl::Switch(camera_name, switch_result)) => {
-                if maybe_raw_data.is_some() {
-                    switch_result.send(Err(MediaError::Any("Cannot switch cameras while the feed is attached to a running pipeline"))).unwrap();
-                } else {
-                    println!("Switching camera to {camera_name}");
-
-                    match find_and_create_camera(&camera_name) {
-                        Err(error) => {
-                            eprintln!("{error}");
-                            switch_result.send(Err(error)).unwrap();
-                        }
-                        Ok((new_info, mut new_camera)) => {
-                            let new_format = new_camera.camera_format();
-                            let new_converter = FrameConverter::build(new_format);
-
-                            if new_camera.open_stream().is_ok() {
-                                println!("Now using {camera_name}");
-                                let _ = camera.stop_stream();
-                                switch_result
-                                    .send(Ok((new_info, new_converter.video_info)))
-                                    .unwrap();
-                                camera = new_camera;
-                                converter = new_converter;
-                            } else {
-                                eprintln!(
-                                    "Unable to switch to {camera_name}. Still using previous camera"
-                                );
-                                switch_result
-                                    .send(Err(MediaError::DeviceUnreachable(camera_name)))
-                                    .unwrap();
+            Ok(CameraControl::Switch(camera_name, new_preference, switch_result)) => {
+                println!("Switching camera to {camera_name}");
+
+                // Release the current device first, since reopening it with a different
+                // format fails while its stream is still running.
+                let _ = camera.stop_stream();
+
+                // An attached pipeline was set up for the current frames, so the new camera's
+                // are scaled to match. Its timestamps carry on since they come from capture time.
+                let output_info = maybe_raw_data.is_some().then_some(converter.video_info);
+
+                match find_and_create_camera(&camera_name, new_preference).and_then(
+                    |(new_info, new_camera)| {
+                        let converter = match output_info {
+                            Some(output_info) => FrameConverter::build_with_output(
+                                new_camera.camera_format(),
+                                output_info,
+                            )?,
+                            None => FrameConverter::build(new_camera.camera_format())?,
+                        };
+                        Ok((new_info, new_camera, converter))
+                    },
+                ) {
+                    Err(error) => {
+                        eprintln!("{error}");
+                        let _ = camera.open_stream();
+                        switch_result.send(Err(error)).unwrap();
+                    }
+                    Ok((new_info, mut new_camera, new_converter)) => {
+                        if new_camera.open_stream().is_ok() {
+                            println!("Now using {camera_name}");
+                            switch_result
+                                .send(Ok((
+                                    new_info.clone(),
+                                    new_converter.camera_format,
+                                    new_converter.video_info,
+                                )))
+                                .unwrap();
+                            camera = new_camera;
+                            camera_info = new_info;
+                            format_preference = new_preference;
+                            converter = new_converter;
+                            failing_since = None;
+                            last_frame = None;
+                            if disconnection.take().is_some() {
+                                let _ = events.send(CameraFeedEvent::Reconnected {
+                                    camera: camera_name,
+                                });
                             }
+                        } else {
+                            eprintln!(
+                                "Unable to switch to {camera_name}. Still using previous camera"
+                            );
+                            let _ = camera.open_stream();
+                            switch_result
+                                .send(Err(MediaError::DeviceUnreachable(camera_name)))
+                                .unwrap();
                         }
                     }
                 }
             }
         }
 
+        if let Some(Disconnection {
+            last_attempt,
+            next_placeholder,
+        }) = &mut disconnection
+        {
+            if last_attempt.elapsed() >= RECONNECT_INTERVAL {
+                *last_attempt = Instant::now();
+
+                match reopen_camera(&camera_info, format_preference, converter.video_info) {
+                    Ok((new_camera, new_converter)) => {
+                        let camera_name = camera_info.human_name();
+                        println!("Camera {camera_name} reconnected");
+                        camera = new_camera;
+                        converter = new_converter;
+                        disconnection = None;
+                        let _ = events.send(CameraFeedEvent::Reconnected {
+                            camera: camera_name,
+                        });
+                        continue;
+                    }
+                    Err(error) => {
+                        info!("Camera is still unavailable: {error}");
+                    }
+                }
+            }
+
+            let now = Instant::now();
+            if now < *next_placeholder {
+                thread::sleep((*next_placeholder - now).min(Duration::from_millis(10)));
+                continue;
+            }
+            *next_placeholder += frame_interval(&converter.video_info);
+
+            let frame = match last_frame.clone() {
+                Some(frame) => frame,
+                None => match converter.black_frame() {
+                    Ok(frame) => frame,
+                    Err(error) => {
+                        warn!("Failed to create placeholder camera frame: {error}");
+                        continue;
+                    }
+                },
+            };
+
+            if send_frame(&mut converter, &rgba_data, &mut maybe_raw_data, frame, now).is_err() {
+                break;
+            }
+            continue;
+        }
+
         // Actual data capture
         match camera.frame() {
             Ok(raw_buffer) => {
                 // TODO: Merge fix in nokhwa lib to use presentation timestamps from the system, like scap does
                 let captured_at = Instant::now();
+                failing_since = None;
 
-                let rgba_frame = converter.rgba(&raw_buffer);
+                let frame = match converter.decode(&raw_buffer) {
+                    Ok(frame) => frame,
+                    Err(error) => {
+                        warn!("Failed to decode camera frame: {error}");
+                        continue;
+                    }
+                };
 
-                if dropping_send(&rgba_data, rgba_frame).is_err() {
-                    // TODO: Also allow changing the connection?
-                    eprintln!("Camera preview has been disconnected. Shutting down feed");
-                    break;
+                if maybe_raw_data.is_some() {
+                    last_frame = Some(frame.clone());
                 }
 
-                if let Some(ref raw_data) = maybe_raw_data {
-                    let frame = RawCameraFrame {
-                        frame: converter.raw(&raw_buffer),
-                        captured_at,
-                    };
-                    if dropping_send(raw_data, frame).is_err() {
-                        eprintln!("Raw data consumer has been disconnected.");
-                        maybe_raw_data = None;
-                    }
+                if send_frame(
+                    &mut converter,
+                    &rgba_data,
+                    &mut maybe_raw_data,
+                    frame,
+                    captured_at,
+                )
+                .is_err()
+                {
+                    break;
                 }
             }
             Err(error) => {
                 warn!("Failed to capture frame: {:?}", error);
+
+                let failing_since = *failing_since.get_or_insert_with(Instant::now);
+                if failing_since.elapsed() >= DISCONNECT_TIMEOUT {
+                    let camera_name = camera_info.human_name();
+                    eprintln!("Camera {camera_name} disconnected. Waiting for it to come back");
+                    let _ = camera.stop_stream();
+                    disconnection = Some(Disconnection {
+                        last_attempt: Instant::now(),
+                        // Fill the gap since the last frame too.
+                        next_placeholder: failing_since,
+                    });
+                    let _ = events.send(CameraFeedEvent::Disconnected {
+                        camera: camera_name,
+                    });
+                    continue;
+                }
+
                 // Optionally, add a small delay to avoid busy-waiting
                 std::thread::sleep(std::time::Duration::from_millis(10));
                 continue;
@@ -294,63 +611,198 @@ fn run_camera_feed(
     }
 
     let _ = camera.stop_stream();
-    println!("Closed {} stream", camera.info().human_name());
 }
 
+/// Sends a frame to the preview and to the attached pipeline, if any. Fails if the preview
+/// has gone away, at which point the feed should shut down.
+fn send_frame(
+    converter: &mut FrameConverter,
+    rgba_data: &Sender<Vec<u8>>,
+    maybe_raw_data: &mut Option<Sender<RawCameraFrame>>,
+    frame: FFVideo,
+    captured_at: Instant,
+) -> Result<(), ()> {
+    let rgba_frame = converter.rgba(&frame);
+
+    if dropping_send(rgba_data, rgba_frame).is_err() {
+        // TODO: Also allow changing the connection?
+        eprintln!("Camera preview has been disconnected. Shutting down feed");
+        return Err(());
+    }
+
+    if let Some(ref raw_data) = maybe_raw_data {
+        let frame = RawCameraFrame { frame, captured_at };
+        if dropping_send(raw_data, frame).is_err() {
+            eprintln!("Raw data consumer has been disconnected.");
+            *maybe_raw_data = None;
+        }
+    }
+
+    Ok(())
+}
+
+/// Opens a camera that went away again, converting its frames to what consumers already
+/// expect in case it came back with a different format.
+fn reopen_camera(
+    camera_info: &CameraInfo,
+    format_preference: CameraFormatPreference,
+    video_info: VideoInfo,
+) -> Result<(Camera, FrameConverter), MediaError> {
+    let (_, mut camera) = find_and_create_camera(&camera_info.human_name(), format_preference)?;
+    let converter = FrameConverter::build_with_output(camera.camera_format(), video_info)?;
+    camera.open_stream()?;
+
+    Ok((camera, converter))
+}
+
+fn frame_interval(video_info: &VideoInfo) -> Duration {
+    let frame_rate = video_info.frame_rate;
+    Duration::from_secs_f64(frame_rate.denominator() as f64 / frame_rate.numerator().max(1) as f64)
+}
+
+/// Turns camera buffers into frames of the feed's `VideoInfo`, and RGBA data for the preview.
 struct FrameConverter {
+    camera_format: CameraFormatInfo,
+    /// What the camera's buffers contain, before decoding.
+    input_info: VideoInfo,
     video_info: VideoInfo,
-    context: scaling::Context,
+    /// MJPEG buffers are compressed, so they're decoded before anything else.
+    decoder: Option<codec::decoder::Video>,
+    /// Converts frames whose pixel format or size differs from `video_info`'s, e.g. MJPEG
+    /// cameras that use 4:2:0 instead of the usual 4:2:2, or a camera that came back with a
+    /// different format after being replugged.
+    normaliser: Option<((Pixel, u32, u32), scaling::Context)>,
+    rgba: scaling::Context,
 }
 
 impl FrameConverter {
-    fn build(camera_format: CameraFormat) -> Self {
-        let format = match camera_format.format() {
-            FrameFormat::MJPEG => RawVideoFormat::Mjpeg,
-            FrameFormat::YUYV => RawVideoFormat::Yuyv,
-            FrameFormat::NV12 => RawVideoFormat::Nv12,
-            FrameFormat::GRAY => RawVideoFormat::Gray,
-            FrameFormat::RAWRGB => RawVideoFormat::RawRgb,
-        };
+    fn build(camera_format: CameraFormat) -> Result<Self, MediaError> {
+        let input_info = Self::input_info(camera_format.into());
+        Self::build_with_output(camera_format, input_info)
+    }
 
-        let video_info = VideoInfo::from_raw(
-            format,
-            camera_format.width(),
-            camera_format.height(),
-            camera_format.frame_rate(),
-        );
+    /// Produces frames of `video_info` regardless of the camera's format, so that consumers
+    /// which were set up for a previous format keep working.
+    fn build_with_output(
+        camera_format: CameraFormat,
+        video_info: VideoInfo,
+    ) -> Result<Self, MediaError> {
+        let camera_format = CameraFormatInfo::from(camera_format);
+
+        let decoder = match camera_format.frame_format {
+            CameraFrameFormat::Mjpeg => {
+                let codec = codec::decoder::find(codec::Id::MJPEG)
+                    .ok_or(MediaError::MissingCodec("MJPEG video"))?;
+                Some(
+                    codec::context::Context::new_with_codec(codec)
+                        .decoder()
+                        .video()?,
+                )
+            }
+            _ => None,
+        };
 
-        let context = ffmpeg::software::converter(
+        let rgba = ffmpeg::software::converter(
             (video_info.width, video_info.height),
             video_info.pixel_format,
-            ffmpeg::format::Pixel::RGBA,
-        )
-        .unwrap();
+            Pixel::RGBA,
+        )?;
 
-        Self {
+        Ok(Self {
+            camera_format,
+            input_info: Self::input_info(camera_format),
             video_info,
-            context,
+            decoder,
+            normaliser: None,
+            rgba,
+        })
+    }
+
+    fn input_info(camera_format: CameraFormatInfo) -> VideoInfo {
+        let format = match camera_format.frame_format {
+            CameraFrameFormat::Mjpeg => RawVideoFormat::Mjpeg,
+            CameraFrameFormat::Yuyv => RawVideoFormat::Yuyv,
+            CameraFrameFormat::Nv12 => RawVideoFormat::Nv12,
+            CameraFrameFormat::Gray => RawVideoFormat::Gray,
+            CameraFrameFormat::RawRgb => RawVideoFormat::RawRgb,
+        };
+
+        VideoInfo::from_raw(
+            format,
+            camera_format.width,
+            camera_format.height,
+            camera_format.frame_rate,
+        )
+    }
+
+    fn decode(&mut self, buffer: &nokhwa::Buffer) -> Result<FFVideo, MediaError> {
+        let decoded = match self.decoder.as_mut() {
+            None => self.input_info.wrap_frame(buffer.buffer(), 0),
+            Some(decoder) => {
+                decoder.send_packet(&FFPacket::copy(buffer.buffer()))?;
+                let mut decoded = FFVideo::empty();
+                decoder.receive_frame(&mut decoded)?;
+                decoded
+            }
+        };
+
+        let input = (decoded.format(), decoded.width(), decoded.height());
+        let output = &self.video_info;
+        if input == (output.pixel_format, output.width, output.height) {
+            return Ok(decoded);
         }
+
+        let normaliser = match &mut self.normaliser {
+            Some((format, normaliser)) if *format == input => normaliser,
+            normaliser => {
+                let context = scaling::Context::get(
+                    input.0,
+                    input.1,
+                    input.2,
+                    output.pixel_format,
+                    output.width,
+                    output.height,
+                    scaling::Flags::BILINEAR,
+                )?;
+                &mut normaliser.insert((input, context)).1
+            }
+        };
+
+        let mut frame = FFVideo::empty();
+        normaliser.run(&decoded, &mut frame)?;
+
+        Ok(frame)
     }
 
-    fn rgba(&mut self, buffer: &nokhwa::Buffer) -> Vec<u8> {
-        let resolution = buffer.resolution();
+    /// A black frame, for when there's no camera frame to show.
+    fn black_frame(&self) -> Result<FFVideo, MediaError> {
+        let (width, height) = (self.video_info.width, self.video_info.height);
+
+        let mut frame = FFVideo::new(Pixel::RGBA, width, height);
+        frame.data_mut(0).fill(0);
+        if self.video_info.pixel_format == Pixel::RGBA {
+            return Ok(frame);
+        }
+
+        let mut converted = FFVideo::empty();
+        ffmpeg::software::converter((width, height), Pixel::RGBA, self.video_info.pixel_format)?
+            .run(&frame, &mut converted)?;
 
-        let input_frame = self.video_info.wrap_frame(buffer.buffer(), 0);
+        Ok(converted)
+    }
+
+    fn rgba(&mut self, frame: &FFVideo) -> Vec<u8> {
         let mut rgba_frame = FFVideo::empty();
 
-        self.context.run(&input_frame, &mut rgba_frame).unwrap();
+        self.rgba.run(frame, &mut rgba_frame).unwrap();
 
         let mut data = rgba_frame.data(0).to_vec();
 
-        data.extend_from_slice(&resolution.height().to_le_bytes());
-        data.extend_from_slice(&resolution.width().to_le_bytes());
+        data.extend_from_slice(&self.video_info.height.to_le_bytes());
+        data.extend_from_slice(&self.video_info.width.to_le_bytes());
 
         data
     }
-
-    fn raw(&mut self, buffer: &nokhwa::Buffer) -> FFVideo {
-        self.video_info.wrap_frame(buffer.buffer(), 0)
-    }
 }
 
 fn dropping_send<T>(sender: &Sender<T>, value: T) -> Result<(), flume::SendError<T>> {
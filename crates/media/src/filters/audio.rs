@@ -3,7 +3,10 @@ use flume::{Receiver, Sender};
 
 use crate::{
     data::{AudioInfo, FFAudio},
-    pipeline::task::PipelinePipeTask,
+    pipeline::{
+        control::{PipelineMessage, PipelineMessages},
+        task::PipelinePipeTask,
+    },
     MediaError,
 };
 
@@ -26,13 +29,19 @@ impl AudioFilter {
         filter_graph.add(&filter::find("abuffer").unwrap(), "in", &input_args)?;
         filter_graph.add(&filter::find("abuffersink").unwrap(), "out", "")?;
 
-        filter_graph.output("in", 0)?.input("out", 0)?.parse(spec)?;
+        // Filters like `aresample` timestamp their output in samples. Later stages, such as drift
+        // correction, expect the time base frames came in with.
+        let spec = format!("{spec},asettb={}", config.time_base);
+        filter_graph
+            .output("in", 0)?
+            .input("out", 0)?
+            .parse(&spec)?;
         filter_graph.validate()?;
 
         Ok(Self { filter_graph, tag })
     }
 
-    fn queue_frame(&mut self, frame: FFAudio) {
+    pub(super) fn queue_frame(&mut self, frame: FFAudio) {
         self.filter_graph
             .get("in")
             .unwrap()
@@ -41,7 +50,7 @@ impl AudioFilter {
             .unwrap();
     }
 
-    fn process_frame(&mut self, output: &Sender<FFAudio>) {
+    pub(super) fn process_frame(&mut self, output: &Sender<FFAudio>) {
         let mut filtered_frame = FFAudio::empty();
 
         while self
@@ -57,7 +66,17 @@ impl AudioFilter {
         }
     }
 
-    fn finish(&mut self, output: &Sender<FFAudio>) {
+    pub(super) fn handle_messages(&mut self, messages: &PipelineMessages) {
+        for message in messages.pending() {
+            if let PipelineMessage::Reconfigure(reconfigure) = message {
+                if let Err(error) = super::send_command(&mut self.filter_graph, &reconfigure) {
+                    eprintln!("Failed to reconfigure {} filter: {error}", self.tag);
+                }
+            }
+        }
+    }
+
+    pub(super) fn finish(&mut self, output: &Sender<FFAudio>) {
         self.filter_graph
             .get("in")
             .unwrap()
@@ -76,6 +95,7 @@ impl PipelinePipeTask for AudioFilter {
     fn run(
         &mut self,
         ready_signal: crate::pipeline::task::PipelineReadySignal,
+        messages: PipelineMessages,
         input: Receiver<Self::Input>,
         output: Sender<Self::Output>,
     ) {
@@ -83,6 +103,7 @@ impl PipelinePipeTask for AudioFilter {
         ready_signal.send(Ok(())).unwrap();
 
         while let Ok(raw_frame) = input.recv() {
+            self.handle_messages(&messages);
             self.queue_frame(raw_frame);
             self.process_frame(&output);
         }
@@ -96,3 +117,30 @@ impl PipelinePipeTask for AudioFilter {
         println!("Shutting down {} audio filtering thread", self.tag);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::RawAudioFormat;
+
+    #[test]
+    fn resampled_frames_keep_the_input_time_base() {
+        ffmpeg::init().unwrap();
+        let config = AudioInfo::from_raw(RawAudioFormat::F32, 48_000, 1, 480);
+        let mut filter = AudioFilter::init("test", config, "aresample=44100").unwrap();
+
+        let (tx, rx) = flume::unbounded();
+        for index in 0..100 {
+            filter.queue_frame(config.wrap_frame(&[0; 480 * 4], index * 10_000));
+            filter.process_frame(&tx);
+        }
+        filter.finish(&tx);
+        drop(tx);
+
+        let frames = rx.iter().collect::<Vec<_>>();
+        assert!(frames.iter().all(|frame| frame.rate() == 44_100));
+        // A second in, counted in microseconds rather than samples
+        let last_pts = frames.last().unwrap().pts().unwrap();
+        assert!((900_000..1_000_000).contains(&last_pts), "{last_pts}");
+    }
+}
@@ -0,0 +1,264 @@
+use std::sync::{Arc, Mutex};
+
+use flume::{Receiver, Sender};
+
+use crate::{
+    data::{AudioInfo, FFAudio},
+    pipeline::{
+        control::PipelineMessages,
+        task::{PipelinePipeTask, PipelineReadySignal},
+    },
+    MediaError,
+};
+
+use super::AudioFilter;
+
+/// Lets `aresample` stretch or squeeze the stream by up to this many samples per second to
+/// keep it in line with the frame timestamps, and pad/trim outright once it is 100ms off.
+const COMPENSATION_SPEC: &str = "aresample=async=1000:min_hard_comp=0.100000:first_pts=0";
+
+/// Timestamp jumps larger than this (in microseconds) are treated as gaps in the input,
+/// such as a device stalling, rather than as clock drift.
+const DISCONTINUITY_THRESHOLD: i64 = 200_000;
+
+/// Sample counts observed by an [`AudioDriftCorrector`], compared against the pipeline clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DriftMeasurement {
+    pub sample_rate: u32,
+    /// Samples the pipeline clock says should have been captured.
+    pub expected_samples: u64,
+    /// Samples the device actually delivered.
+    pub actual_samples: u64,
+}
+
+impl DriftMeasurement {
+    pub fn expected_duration(&self) -> f64 {
+        self.samples_to_seconds(self.expected_samples)
+    }
+
+    pub fn actual_duration(&self) -> f64 {
+        self.samples_to_seconds(self.actual_samples)
+    }
+
+    /// Drift in seconds. Positive when the device runs fast relative to the pipeline clock.
+    pub fn drift_seconds(&self) -> f64 {
+        self.actual_duration() - self.expected_duration()
+    }
+
+    /// Drift rate in parts per million.
+    pub fn ppm(&self) -> f64 {
+        if self.expected_samples == 0 {
+            return 0.0;
+        }
+
+        (self.actual_samples as f64 - self.expected_samples as f64) * 1_000_000.0
+            / self.expected_samples as f64
+    }
+
+    fn samples_to_seconds(&self, samples: u64) -> f64 {
+        if self.sample_rate == 0 {
+            return 0.0;
+        }
+
+        samples as f64 / self.sample_rate as f64
+    }
+}
+
+/// Shared handle to the latest [`DriftMeasurement`], readable while the pipeline runs.
+#[derive(Debug, Clone, Default)]
+pub struct DriftTracker(Arc<Mutex<DriftMeasurement>>);
+
+impl DriftTracker {
+    pub fn measurement(&self) -> DriftMeasurement {
+        *self.0.lock().unwrap()
+    }
+
+    fn update(&self, measurement: DriftMeasurement) {
+        *self.0.lock().unwrap() = measurement;
+    }
+}
+
+/// Measures how far an audio device's sample clock drifts from the pipeline clock,
+/// and resamples the stream so it stays aligned with the other tracks.
+pub struct AudioDriftCorrector {
+    tag: &'static str,
+    filter: AudioFilter,
+    meter: DriftMeter,
+}
+
+impl AudioDriftCorrector {
+    pub fn init(tag: &'static str, config: AudioInfo) -> Result<Self, MediaError> {
+        let meter = DriftMeter::new(config.rate() as u32);
+        let filter = AudioFilter::init(tag, config, COMPENSATION_SPEC)?;
+
+        Ok(Self { tag, filter, meter })
+    }
+
+    pub fn tracker(&self) -> DriftTracker {
+        self.meter.tracker.clone()
+    }
+}
+
+/// Counts the samples of each frame against the time its timestamps say has passed.
+struct DriftMeter {
+    tracker: DriftTracker,
+    sample_rate: u32,
+    measurement: DriftMeasurement,
+    // Timestamp at which the next frame would start if no samples were dropped or gained.
+    next_pts: Option<i64>,
+    // Microseconds of input gaps excluded from the expected sample count.
+    skipped_time: i64,
+    first_pts: Option<i64>,
+}
+
+impl DriftMeter {
+    fn new(sample_rate: u32) -> Self {
+        Self {
+            tracker: DriftTracker::default(),
+            sample_rate,
+            measurement: DriftMeasurement {
+                sample_rate,
+                ..Default::default()
+            },
+            next_pts: None,
+            skipped_time: 0,
+            first_pts: None,
+        }
+    }
+
+    fn measure(&mut self, frame: &FFAudio) {
+        if let Some(pts) = frame.pts() {
+            self.record(pts, frame.samples() as u64);
+        }
+    }
+
+    fn record(&mut self, pts: i64, samples: u64) {
+        let first_pts = *self.first_pts.get_or_insert(pts);
+
+        if let Some(next_pts) = self.next_pts {
+            let gap = pts - next_pts;
+            if gap > DISCONTINUITY_THRESHOLD {
+                self.skipped_time += gap;
+            }
+        }
+
+        // Both counts are taken at the start of this frame, before its own samples are added.
+        let elapsed = (pts - first_pts - self.skipped_time).max(0) as u64;
+        self.measurement.expected_samples = elapsed * self.sample_rate as u64 / 1_000_000;
+        self.tracker.update(self.measurement);
+
+        self.measurement.actual_samples += samples;
+        self.next_pts = Some(pts + (samples * 1_000_000 / self.sample_rate as u64) as i64);
+    }
+}
+
+impl PipelinePipeTask for AudioDriftCorrector {
+    type Input = FFAudio;
+    type Output = FFAudio;
+
+    fn run(
+        &mut self,
+        ready_signal: PipelineReadySignal,
+        messages: PipelineMessages,
+        input: Receiver<Self::Input>,
+        output: Sender<Self::Output>,
+    ) {
+        println!("Starting {} drift correction thread", self.tag);
+        ready_signal.send(Ok(())).unwrap();
+
+        while let Ok(raw_frame) = input.recv() {
+            self.filter.handle_messages(&messages);
+            self.meter.measure(&raw_frame);
+            self.filter.queue_frame(raw_frame);
+            self.filter.process_frame(&output);
+        }
+
+        let measurement = self.meter.tracker.measurement();
+        println!(
+            "Measured {} drift of {:.3}s ({:.1} ppm). Finishing up drift correction.",
+            self.tag,
+            measurement.drift_seconds(),
+            measurement.ppm()
+        );
+        self.filter.finish(&output);
+
+        println!("Shutting down {} drift correction thread", self.tag);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 48_000;
+
+    /// Feeds 10ms worth of frames by the pipeline clock, each holding `samples_per_frame`
+    /// samples, and returns what was measured at the start of the last one.
+    fn measure_frames(frames: i64, samples_per_frame: u64) -> DriftMeasurement {
+        let mut meter = DriftMeter::new(SAMPLE_RATE);
+        let tracker = meter.tracker.clone();
+
+        for i in 0..frames {
+            meter.record(i * 10_000, samples_per_frame);
+        }
+
+        tracker.measurement()
+    }
+
+    #[test]
+    fn nothing_measured_is_no_drift() {
+        let measurement = DriftTracker::default().measurement();
+
+        assert_eq!(measurement.ppm(), 0.0);
+        assert_eq!(measurement.drift_seconds(), 0.0);
+    }
+
+    #[test]
+    fn device_in_step_with_the_clock_has_no_drift() {
+        let measurement = measure_frames(101, 480);
+
+        assert_eq!(measurement.expected_samples, 48_000);
+        assert_eq!(measurement.actual_samples, 48_000);
+        assert_eq!(measurement.expected_duration(), 1.0);
+        assert_eq!(measurement.drift_seconds(), 0.0);
+        assert_eq!(measurement.ppm(), 0.0);
+    }
+
+    #[test]
+    fn fast_device_drifts_ahead() {
+        let measurement = measure_frames(101, 481);
+
+        assert_eq!(measurement.expected_samples, 48_000);
+        assert_eq!(measurement.actual_samples, 48_100);
+        assert!((measurement.drift_seconds() - 100.0 / 48_000.0).abs() < 1e-9);
+        assert!((measurement.ppm() - 2083.333).abs() < 0.001);
+    }
+
+    #[test]
+    fn slow_device_drifts_behind() {
+        let measurement = measure_frames(101, 479);
+
+        assert_eq!(measurement.expected_samples, 48_000);
+        assert_eq!(measurement.actual_samples, 47_900);
+        assert!((measurement.drift_seconds() + 100.0 / 48_000.0).abs() < 1e-9);
+        assert!((measurement.ppm() + 2083.333).abs() < 0.001);
+    }
+
+    #[test]
+    fn gaps_in_the_input_are_not_drift() {
+        let mut meter = DriftMeter::new(SAMPLE_RATE);
+
+        for i in 0..50 {
+            meter.record(i * 10_000, 480);
+        }
+        // The device stalls for half a second, then carries on
+        for i in 0..51 {
+            meter.record(1_000_000 + i * 10_000, 480);
+        }
+
+        let measurement = meter.tracker.measurement();
+        assert_eq!(measurement.expected_samples, 48_000);
+        assert_eq!(measurement.actual_samples, 48_000);
+        assert_eq!(measurement.ppm(), 0.0);
+    }
+}
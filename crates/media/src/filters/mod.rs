@@ -1,5 +1,42 @@
 mod audio;
+mod drift;
+mod redact;
 mod video;
 
 pub use audio::AudioFilter;
-pub use video::VideoFilter;
+pub use drift::{AudioDriftCorrector, DriftMeasurement, DriftTracker};
+pub use redact::{RedactionRegion, RedactionStyle, VideoRedactor};
+pub use video::{ColorSpace, ScaleAlgorithm, Transpose, VideoFilter, VideoFilterOp};
+
+use std::ffi::CString;
+
+use ffmpeg::filter;
+
+use crate::{pipeline::control::Reconfigure, MediaError};
+
+/// Passes a runtime command to the filters in a graph, e.g. `volume` `volume` `0.5`.
+fn send_command(graph: &mut filter::Graph, reconfigure: &Reconfigure) -> Result<(), MediaError> {
+    let to_cstring = |value: &str| {
+        CString::new(value).map_err(|_| MediaError::Any("Filter commands cannot contain NUL bytes"))
+    };
+    let target = to_cstring(&reconfigure.target)?;
+    let command = to_cstring(&reconfigure.command)?;
+    let argument = to_cstring(&reconfigure.argument)?;
+
+    let result = unsafe {
+        ffmpeg::ffi::avfilter_graph_send_command(
+            graph.as_mut_ptr(),
+            target.as_ptr(),
+            command.as_ptr(),
+            argument.as_ptr(),
+            std::ptr::null_mut(),
+            0,
+            0,
+        )
+    };
+
+    match result {
+        0.. => Ok(()),
+        error => Err(ffmpeg::Error::from(error).into()),
+    }
+}
@@ -0,0 +1,330 @@
+use std::time::{Duration, Instant};
+
+use flume::{Receiver, Sender};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+use crate::{
+    data::{FFVideo, Pixel, VideoInfo},
+    pipeline::{control::PipelineMessages, task::PipelinePipeTask},
+    platform::Bounds,
+    sources::ScreenCaptureTarget,
+    MediaError,
+};
+
+/// How often a captured window's position is checked, so that its redactions can follow it.
+const WINDOW_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Type)]
+#[serde(
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase",
+    tag = "kind"
+)]
+pub enum RedactionStyle {
+    /// Replaces the region with squares of `block_size` pixels, each the average of its area.
+    Pixelate { block_size: u32 },
+    /// Paints over the region with an RGB colour.
+    Fill { color: [u8; 3] },
+}
+
+impl Default for RedactionStyle {
+    fn default() -> Self {
+        Self::Pixelate { block_size: 24 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct RedactionRegion {
+    /// In points, relative to the top left of the captured screen or window. Regions of a
+    /// captured window move along with it.
+    pub bounds: Bounds,
+    #[serde(default)]
+    pub style: RedactionStyle,
+}
+
+/// A region in frame pixels, clipped to the frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PixelRect {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+}
+
+impl PixelRect {
+    fn from_points(
+        bounds: &Bounds,
+        (offset_x, offset_y): (f64, f64),
+        (scale_x, scale_y): (f64, f64),
+        (frame_width, frame_height): (usize, usize),
+    ) -> Option<Self> {
+        let clamp = |value: f64, max: usize| (value.max(0.0) as usize).min(max);
+
+        let left = clamp(((bounds.x + offset_x) * scale_x).floor(), frame_width);
+        let top = clamp(((bounds.y + offset_y) * scale_y).floor(), frame_height);
+        let right = clamp(
+            ((bounds.x + offset_x + bounds.width) * scale_x).ceil(),
+            frame_width,
+        );
+        let bottom = clamp(
+            ((bounds.y + offset_y + bounds.height) * scale_y).ceil(),
+            frame_height,
+        );
+
+        (right > left && bottom > top).then_some(Self {
+            x: left,
+            y: top,
+            width: right - left,
+            height: bottom - top,
+        })
+    }
+}
+
+struct WindowTracking {
+    window_id: u32,
+    initial: Bounds,
+    current: Bounds,
+    last_poll: Option<Instant>,
+}
+
+impl WindowTracking {
+    /// How far the window has moved since recording started, in points.
+    fn offset(&mut self) -> (f64, f64) {
+        if self.last_poll.map_or(true, |last_poll| {
+            last_poll.elapsed() >= WINDOW_POLL_INTERVAL
+        }) {
+            self.last_poll = Some(Instant::now());
+
+            // Keep the last known position while the window is hidden or minimised.
+            if let Some(window) = crate::platform::get_on_screen_windows()
+                .into_iter()
+                .find(|window| window.window_id == self.window_id)
+            {
+                self.current = window.bounds;
+            }
+        }
+
+        (
+            self.current.x - self.initial.x,
+            self.current.y - self.initial.y,
+        )
+    }
+}
+
+/// Pixelates or fills regions of BGRA screen frames before they're encoded, so that what's
+/// behind them never reaches disk.
+pub struct VideoRedactor {
+    tag: &'static str,
+    info: VideoInfo,
+    regions: Vec<RedactionRegion>,
+    /// Frame pixels per point.
+    scale: (f64, f64),
+    tracking: Option<WindowTracking>,
+}
+
+impl VideoRedactor {
+    pub fn init(
+        tag: &'static str,
+        info: VideoInfo,
+        target: &ScreenCaptureTarget,
+        regions: Vec<RedactionRegion>,
+    ) -> Result<Self, MediaError> {
+        if info.pixel_format != Pixel::BGRA {
+            return Err(MediaError::Any(
+                "Redaction is only supported for BGRA frames",
+            ));
+        }
+
+        let (captured, tracking) = match target {
+            ScreenCaptureTarget::Window(window) => (
+                window.bounds,
+                Some(WindowTracking {
+                    window_id: window.id,
+                    initial: window.bounds,
+                    current: window.bounds,
+                    last_poll: None,
+                }),
+            ),
+            ScreenCaptureTarget::Screen => (crate::platform::get_main_display_bounds(), None),
+        };
+
+        Ok(Self {
+            tag,
+            info,
+            regions,
+            scale: (
+                info.width as f64 / captured.width,
+                info.height as f64 / captured.height,
+            ),
+            tracking,
+        })
+    }
+
+    fn redact_frame(&mut self, frame: &mut FFVideo) {
+        let offset = self
+            .tracking
+            .as_mut()
+            .map_or((0.0, 0.0), WindowTracking::offset);
+        let size = (self.info.width as usize, self.info.height as usize);
+        let stride = frame.stride(0);
+        let data = frame.data_mut(0);
+
+        for region in &self.regions {
+            let Some(rect) = PixelRect::from_points(&region.bounds, offset, self.scale, size)
+            else {
+                continue;
+            };
+
+            match region.style {
+                RedactionStyle::Pixelate { block_size } => {
+                    pixelate(data, stride, rect, block_size.max(1) as usize)
+                }
+                RedactionStyle::Fill { color: [r, g, b] } => {
+                    fill(data, stride, rect, [b, g, r, 255])
+                }
+            }
+        }
+    }
+}
+
+const BYTES_PER_PIXEL: usize = 4;
+
+fn fill(data: &mut [u8], stride: usize, rect: PixelRect, pixel: [u8; BYTES_PER_PIXEL]) {
+    for y in rect.y..rect.y + rect.height {
+        let row = y * stride + rect.x * BYTES_PER_PIXEL;
+        for chunk in data[row..row + rect.width * BYTES_PER_PIXEL].chunks_exact_mut(BYTES_PER_PIXEL)
+        {
+            chunk.copy_from_slice(&pixel);
+        }
+    }
+}
+
+fn pixelate(data: &mut [u8], stride: usize, rect: PixelRect, block_size: usize) {
+    for block_y in (rect.y..rect.y + rect.height).step_by(block_size) {
+        for block_x in (rect.x..rect.x + rect.width).step_by(block_size) {
+            let block = PixelRect {
+                x: block_x,
+                y: block_y,
+                width: block_size.min(rect.x + rect.width - block_x),
+                height: block_size.min(rect.y + rect.height - block_y),
+            };
+
+            let mut sums = [0usize; BYTES_PER_PIXEL];
+            for y in block.y..block.y + block.height {
+                let row = y * stride + block.x * BYTES_PER_PIXEL;
+                for chunk in
+                    data[row..row + block.width * BYTES_PER_PIXEL].chunks_exact(BYTES_PER_PIXEL)
+                {
+                    for (sum, value) in sums.iter_mut().zip(chunk) {
+                        *sum += *value as usize;
+                    }
+                }
+            }
+
+            let count = block.width * block.height;
+            fill(data, stride, block, sums.map(|sum| (sum / count) as u8));
+        }
+    }
+}
+
+impl PipelinePipeTask for VideoRedactor {
+    type Input = FFVideo;
+    type Output = FFVideo;
+
+    fn run(
+        &mut self,
+        ready_signal: crate::pipeline::task::PipelineReadySignal,
+        _messages: PipelineMessages,
+        input: Receiver<Self::Input>,
+        output: Sender<Self::Output>,
+    ) {
+        println!("Starting {} redaction thread", self.tag);
+        ready_signal.send(Ok(())).unwrap();
+
+        while let Ok(mut frame) = input.recv() {
+            self.redact_frame(&mut frame);
+
+            if output.send(frame).is_err() {
+                eprintln!("{} redaction output is unreachable", self.tag);
+                break;
+            }
+        }
+
+        println!("Shutting down {} redaction thread", self.tag);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bounds(x: f64, y: f64, width: f64, height: f64) -> Bounds {
+        Bounds {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn regions_are_scaled_offset_and_clipped() {
+        let rect = |bounds, offset| PixelRect::from_points(&bounds, offset, (2.0, 2.0), (100, 50));
+
+        assert_eq!(
+            rect(bounds(10.0, 5.0, 4.5, 2.0), (0.0, 0.0)),
+            Some(PixelRect {
+                x: 20,
+                y: 10,
+                width: 9,
+                height: 4
+            })
+        );
+        assert_eq!(
+            rect(bounds(10.0, 5.0, 100.0, 100.0), (-12.0, 0.0)),
+            Some(PixelRect {
+                x: 0,
+                y: 10,
+                width: 100,
+                height: 40
+            })
+        );
+        assert_eq!(rect(bounds(60.0, 0.0, 10.0, 10.0), (0.0, 0.0)), None);
+    }
+
+    #[test]
+    fn pixelate_averages_each_block() {
+        let (width, height, stride) = (4, 2, 5 * BYTES_PER_PIXEL);
+        let mut data = vec![0u8; stride * height];
+        for y in 0..height {
+            for x in 0..width {
+                data[y * stride + x * BYTES_PER_PIXEL..][..BYTES_PER_PIXEL]
+                    .fill((y * width + x) as u8 * 10);
+            }
+        }
+        // Padding at the end of each row must be left alone.
+        data[width * BYTES_PER_PIXEL] = 255;
+
+        let rect = PixelRect {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        };
+        pixelate(&mut data, stride, rect, 3);
+
+        let pixel = |x: usize, y: usize| data[y * stride + x * BYTES_PER_PIXEL];
+        // Blocks are 3x2 and 1x2, averaging [0, 10, 20, 40, 50, 60] and [30, 70].
+        assert_eq!(
+            (0..width).map(|x| pixel(x, 0)).collect::<Vec<_>>(),
+            [30, 30, 30, 50]
+        );
+        assert_eq!(
+            (0..width).map(|x| pixel(x, 1)).collect::<Vec<_>>(),
+            [30, 30, 30, 50]
+        );
+        assert_eq!(data[width * BYTES_PER_PIXEL], 255);
+    }
+}
@@ -1,22 +1,258 @@
 use ffmpeg::{filter, format::pixel::Pixel};
+use serde::{Deserialize, Serialize};
+use specta::Type;
 
 use crate::{
-    data::{FFVideo, VideoInfo},
-    pipeline::task::PipelinePipeTask,
+    data::{FFRational, FFVideo, VideoInfo},
+    pipeline::{
+        control::{PipelineMessage, PipelineMessages},
+        task::PipelinePipeTask,
+    },
     MediaError,
 };
 use flume::Sender;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub enum ScaleAlgorithm {
+    FastBilinear,
+    #[default]
+    Bilinear,
+    Bicubic,
+    Lanczos,
+    /// Nearest neighbour. Keeps hard edges, e.g. for pixel art or text at integer scales.
+    Neighbor,
+    Area,
+}
+
+impl ScaleAlgorithm {
+    fn flag(&self) -> &'static str {
+        match self {
+            Self::FastBilinear => "fast_bilinear",
+            Self::Bilinear => "bilinear",
+            Self::Bicubic => "bicubic",
+            Self::Lanczos => "lanczos",
+            Self::Neighbor => "neighbor",
+            Self::Area => "area",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub enum Transpose {
+    /// Rotate 90 degrees clockwise.
+    Clock,
+    /// Rotate 90 degrees counter-clockwise.
+    CounterClock,
+    /// Rotate 90 degrees clockwise and flip vertically.
+    ClockFlip,
+    /// Rotate 90 degrees counter-clockwise and flip vertically.
+    CounterClockFlip,
+}
+
+impl Transpose {
+    fn dir(&self) -> &'static str {
+        match self {
+            Self::Clock => "clock",
+            Self::CounterClock => "cclock",
+            Self::ClockFlip => "clock_flip",
+            Self::CounterClockFlip => "cclock_flip",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub enum ColorSpace {
+    Bt601,
+    Bt709,
+    Bt2020,
+}
+
+impl ColorSpace {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Bt601 => "bt601-6-525",
+            Self::Bt709 => "bt709",
+            Self::Bt2020 => "bt2020",
+        }
+    }
+}
+
+/// A single step of a video filter graph. Operations are applied in order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase", rename_all_fields = "camelCase", tag = "op")]
+pub enum VideoFilterOp {
+    Crop {
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    },
+    Scale {
+        width: u32,
+        height: u32,
+        #[serde(default)]
+        algorithm: ScaleAlgorithm,
+    },
+    Fps {
+        fps: u32,
+    },
+    /// Places the frame on a `width` x `height` canvas. Centered unless `x` and `y` are set.
+    Pad {
+        width: u32,
+        height: u32,
+        #[serde(default)]
+        x: Option<u32>,
+        #[serde(default)]
+        y: Option<u32>,
+        #[serde(default = "default_pad_color")]
+        color: String,
+    },
+    Transpose {
+        direction: Transpose,
+    },
+    /// Rotates clockwise. Multiples of 90 degrees swap the dimensions as needed, other angles
+    /// keep the frame size and fill the uncovered corners with black.
+    Rotate {
+        degrees: f32,
+    },
+    ConvertColorSpace {
+        to: ColorSpace,
+        /// Overrides the colour space the input is tagged with, for sources that don't tag it.
+        #[serde(default)]
+        from: Option<ColorSpace>,
+    },
+    Deinterlace,
+    /// Reduces noise, e.g. from webcams in low light. Uses FFmpeg's defaults if `strength`
+    /// is unset.
+    Denoise {
+        #[serde(default)]
+        strength: Option<f32>,
+    },
+    /// A raw FFmpeg filter spec, e.g. `eq=brightness=0.1`.
+    Raw {
+        spec: String,
+    },
+}
+
+fn default_pad_color() -> String {
+    "black".to_string()
+}
+
+impl VideoFilterOp {
+    /// The operations `VideoFilter::init` uses: scale to the output size, then convert to its
+    /// frame rate.
+    pub fn defaults(output_config: &VideoInfo) -> Vec<Self> {
+        vec![
+            Self::Scale {
+                width: output_config.width,
+                height: output_config.height,
+                algorithm: ScaleAlgorithm::Bilinear,
+            },
+            Self::Fps {
+                fps: output_config.frame_rate.numerator() as u32,
+            },
+        ]
+    }
+
+    fn spec(&self) -> String {
+        match self {
+            Self::Crop {
+                x,
+                y,
+                width,
+                height,
+            } => format!("crop={width}:{height}:{x}:{y}"),
+            Self::Scale {
+                width,
+                height,
+                algorithm,
+            } => format!("scale={width}x{height}:flags={}", algorithm.flag()),
+            Self::Fps { fps } => format!("fps=fps={fps}"),
+            Self::Pad {
+                width,
+                height,
+                x,
+                y,
+                color,
+            } => {
+                let x = x.map_or("(ow-iw)/2".to_string(), |x| x.to_string());
+                let y = y.map_or("(oh-ih)/2".to_string(), |y| y.to_string());
+                format!("pad={width}:{height}:{x}:{y}:color={color}")
+            }
+            Self::Transpose { direction } => format!("transpose=dir={}", direction.dir()),
+            Self::Rotate { degrees } => {
+                let degrees = degrees.rem_euclid(360.0);
+                match (degrees / 90.0).fract() == 0.0 {
+                    true => match degrees as u32 {
+                        90 => "transpose=dir=clock".to_string(),
+                        180 => "hflip,vflip".to_string(),
+                        270 => "transpose=dir=cclock".to_string(),
+                        _ => "null".to_string(),
+                    },
+                    false => format!("rotate=angle={degrees}*PI/180:fillcolor=black"),
+                }
+            }
+            Self::ConvertColorSpace { to, from } => match from {
+                Some(from) => format!("colorspace=all={}:iall={}", to.name(), from.name()),
+                None => format!("colorspace=all={}", to.name()),
+            },
+            Self::Deinterlace => "yadif".to_string(),
+            Self::Denoise { strength } => match strength {
+                Some(strength) => format!("hqdn3d=luma_spatial={strength}"),
+                None => "hqdn3d".to_string(),
+            },
+            Self::Raw { spec } => spec.clone(),
+        }
+    }
+}
+
+/// Joins `ops` into a filter graph spec. Encoders take frames timestamped in `1/frame_rate`,
+/// which only `fps` converts to, so it's appended at `frame_rate` if `ops` don't include it.
+fn graph_spec(ops: &[VideoFilterOp], frame_rate: FFRational) -> String {
+    let mut specs = ops.iter().map(VideoFilterOp::spec).collect::<Vec<_>>();
+
+    if !ops.iter().any(|op| matches!(op, VideoFilterOp::Fps { .. })) {
+        specs.push(format!(
+            "fps=fps={}/{}",
+            frame_rate.numerator(),
+            frame_rate.denominator()
+        ));
+    }
+
+    specs.join(",")
+}
+
 pub struct VideoFilter {
     tag: &'static str,
     filter_graph: filter::Graph,
+    output_config: VideoInfo,
 }
 
 impl VideoFilter {
+    /// Scales and converts the frame rate of the input to match `output_config`.
     pub fn init(
         tag: &'static str,
         input_config: VideoInfo,
         output_config: VideoInfo,
+    ) -> Result<Self, MediaError> {
+        Self::with_ops(
+            tag,
+            input_config,
+            &VideoFilterOp::defaults(&output_config),
+            output_config.pixel_format,
+        )
+    }
+
+    /// Applies `ops` in order, converting the result to `pixel_format`. Without an `Fps`
+    /// operation, the input's frame rate is kept.
+    pub fn with_ops(
+        tag: &'static str,
+        input_config: VideoInfo,
+        ops: &[VideoFilterOp],
+        pixel_format: Pixel,
     ) -> Result<Self, MediaError> {
         let mut filter_graph = filter::Graph::new();
 
@@ -35,59 +271,104 @@ impl VideoFilter {
         input.set_pixel_format(input_config.pixel_format);
 
         let mut output = filter_graph.get("out").unwrap();
-        output.set_pixel_format(output_config.pixel_format);
-
-        // TODO: Remove fps hardcoding?
-        let spec = format!(
-            "scale={}x{}:flags=bilinear,fps=fps={}",
-            output_config.width,
-            output_config.height,
-            output_config.frame_rate.numerator()
-        );
+        output.set_pixel_format(pixel_format);
+
         filter_graph
             .output("in", 0)?
             .input("out", 0)?
-            .parse(&spec)?;
+            .parse(&graph_spec(ops, input_config.frame_rate))?;
         filter_graph.validate()?;
 
-        Ok(Self { filter_graph, tag })
+        let output_config = sink_config(&mut filter_graph, input_config, pixel_format);
+
+        Ok(Self {
+            tag,
+            filter_graph,
+            output_config,
+        })
+    }
+
+    /// The size, frame rate and format of the frames this filter produces.
+    pub fn output_info(&self) -> VideoInfo {
+        self.output_config
     }
 
-    fn queue_frame(&mut self, frame: FFVideo) {
-        self.filter_graph
-            .get("in")
-            .unwrap()
-            .source()
-            .add(&frame)
-            .unwrap();
+    pub(crate) fn queue_frame(&mut self, frame: FFVideo) -> Result<(), MediaError> {
+        self.filter_graph.get("in").unwrap().source().add(&frame)?;
+
+        Ok(())
     }
 
-    fn process_frame(&mut self, output: &Sender<FFVideo>) {
-        let mut filtered_frame = FFVideo::empty();
+    pub(crate) fn process_frame(&mut self, output: &Sender<FFVideo>) -> Result<(), MediaError> {
+        loop {
+            let mut filtered_frame = FFVideo::empty();
 
-        // TODO: Handle errors that are not EGAIN/"needs more data"
-        while self
-            .filter_graph
-            .get("out")
-            .unwrap()
-            .sink()
-            .frame(&mut filtered_frame)
-            .is_ok()
-        {
-            output.send(filtered_frame).unwrap();
-            filtered_frame = FFVideo::empty();
+            match self
+                .filter_graph
+                .get("out")
+                .unwrap()
+                .sink()
+                .frame(&mut filtered_frame)
+            {
+                Ok(()) => output
+                    .send(filtered_frame)
+                    .map_err(|_| MediaError::Any("The next task has stopped"))?,
+                // The graph needs more input, or has been fully flushed.
+                Err(ffmpeg::Error::Other {
+                    errno: ffmpeg::util::error::EAGAIN,
+                })
+                | Err(ffmpeg::Error::Eof) => return Ok(()),
+                Err(error) => return Err(error.into()),
+            }
         }
     }
 
-    fn finish(&mut self, output: &Sender<FFVideo>) {
-        self.filter_graph
-            .get("in")
-            .unwrap()
-            .source()
-            .flush()
-            .unwrap();
+    fn handle_messages(&mut self, messages: &PipelineMessages) {
+        for message in messages.pending() {
+            if let PipelineMessage::Reconfigure(reconfigure) = message {
+                if let Err(error) = super::send_command(&mut self.filter_graph, &reconfigure) {
+                    eprintln!("Failed to reconfigure {} filter: {error}", self.tag);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn finish(&mut self, output: &Sender<FFVideo>) -> Result<(), MediaError> {
+        self.filter_graph.get("in").unwrap().source().flush()?;
+
+        self.process_frame(output)
+    }
+}
+
+/// Reads what the graph negotiated for its output, since operations like `transpose` or raw
+/// specs can change the frame size.
+fn sink_config(
+    graph: &mut filter::Graph,
+    input_config: VideoInfo,
+    pixel_format: Pixel,
+) -> VideoInfo {
+    let sink = graph.get("out").unwrap();
+
+    let (width, height, frame_rate, time_base) = unsafe {
+        let sink = sink.as_ptr();
+        (
+            ffmpeg::ffi::av_buffersink_get_w(sink),
+            ffmpeg::ffi::av_buffersink_get_h(sink),
+            FFRational::from(ffmpeg::ffi::av_buffersink_get_frame_rate(sink)),
+            FFRational::from(ffmpeg::ffi::av_buffersink_get_time_base(sink)),
+        )
+    };
 
-        self.process_frame(output);
+    VideoInfo {
+        pixel_format,
+        width: width as u32,
+        height: height as u32,
+        time_base,
+        // Without an `fps` operation the graph doesn't know the frame rate.
+        frame_rate: match frame_rate.numerator() {
+            0 => input_config.frame_rate,
+            _ => frame_rate,
+        },
     }
 }
 
@@ -98,6 +379,7 @@ impl PipelinePipeTask for VideoFilter {
     fn run(
         &mut self,
         ready_signal: crate::pipeline::task::PipelineReadySignal,
+        messages: PipelineMessages,
         input: flume::Receiver<Self::Input>,
         output: Sender<Self::Output>,
     ) {
@@ -105,16 +387,93 @@ impl PipelinePipeTask for VideoFilter {
         ready_signal.send(Ok(())).unwrap();
 
         while let Ok(raw_frame) = input.recv() {
-            self.queue_frame(raw_frame);
-            self.process_frame(&output);
+            self.handle_messages(&messages);
+
+            if let Err(error) = self
+                .queue_frame(raw_frame)
+                .and_then(|_| self.process_frame(&output))
+            {
+                eprintln!("Failed to filter {} frame: {error}", self.tag);
+            }
         }
 
         println!(
             "Received last raw {} frame. Finishing up filtering.",
             self.tag
         );
-        self.finish(&output);
+        if let Err(error) = self.finish(&output) {
+            eprintln!("Failed to finish {} filtering: {error}", self.tag);
+        }
 
         println!("Shutting down {} video filtering thread", self.tag);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::RawVideoFormat;
+
+    #[test]
+    fn defaults_match_the_original_spec() {
+        let output = VideoInfo::from_raw(RawVideoFormat::Bgra, 2560, 1440, 60).scaled(1920, 30);
+
+        assert_eq!(
+            graph_spec(&VideoFilterOp::defaults(&output), output.frame_rate),
+            "scale=1920x1080:flags=bilinear,fps=fps=30"
+        );
+        assert_eq!(graph_spec(&[], FFRational(30, 1)), "fps=fps=30/1");
+        assert_eq!(
+            graph_spec(&[VideoFilterOp::Deinterlace], FFRational(30000, 1001)),
+            "yadif,fps=fps=30000/1001"
+        );
+    }
+
+    #[test]
+    fn output_info_follows_the_graph() {
+        ffmpeg::init().unwrap();
+        let input = VideoInfo::from_raw(RawVideoFormat::Bgra, 1280, 720, 30);
+
+        let filter = VideoFilter::with_ops(
+            "test",
+            input,
+            &[
+                VideoFilterOp::Crop {
+                    x: 0,
+                    y: 0,
+                    width: 640,
+                    height: 480,
+                },
+                VideoFilterOp::Rotate { degrees: -90.0 },
+                VideoFilterOp::Fps { fps: 24 },
+            ],
+            Pixel::YUV420P,
+        )
+        .unwrap();
+
+        let output = filter.output_info();
+        assert_eq!((output.width, output.height), (480, 640));
+        assert_eq!(output.frame_rate, FFRational(24, 1));
+        assert_eq!(output.time_base, FFRational(1, 24));
+        assert_eq!(output.pixel_format, Pixel::YUV420P);
+    }
+
+    #[test]
+    fn output_is_timestamped_in_frames_without_an_fps_operation() {
+        ffmpeg::init().unwrap();
+        let input = VideoInfo::from_raw(RawVideoFormat::Bgra, 1280, 720, 30);
+
+        let filter = VideoFilter::with_ops(
+            "test",
+            input,
+            &[VideoFilterOp::Rotate { degrees: 90.0 }],
+            Pixel::YUV420P,
+        )
+        .unwrap();
+
+        let output = filter.output_info();
+        assert_eq!((output.width, output.height), (720, 1280));
+        assert_eq!(output.frame_rate, FFRational(30, 1));
+        assert_eq!(output.time_base, FFRational(1, 30));
+    }
+}
@@ -7,6 +7,7 @@
 use thiserror::Error;
 
 pub mod data;
+pub mod description;
 pub mod encoders;
 pub mod feeds;
 pub mod filters;
@@ -34,15 +35,32 @@ pub enum MediaError {
     #[error("Failed to launch task: #{0}")]
     TaskLaunch(String),
 
+    #[error("The pipeline has no task named {0}")]
+    UnknownTask(String),
+
+    #[error("Failed to launch pipeline tasks: {}", describe_launch_failures(.0))]
+    PipelineLaunch(Vec<(String, MediaError)>),
+
     #[error("FFmpeg error: {0}")]
     FFmpeg(#[from] ffmpeg::Error),
 
     #[error("Camera error: {0}")]
     Nokhwa(#[from] nokhwa::NokhwaError),
 
+    #[error("Invalid pipeline description: {0}")]
+    InvalidDescription(#[from] description::DescriptionError),
+
     #[error("Could not find a suitable codec for {0}")]
     MissingCodec(&'static str),
 
     #[error("Device {0} is unreachable. It may have been disconnected")]
     DeviceUnreachable(String),
 }
+
+fn describe_launch_failures(failures: &[(String, MediaError)]) -> String {
+    failures
+        .iter()
+        .map(|(name, error)| format!("{name} ({error})"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
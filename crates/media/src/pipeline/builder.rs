@@ -1,10 +1,12 @@
 use flume::Receiver;
+use futures::future::join_all;
 use indexmap::IndexMap;
 use std::thread::{self, JoinHandle};
 
 use crate::pipeline::{
     clock::CloneFrom,
-    control::ControlBroadcast,
+    control::{Control, ControlBroadcast},
+    join_tasks,
     task::{PipelinePipeTask, PipelineReadySignal, PipelineSinkTask, PipelineSourceTask},
     MediaError, Pipeline, PipelineClock,
 };
@@ -75,7 +77,7 @@ impl<T: PipelineClock> PipelineBuilder<T> {
     pub async fn build(self) -> Result<Pipeline<T>, MediaError> {
         let Self {
             clock,
-            control,
+            mut control,
             tasks,
         } = self;
 
@@ -84,19 +86,28 @@ impl<T: PipelineClock> PipelineBuilder<T> {
         }
 
         let mut task_handles = IndexMap::new();
+        let mut ready_signals = Vec::with_capacity(tasks.len());
 
-        // TODO: Shut down tasks if launch failed.
         for (name, task) in tasks.into_iter() {
-            // TODO: Wait for these in parallel?
-            let _ = task
-                .ready_signal
-                .recv_async()
-                .await
-                .map_err(|_| MediaError::TaskLaunch(name.clone()))??;
-
+            ready_signals.push(wait_until_ready(name.clone(), task.ready_signal));
             task_handles.insert(name, task.join_handle);
         }
 
+        let failures: Vec<(String, MediaError)> = join_all(ready_signals)
+            .await
+            .into_iter()
+            .filter_map(Result::err)
+            .collect();
+
+        if !failures.is_empty() {
+            eprintln!("Pipeline failed to launch. Shutting down all tasks.");
+            // Tasks that already failed have dropped their control receivers, so some sends may fail.
+            let _ = control.broadcast(Control::Shutdown).await;
+            join_tasks(task_handles).await;
+
+            return Err(MediaError::PipelineLaunch(failures));
+        }
+
         Ok(Pipeline {
             clock,
             control,
@@ -106,6 +117,20 @@ impl<T: PipelineClock> PipelineBuilder<T> {
     }
 }
 
+async fn wait_until_ready(
+    name: String,
+    ready_signal: Receiver<Result<(), MediaError>>,
+) -> Result<(), (String, MediaError)> {
+    match ready_signal.recv_async().await {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(error)) => Err((name, error)),
+        Err(_) => {
+            let error = MediaError::TaskLaunch(name.clone());
+            Err((name, error))
+        }
+    }
+}
+
 pub struct PipelinePathBuilder<Clock: PipelineClock, PreviousOutput: Send> {
     pipeline: PipelineBuilder<Clock>,
     next_input: Receiver<PreviousOutput>,
@@ -124,10 +149,12 @@ impl<Clock: PipelineClock, PreviousOutput: Send + 'static>
             next_input: input,
         } = self;
 
+        let name = name.into();
         let (output, next_input) = flume::bounded(task.queue_size());
+        let messages = pipeline.control.add_message_listener(name.clone());
 
-        pipeline.spawn_task(name.into(), move |ready_signal| {
-            task.run(ready_signal, input, output);
+        pipeline.spawn_task(name, move |ready_signal| {
+            task.run(ready_signal, messages, input, output);
         });
 
         PipelinePathBuilder {
@@ -146,10 +173,114 @@ impl<Clock: PipelineClock, PreviousOutput: Send + 'static>
             next_input: input,
         } = self;
 
-        pipeline.spawn_task(name.into(), move |ready_signal| {
-            task.run(ready_signal, input);
+        let name = name.into();
+        let messages = pipeline.control.add_message_listener(name.clone());
+
+        pipeline.spawn_task(name, move |ready_signal| {
+            task.run(ready_signal, messages, input);
         });
 
         pipeline
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    use flume::Sender;
+
+    use super::*;
+    use crate::pipeline::{
+        control::{PipelineControlSignal, PipelineMessages},
+        SynchronisedClock,
+    };
+
+    /// Fails its ready signal or waits to be shut down, recording when its thread finishes.
+    struct Launch {
+        fails: bool,
+        finished: Arc<AtomicBool>,
+    }
+
+    impl PipelineSourceTask for Launch {
+        type Output = ();
+        type Clock = SynchronisedClock<()>;
+
+        fn run(
+            &mut self,
+            _: Self::Clock,
+            ready_signal: PipelineReadySignal,
+            mut control_signal: PipelineControlSignal,
+            _: Sender<()>,
+        ) {
+            if self.fails {
+                let _ = ready_signal.send(Err(MediaError::Any("no device")));
+            } else {
+                let _ = ready_signal.send(Ok(()));
+                while !matches!(
+                    control_signal.blocking_last(),
+                    Some(Control::Shutdown) | None
+                ) {}
+            }
+
+            self.finished.store(true, Ordering::Release);
+        }
+    }
+
+    struct Discard;
+
+    impl PipelineSinkTask for Discard {
+        type Input = ();
+
+        fn run(
+            &mut self,
+            ready_signal: PipelineReadySignal,
+            _: PipelineMessages,
+            input: Receiver<()>,
+        ) {
+            let _ = ready_signal.send(Ok(()));
+            while input.recv().is_ok() {}
+        }
+    }
+
+    #[test]
+    fn failed_launches_shut_down_every_task() {
+        let failed = Arc::new(AtomicBool::new(false));
+        let launched = Arc::new(AtomicBool::new(false));
+
+        let result = futures::executor::block_on(
+            PipelineBuilder::new(SynchronisedClock::<()>::new())
+                .source(
+                    "broken",
+                    Launch {
+                        fails: true,
+                        finished: failed.clone(),
+                    },
+                )
+                .sink("broken_sink", Discard)
+                .source(
+                    "working",
+                    Launch {
+                        fails: false,
+                        finished: launched.clone(),
+                    },
+                )
+                .sink("working_sink", Discard)
+                .build(),
+        );
+
+        let Err(error @ MediaError::PipelineLaunch(_)) = result else {
+            panic!("The pipeline launched");
+        };
+        assert_eq!(
+            error.to_string(),
+            "Failed to launch pipeline tasks: broken (Media error: no device)"
+        );
+
+        assert!(failed.load(Ordering::Acquire));
+        assert!(launched.load(Ordering::Acquire));
+    }
+}
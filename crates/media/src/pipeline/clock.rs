@@ -23,10 +23,66 @@ where
     }
 }
 
+/// Where a [`SynchronisedClock`] gets the current time from.
+pub trait TimeSource: Clone + Send + Sync + 'static {
+    fn now(&self) -> Instant;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RealTime;
+
+impl TimeSource for RealTime {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A time source that only moves when told to, so that clock behaviour can be tested deterministically.
+/// Clones share the same time.
+#[derive(Debug, Clone)]
+pub struct SimulatedTime {
+    origin: Instant,
+    elapsed_nanoseconds: Arc<AtomicU64>,
+}
+
+impl SimulatedTime {
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+            elapsed_nanoseconds: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub fn advance(&self, delta: Duration) {
+        let delta_nanos = delta.as_nanos().try_into().unwrap();
+        self.elapsed_nanoseconds
+            .fetch_add(delta_nanos, Ordering::AcqRel);
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_nanos(self.elapsed_nanoseconds.load(Ordering::Acquire))
+    }
+}
+
+impl Default for SimulatedTime {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimeSource for SimulatedTime {
+    fn now(&self) -> Instant {
+        self.origin + self.elapsed()
+    }
+}
+
 pub trait PipelineClock: Clone + Send + 'static {
     fn start(&mut self);
 
     fn stop(&mut self);
+
+    /// Recording time so far, excluding pauses.
+    fn elapsed(&self) -> Duration;
 }
 
 pub trait LocalTimestamp: Sized + Clone {
@@ -55,7 +111,8 @@ impl LocalTimestamp for RawNanoseconds {
 }
 
 #[derive(Debug, Clone)]
-pub struct SynchronisedClock<T: LocalTimestamp> {
+pub struct SynchronisedClock<T: LocalTimestamp, S: TimeSource = RealTime> {
+    time: S,
     local_start_time: Option<Instant>,
     global_start_time: Arc<RwLock<Instant>>,
     first_local_timestamp: Option<T>,
@@ -64,11 +121,12 @@ pub struct SynchronisedClock<T: LocalTimestamp> {
     running: Arc<AtomicBool>,
 }
 
-impl<Source: LocalTimestamp, Target: LocalTimestamp> CloneInto<SynchronisedClock<Target>>
-    for SynchronisedClock<Source>
+impl<Source: LocalTimestamp, Target: LocalTimestamp, S: TimeSource>
+    CloneInto<SynchronisedClock<Target, S>> for SynchronisedClock<Source, S>
 {
-    fn clone_into(&self) -> SynchronisedClock<Target> {
+    fn clone_into(&self) -> SynchronisedClock<Target, S> {
         let SynchronisedClock {
+            time,
             global_start_time,
             resume_offset_nanoseconds,
             running,
@@ -76,6 +134,7 @@ impl<Source: LocalTimestamp, Target: LocalTimestamp> CloneInto<SynchronisedClock
         } = self.clone();
 
         SynchronisedClock {
+            time,
             global_start_time,
             resume_offset_nanoseconds,
             running,
@@ -91,9 +150,16 @@ impl<T: LocalTimestamp> SynchronisedClock<T> {
     }
 
     pub fn new() -> Self {
+        Self::with_time_source(RealTime)
+    }
+}
+
+impl<T: LocalTimestamp, S: TimeSource> SynchronisedClock<T, S> {
+    pub fn with_time_source(time: S) -> Self {
         Self {
+            global_start_time: Arc::new(RwLock::new(time.now())),
+            time,
             local_start_time: None,
-            global_start_time: Arc::new(RwLock::new(Instant::now())),
             first_local_timestamp: None,
             resume_offset_nanoseconds: Arc::new(AtomicU64::new(0)),
             running: Arc::new(AtomicBool::new(false)),
@@ -121,7 +187,7 @@ impl<T: LocalTimestamp> SynchronisedClock<T> {
     }
 
     pub fn timestamp_for(&mut self, local: T) -> Option<i64> {
-        let now = Instant::now();
+        let now = self.time.now();
 
         if !self.running() {
             return None;
@@ -154,12 +220,12 @@ impl<T: LocalTimestamp> SynchronisedClock<T> {
     }
 }
 
-impl PipelineClock for SynchronisedClock<()> {
+impl<S: TimeSource> PipelineClock for SynchronisedClock<(), S> {
     fn start(&mut self) {
         if !self.running() {
             let mut start_time = self.global_start_time.write().unwrap();
 
-            let now = Instant::now();
+            let now = self.time.now();
             *start_time = now;
             self.set_running(true);
         }
@@ -169,9 +235,186 @@ impl PipelineClock for SynchronisedClock<()> {
         if self.running() {
             self.set_running(false);
 
-            let now = Instant::now();
+            let now = self.time.now();
             let start_time = self.global_start_time.read().unwrap();
             self.update_resume_offset(now - *start_time);
         }
     }
+
+    fn elapsed(&self) -> Duration {
+        if !self.running() {
+            return self.resume_offset();
+        }
+
+        let start_time = self.global_start_time.read().unwrap();
+        self.time.now() - *start_time + self.resume_offset()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cpal::StreamInstant;
+
+    type Clock = SynchronisedClock<(), SimulatedTime>;
+
+    fn millis(value: u64) -> Duration {
+        Duration::from_millis(value)
+    }
+
+    fn micros(value: Duration) -> Option<i64> {
+        Some(value.as_micros().try_into().unwrap())
+    }
+
+    fn local_clock<T: LocalTimestamp>(clock: &Clock) -> SynchronisedClock<T, SimulatedTime> {
+        CloneInto::clone_into(clock)
+    }
+
+    fn stream_instant(offset: Duration) -> StreamInstant {
+        // SAFETY: `StreamInstant` is a plain pair of integers and cpal offers no public constructor,
+        // so an all-zero value is a valid instant to offset from.
+        let zero: StreamInstant = unsafe { std::mem::zeroed() };
+        zero.add(offset).unwrap()
+    }
+
+    /// Runs the clock through three recording segments separated by pauses, producing local
+    /// timestamps with `local_at`. Local time starts at an arbitrary point and jumps across each
+    /// pause, which the clock should ignore.
+    fn assert_pause_resume_cycles<T: LocalTimestamp>(local_at: impl Fn(Duration) -> T) {
+        let time = SimulatedTime::new();
+        let mut clock = Clock::with_time_source(time.clone());
+        let mut local = local_clock::<T>(&clock);
+
+        assert_eq!(local.timestamp_for(local_at(millis(5_000))), None);
+
+        // First segment: 0ms - 200ms
+        clock.start();
+        time.advance(millis(100));
+        assert_eq!(
+            local.timestamp_for(local_at(millis(5_000))),
+            micros(millis(100))
+        );
+        time.advance(millis(50));
+        assert_eq!(
+            local.timestamp_for(local_at(millis(5_050))),
+            micros(millis(150))
+        );
+        time.advance(millis(50));
+        clock.stop();
+
+        time.advance(millis(1_000));
+        assert_eq!(local.timestamp_for(local_at(millis(6_250))), None);
+
+        // Second segment: 200ms - 300ms
+        clock.start();
+        time.advance(millis(10));
+        assert_eq!(
+            local.timestamp_for(local_at(millis(9_000))),
+            micros(millis(210))
+        );
+        time.advance(millis(40));
+        assert_eq!(
+            local.timestamp_for(local_at(millis(9_040))),
+            micros(millis(250))
+        );
+        time.advance(millis(50));
+        clock.stop();
+
+        time.advance(millis(700));
+
+        // Third segment: 300ms onwards
+        clock.start();
+        time.advance(millis(5));
+        assert_eq!(
+            local.timestamp_for(local_at(millis(20_000))),
+            micros(millis(305))
+        );
+        time.advance(millis(1_000));
+        assert_eq!(
+            local.timestamp_for(local_at(millis(21_000))),
+            micros(millis(1_305))
+        );
+    }
+
+    #[test]
+    fn raw_nanoseconds_across_pauses() {
+        assert_pause_resume_cycles(|offset| RawNanoseconds(offset.as_nanos() as u64));
+    }
+
+    #[test]
+    fn instants_across_pauses() {
+        let origin = Instant::now();
+        assert_pause_resume_cycles(|offset| origin + offset);
+    }
+
+    #[test]
+    fn stream_instants_across_pauses() {
+        assert_pause_resume_cycles(stream_instant);
+    }
+
+    #[test]
+    fn local_clocks_created_mid_recording_start_at_current_time() {
+        let time = SimulatedTime::new();
+        let mut clock = Clock::with_time_source(time.clone());
+
+        clock.start();
+        time.advance(millis(300));
+        clock.stop();
+        time.advance(millis(300));
+        clock.start();
+        time.advance(millis(20));
+
+        let mut screen = local_clock::<RawNanoseconds>(&clock);
+        let mut camera = local_clock::<Instant>(&clock);
+        let camera_origin = Instant::now();
+
+        assert_eq!(
+            screen.timestamp_for(RawNanoseconds(42)),
+            micros(millis(320))
+        );
+        time.advance(millis(30));
+        assert_eq!(camera.timestamp_for(camera_origin), micros(millis(350)));
+        assert_eq!(
+            screen.timestamp_for(RawNanoseconds(42 + millis(30).as_nanos() as u64)),
+            micros(millis(350))
+        );
+    }
+
+    #[test]
+    fn repeated_start_and_stop_are_ignored() {
+        let time = SimulatedTime::new();
+        let mut clock = Clock::with_time_source(time.clone());
+        let mut local = local_clock::<RawNanoseconds>(&clock);
+
+        clock.start();
+        time.advance(millis(100));
+        clock.start();
+        assert_eq!(local.timestamp_for(RawNanoseconds(0)), micros(millis(100)));
+
+        clock.stop();
+        time.advance(millis(100));
+        clock.stop();
+        clock.start();
+        time.advance(millis(1));
+        assert_eq!(local.timestamp_for(RawNanoseconds(0)), micros(millis(101)));
+    }
+
+    #[test]
+    fn elapsed_excludes_pauses() {
+        let time = SimulatedTime::new();
+        let mut clock = Clock::with_time_source(time.clone());
+        assert_eq!(clock.elapsed(), Duration::ZERO);
+
+        clock.start();
+        time.advance(millis(100));
+        assert_eq!(clock.elapsed(), millis(100));
+
+        clock.stop();
+        time.advance(millis(500));
+        assert_eq!(clock.elapsed(), millis(100));
+
+        clock.start();
+        time.advance(millis(20));
+        assert_eq!(clock.elapsed(), millis(120));
+    }
 }
@@ -1,7 +1,11 @@
 use flume::{Receiver, Sender, TryRecvError};
 use indexmap::IndexMap;
 
-use crate::pipeline::MediaError;
+use crate::{
+    encoders::StreamStatus,
+    pipeline::{limits::RecordingLimit, MediaError},
+    sources::AudioInputStatus,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Control {
@@ -10,9 +14,83 @@ pub enum Control {
     Shutdown,
 }
 
+/// Messages that can be delivered to running tasks without affecting playback.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PipelineMessage {
+    /// A point of interest at the given pipeline timestamp, in microseconds.
+    Marker { timestamp: i64, label: String },
+    /// Asks encoders to start a new group of pictures with the next frame.
+    RequestKeyframe,
+    /// Changes a parameter of a running task, e.g. the gain of a `volume` audio filter.
+    Reconfigure(Reconfigure),
+}
+
+/// Forwarded to FFmpeg filter graphs as `avfilter_graph_send_command(target, command, argument)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reconfigure {
+    pub target: String,
+    pub command: String,
+    pub argument: String,
+}
+
+/// Updates that tasks report back to whoever is running the pipeline.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PipelineStatus {
+    /// The connection state of an encoder's live stream output changed.
+    Stream(StreamStatus),
+    /// The microphone was lost, came back, or was replaced by another device.
+    AudioInput(AudioInputStatus),
+    /// A file source has sent everything it had. The pipeline can be shut down once the rest
+    /// of it has caught up.
+    EndOfInput,
+    /// A file source couldn't read the rest of its input. As with [`Self::EndOfInput`], the
+    /// pipeline can be shut down once the rest of it has caught up.
+    InputFailed(String),
+    /// The recording reached one of the limits it was given, and should be stopped.
+    LimitReached(RecordingLimit),
+}
+
+/// Sends [`PipelineStatus`] updates from a task, tagged with the task's name.
+#[derive(Debug, Clone)]
+pub struct StatusReporter {
+    task: String,
+    sender: Sender<(String, PipelineStatus)>,
+}
+
+impl StatusReporter {
+    pub fn report(&self, status: PipelineStatus) {
+        // Only fails once the pipeline itself is gone, at which point nobody is listening.
+        let _ = self.sender.send((self.task.clone(), status));
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MessageTarget {
+    All,
+    Task(String),
+}
+
+/// Typed messages sent to a single task. Tasks check for these between frames.
+pub struct PipelineMessages {
+    receiver: Receiver<PipelineMessage>,
+    reporter: StatusReporter,
+}
+
+impl PipelineMessages {
+    /// Drains all messages received since the last call, without blocking.
+    pub fn pending(&self) -> impl Iterator<Item = PipelineMessage> + '_ {
+        self.receiver.try_iter()
+    }
+
+    pub fn reporter(&self) -> StatusReporter {
+        self.reporter.clone()
+    }
+}
+
 pub struct PipelineControlSignal {
     last_value: Option<Control>,
     receiver: Receiver<Control>,
+    messages: PipelineMessages,
 }
 
 impl PipelineControlSignal {
@@ -39,6 +117,10 @@ impl PipelineControlSignal {
         }
     }
 
+    pub fn messages(&self) -> &PipelineMessages {
+        &self.messages
+    }
+
     pub fn blocking_last(&mut self) -> Option<Control> {
         println!("Waiting for play signal...");
         self.last_value = self.receiver.recv().ok();
@@ -49,18 +131,89 @@ impl PipelineControlSignal {
 
 /// An extremely naive broadcast channel. Sends values synchronously to all receivers,
 /// might block if one receiver takes too long to receive value.
-#[derive(Debug, Default)]
+///
+/// Playback controls only go to sources, while messages can be sent to any task.
+/// Status updates flow the other way, from any task back to the pipeline.
+#[derive(Debug)]
 pub(super) struct ControlBroadcast {
     listeners: IndexMap<String, Sender<Control>>,
+    message_listeners: IndexMap<String, Sender<PipelineMessage>>,
+    status: (
+        Sender<(String, PipelineStatus)>,
+        Receiver<(String, PipelineStatus)>,
+    ),
+}
+
+impl Default for ControlBroadcast {
+    fn default() -> Self {
+        Self {
+            listeners: IndexMap::new(),
+            message_listeners: IndexMap::new(),
+            status: flume::unbounded(),
+        }
+    }
 }
 
 impl ControlBroadcast {
     pub fn add_listener(&mut self, name: String) -> PipelineControlSignal {
         let (sender, receiver) = flume::bounded(1);
+        let messages = self.add_message_listener(name.clone());
         self.listeners.insert(name, sender);
         PipelineControlSignal {
             last_value: None,
             receiver,
+            messages,
+        }
+    }
+
+    pub fn add_message_listener(&mut self, name: String) -> PipelineMessages {
+        // Unbounded, so that a busy task never holds up the caller.
+        let (sender, receiver) = flume::unbounded();
+        let reporter = StatusReporter {
+            task: name.clone(),
+            sender: self.status.0.clone(),
+        };
+        self.message_listeners.insert(name, sender);
+        PipelineMessages { receiver, reporter }
+    }
+
+    pub fn status_updates(&self) -> Receiver<(String, PipelineStatus)> {
+        self.status.1.clone()
+    }
+
+    pub fn send_message(
+        &self,
+        target: &MessageTarget,
+        message: PipelineMessage,
+    ) -> Result<(), MediaError> {
+        match target {
+            MessageTarget::All => {
+                let mut any_dropped = false;
+
+                for (name, listener) in self.message_listeners.iter() {
+                    if listener.send(message.clone()).is_err() {
+                        eprintln!("{name} is unreachable!");
+                        any_dropped = true;
+                    }
+                }
+
+                match any_dropped {
+                    false => Ok(()),
+                    true => Err(MediaError::Any(
+                        "Attempted to send a message to a task that has been dropped",
+                    )),
+                }
+            }
+            MessageTarget::Task(name) => {
+                let listener = self
+                    .message_listeners
+                    .get(name)
+                    .ok_or_else(|| MediaError::UnknownTask(name.clone()))?;
+
+                listener.send(message).map_err(|_| {
+                    MediaError::Any("Attempted to send a message to a task that has been dropped")
+                })
+            }
         }
     }
 
@@ -0,0 +1,204 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::pipeline::{
+    control::{Control, PipelineControlSignal, PipelineStatus},
+    PipelineClock,
+};
+
+const CHECK_INTERVAL: Duration = Duration::from_millis(500);
+const MEGABYTE: u64 = 1024 * 1024;
+
+/// Optional points at which a recording should stop by itself.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordingLimits {
+    /// Recording time, excluding pauses.
+    #[serde(default)]
+    pub max_duration_secs: Option<u32>,
+    /// Combined size of the pipeline's output files, including any HLS segments.
+    #[serde(default)]
+    pub max_size_mb: Option<u32>,
+    /// Free space to leave on the disk the recording is written to.
+    #[serde(default)]
+    pub min_free_disk_mb: Option<u32>,
+}
+
+/// The limit that made a recording stop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub enum RecordingLimit {
+    Duration,
+    Size,
+    FreeDiskSpace,
+}
+
+/// What the limits are checked against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Usage {
+    elapsed: Duration,
+    output_bytes: u64,
+    /// `None` if the disk couldn't be queried.
+    free_disk_bytes: Option<u64>,
+}
+
+impl RecordingLimits {
+    pub fn is_empty(&self) -> bool {
+        self.max_duration_secs.is_none()
+            && self.max_size_mb.is_none()
+            && self.min_free_disk_mb.is_none()
+    }
+
+    fn exceeded_by(&self, usage: &Usage) -> Option<RecordingLimit> {
+        if let Some(max) = self.max_duration_secs {
+            if usage.elapsed >= Duration::from_secs(max.into()) {
+                return Some(RecordingLimit::Duration);
+            }
+        }
+        if let Some(max) = self.max_size_mb {
+            if usage.output_bytes >= u64::from(max) * MEGABYTE {
+                return Some(RecordingLimit::Size);
+            }
+        }
+        if let (Some(min), Some(free)) = (self.min_free_disk_mb, usage.free_disk_bytes) {
+            if free < u64::from(min) * MEGABYTE {
+                return Some(RecordingLimit::FreeDiskSpace);
+            }
+        }
+
+        None
+    }
+}
+
+/// The size of the file at `path`, or of every file under it if it's a directory, such as
+/// the one HLS segments are written to.
+fn disk_usage(path: &Path) -> u64 {
+    // Outputs only appear once their encoder has written its header.
+    let Ok(meta) = std::fs::metadata(path) else {
+        return 0;
+    };
+    if !meta.is_dir() {
+        return meta.len();
+    }
+
+    // Segments come and go while this runs, so whatever can't be read is skipped.
+    std::fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .map(|entry| disk_usage(&entry.path()))
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+// The field types of `statvfs` differ between platforms.
+#[allow(clippy::unnecessary_cast)]
+fn free_disk_space(path: &Path) -> Option<u64> {
+    let stat = nix::sys::statvfs::statvfs(path).ok()?;
+
+    Some(stat.blocks_available() as u64 * stat.fragment_size() as u64)
+}
+
+/// Checks the limits while the pipeline is playing, reporting the first one that's reached.
+/// Stopping is left to whoever runs the pipeline, so that outputs are finished properly.
+pub(super) fn watch_limits(
+    limits: RecordingLimits,
+    clock: impl PipelineClock,
+    outputs: Vec<PathBuf>,
+    volume: PathBuf,
+    mut control_signal: PipelineControlSignal,
+) {
+    let reporter = control_signal.messages().reporter();
+
+    loop {
+        match control_signal.last() {
+            Some(Control::Play) => {}
+            Some(Control::Pause) => continue,
+            Some(Control::Shutdown) | None => return,
+        }
+
+        let usage = Usage {
+            elapsed: clock.elapsed(),
+            output_bytes: outputs.iter().map(|path| disk_usage(path)).sum(),
+            free_disk_bytes: free_disk_space(&volume),
+        };
+
+        if let Some(limit) = limits.exceeded_by(&usage) {
+            println!("Recording reached its {limit:?} limit: {usage:?}");
+            reporter.report(PipelineStatus::LimitReached(limit));
+
+            while !matches!(
+                control_signal.blocking_last(),
+                Some(Control::Shutdown) | None
+            ) {}
+            return;
+        }
+
+        std::thread::sleep(CHECK_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disk_usage_includes_segment_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("display.mp4");
+        let segments = dir.path().join("segments");
+        std::fs::write(&file, [0; 100]).unwrap();
+        std::fs::create_dir(&segments).unwrap();
+        std::fs::write(segments.join("segment_00000.ts"), [0; 30]).unwrap();
+        std::fs::write(segments.join("stream.m3u8"), [0; 5]).unwrap();
+
+        assert_eq!(disk_usage(&file), 100);
+        assert_eq!(disk_usage(&segments), 35);
+        assert_eq!(disk_usage(dir.path()), 135);
+        assert_eq!(disk_usage(&dir.path().join("camera.mp4")), 0);
+    }
+
+    #[test]
+    fn limits_are_checked_in_order() {
+        let usage = Usage {
+            elapsed: Duration::from_secs(60),
+            output_bytes: 100 * MEGABYTE,
+            free_disk_bytes: Some(500 * MEGABYTE),
+        };
+        let limits = |max_duration_secs, max_size_mb, min_free_disk_mb| RecordingLimits {
+            max_duration_secs,
+            max_size_mb,
+            min_free_disk_mb,
+        };
+
+        assert_eq!(RecordingLimits::default().exceeded_by(&usage), None);
+        assert_eq!(
+            limits(Some(61), Some(101), Some(499)).exceeded_by(&usage),
+            None
+        );
+        assert_eq!(
+            limits(Some(60), Some(100), Some(501)).exceeded_by(&usage),
+            Some(RecordingLimit::Duration)
+        );
+        assert_eq!(
+            limits(None, Some(100), Some(501)).exceeded_by(&usage),
+            Some(RecordingLimit::Size)
+        );
+        assert_eq!(
+            limits(None, None, Some(501)).exceeded_by(&usage),
+            Some(RecordingLimit::FreeDiskSpace)
+        );
+        assert_eq!(
+            limits(None, None, Some(501)).exceeded_by(&Usage {
+                free_disk_bytes: None,
+                ..usage
+            }),
+            None
+        );
+    }
+}
@@ -1,16 +1,21 @@
 use indexmap::IndexMap;
-use std::thread::JoinHandle;
+use std::{path::PathBuf, thread::JoinHandle};
 
 pub mod builder;
 pub mod clock;
 pub mod control;
+pub mod limits;
 pub mod task;
 
 use crate::MediaError;
 
 use builder::PipelineBuilder;
 pub use clock::*;
-use control::{Control, ControlBroadcast, PipelineControlSignal};
+use control::{
+    Control, ControlBroadcast, MessageTarget, PipelineControlSignal, PipelineMessage,
+    PipelineStatus,
+};
+use limits::RecordingLimits;
 
 pub struct Pipeline<T: PipelineClock> {
     clock: T,
@@ -44,6 +49,65 @@ impl<T: PipelineClock> Pipeline<T> {
         self.control.broadcast(Control::Pause).await
     }
 
+    /// Delivers a message to every task, or to a single named task.
+    pub fn send_message(
+        &self,
+        target: MessageTarget,
+        message: PipelineMessage,
+    ) -> Result<(), MediaError> {
+        if self.is_shutdown {
+            return Err(MediaError::ShutdownPipeline);
+        };
+
+        self.control.send_message(&target, message)
+    }
+
+    /// Recording time so far, excluding pauses.
+    pub fn elapsed(&self) -> std::time::Duration {
+        self.clock.elapsed()
+    }
+
+    /// Tells every task about a marker at the current pipeline time, returning its timestamp.
+    pub fn add_marker(&self, label: impl Into<String>) -> Result<i64, MediaError> {
+        let timestamp = self.clock.elapsed().as_micros().try_into().unwrap();
+        let label = label.into();
+        println!("Adding marker {label:?} at {timestamp}us");
+
+        self.send_message(
+            MessageTarget::All,
+            PipelineMessage::Marker { timestamp, label },
+        )?;
+
+        Ok(timestamp)
+    }
+
+    /// Reports [`PipelineStatus::LimitReached`] once the recording time, the size of `outputs`
+    /// or the free space on the disk holding `volume` reaches one of `limits`. Directories in
+    /// `outputs` count with everything in them. Has to be set up before the pipeline starts
+    /// playing.
+    pub fn watch_limits(&mut self, limits: RecordingLimits, outputs: Vec<PathBuf>, volume: PathBuf)
+    where
+        T: Clone + Send + 'static,
+    {
+        if limits.is_empty() {
+            return;
+        }
+
+        let name = "limits".to_string();
+        let clock = self.clock.clone();
+        let control_signal = self.control.add_listener(name.clone());
+        let handle = std::thread::spawn(move || {
+            limits::watch_limits(limits, clock, outputs, volume, control_signal);
+        });
+        self.task_handles.insert(name, handle);
+    }
+
+    /// Status updates reported by tasks, along with the name of the task that sent them.
+    /// The channel closes once the pipeline and all of its tasks are gone.
+    pub fn status_updates(&self) -> flume::Receiver<(String, PipelineStatus)> {
+        self.control.status_updates()
+    }
+
     pub async fn shutdown(&mut self) -> Result<(), MediaError> {
         if self.is_shutdown {
             return Err(MediaError::ShutdownPipeline);
@@ -51,11 +115,26 @@ impl<T: PipelineClock> Pipeline<T> {
 
         println!("Shutting down pipeline execution");
         let _ = self.control.broadcast(Control::Shutdown).await;
-        for (_name, task) in self.task_handles.drain(..) {
-            let _ = task.join();
-        }
+        join_tasks(std::mem::take(&mut self.task_handles)).await;
         println!("Pipeline has been stopped.");
         // TODO: Collect shutdown errors?
         Ok(())
     }
 }
+
+/// Waits for task threads to finish. The joins happen on a thread of their own, so that a
+/// task stuck in a device call holds up only the caller rather than a runtime worker.
+async fn join_tasks(task_handles: IndexMap<String, JoinHandle<()>>) {
+    let (done, finished) = flume::bounded(1);
+
+    std::thread::spawn(move || {
+        for (name, task) in task_handles {
+            if task.join().is_err() {
+                eprintln!("Task {name} panicked while shutting down");
+            }
+        }
+        let _ = done.send(());
+    });
+
+    let _ = finished.recv_async().await;
+}
@@ -1,6 +1,9 @@
 use flume::{Receiver, Sender};
 
-use crate::pipeline::{MediaError, PipelineControlSignal};
+use crate::pipeline::{
+    control::{PipelineControlSignal, PipelineMessages},
+    MediaError,
+};
 
 const DEFAULT_QUEUE_SIZE: usize = 2048;
 
@@ -30,6 +33,7 @@ pub trait PipelinePipeTask: Send {
     fn run(
         &mut self,
         ready_signal: PipelineReadySignal,
+        messages: PipelineMessages,
         input: Receiver<Self::Input>,
         output: Sender<Self::Output>,
     );
@@ -42,5 +46,10 @@ pub trait PipelinePipeTask: Send {
 pub trait PipelineSinkTask: Send {
     type Input;
 
-    fn run(&mut self, ready_signal: PipelineReadySignal, input: Receiver<Self::Input>);
+    fn run(
+        &mut self,
+        ready_signal: PipelineReadySignal,
+        messages: PipelineMessages,
+        input: Receiver<Self::Input>,
+    );
 }
@@ -7,7 +7,7 @@ use core_foundation::{
 };
 use core_graphics::{
     base::boolean_t,
-    display::{CFArrayGetValueAtIndex, CFDictionaryRef, CGRect},
+    display::{CFArrayGetValueAtIndex, CFDictionaryRef, CGDisplay, CGRect},
     window::{
         kCGNullWindowID, kCGWindowBounds, kCGWindowLayer, kCGWindowListExcludeDesktopElements,
         kCGWindowListOptionOnScreenOnly, kCGWindowName, kCGWindowNumber, kCGWindowOwnerName,
@@ -92,6 +92,18 @@ pub fn get_on_screen_windows() -> Vec<Window> {
     windows
 }
 
+/// Bounds of the main display in points, which is what screen capture records.
+pub fn get_main_display_bounds() -> Bounds {
+    let rect = CGDisplay::main().bounds();
+
+    Bounds {
+        x: rect.origin.x,
+        y: rect.origin.y,
+        width: rect.size.width,
+        height: rect.size.height,
+    }
+}
+
 unsafe fn get_nullable_value_from_dict(
     cf_dictionary_ref: CFDictionaryRef,
     key: CFStringRef,
@@ -7,7 +7,7 @@ mod platform_impl;
 
 pub use platform_impl::*;
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, Type)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Type)]
 pub struct Bounds {
     pub x: f64,
     pub y: f64,
@@ -1,16 +1,26 @@
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use cpal::{
-    BufferSize, Device, SampleFormat, Stream, StreamConfig, StreamInstant, SupportedBufferSize,
-    SupportedStreamConfig,
+    BufferSize, Device, FromSample, SampleFormat, SampleRate, SizedSample, Stream, StreamConfig,
+    StreamError, StreamInstant, SupportedBufferSize, SupportedStreamConfig,
 };
-use flume::Sender;
+use flume::{Receiver, RecvTimeoutError, Sender, TrySendError};
 use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::{
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
 
 use crate::{
-    data::{AudioInfo, FFAudio, RawAudioFormat},
+    data::{AudioInfo, FFAudio, RawAudioFormat, Sample},
     pipeline::{
-        clock::{LocalTimestamp, SynchronisedClock},
-        control::Control,
+        clock::{CloneInto, LocalTimestamp, RealTime, SynchronisedClock, TimeSource},
+        control::{Control, PipelineStatus, StatusReporter},
         task::PipelineSourceTask,
     },
     MediaError,
@@ -18,23 +28,52 @@ use crate::{
 
 pub type AudioInputDeviceMap = IndexMap<String, (Device, SupportedStreamConfig)>;
 
+/// Used when a device doesn't say which buffer sizes it supports. About 20ms at 48kHz.
+const DEFAULT_BUFFER_SIZE: u32 = 1024;
+/// How often a lost device is looked for again, and silence written in its place.
+const RECONNECT_INTERVAL: Duration = Duration::from_millis(500);
+/// How long to wait for a lost device to come back before switching to the default input.
+const FALLBACK_AFTER: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Type)]
+#[serde(
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase",
+    tag = "state"
+)]
+pub enum AudioInputStatus {
+    /// The device went away. Silence is recorded until it, or another device, takes over.
+    Disconnected {
+        device: String,
+    },
+    Reconnected {
+        device: String,
+    },
+    /// The lost device didn't come back, so the default input is used instead.
+    FellBack {
+        from: String,
+        to: String,
+    },
+}
+
 impl LocalTimestamp for StreamInstant {
     fn elapsed_since(&self, other: &Self) -> std::time::Duration {
         self.duration_since(other).unwrap()
     }
 }
 
-pub struct AudioInputSource {
+pub struct AudioInputSource<S: TimeSource = RealTime> {
     device: Device,
     device_name: String,
     config: SupportedStreamConfig,
+    _time: PhantomData<S>,
 }
 
-impl AudioInputSource {
+impl<S: TimeSource> AudioInputSource<S> {
     pub fn init(selected_audio_input: Option<&String>) -> Option<Self> {
         println!("Selected audio input: {:?}", selected_audio_input);
 
-        let mut devices = Self::get_devices();
+        let mut devices = AudioInputSource::get_devices();
 
         selected_audio_input
             .and_then(|device_name| devices.swap_remove_entry(device_name))
@@ -45,6 +84,7 @@ impl AudioInputSource {
                     device,
                     device_name,
                     config,
+                    _time: PhantomData,
                 }
             })
     }
@@ -52,8 +92,10 @@ impl AudioInputSource {
     pub fn info(&self) -> AudioInfo {
         let format = format_for(self.config.sample_format()).unwrap();
         let buffer_size = match self.config.buffer_size() {
-            SupportedBufferSize::Range { max, .. } => *max,
-            SupportedBufferSize::Unknown => todo!("What's a decent default value for this?"),
+            SupportedBufferSize::Range { min, max } => {
+                DEFAULT_BUFFER_SIZE.clamp(*min, (*max).max(*min))
+            }
+            SupportedBufferSize::Unknown => DEFAULT_BUFFER_SIZE,
         };
         AudioInfo::from_raw(
             format,
@@ -63,6 +105,60 @@ impl AudioInputSource {
         )
     }
 
+    /// Builds a paused stream that sends the device's audio as frames of `audio_info`. Stream
+    /// errors, such as the device being unplugged, are sent to `errors`.
+    pub fn build_stream(
+        &self,
+        audio_info: AudioInfo,
+        mut clock: SynchronisedClock<StreamInstant, S>,
+        output: Sender<FFAudio>,
+        errors: Sender<StreamError>,
+        written_until: Arc<AtomicI64>,
+    ) -> Result<Stream, MediaError> {
+        let mut stream_config: StreamConfig = self.config.clone().into();
+        stream_config.buffer_size = BufferSize::Fixed(audio_info.buffer_size);
+        let sample_format = self.config.sample_format();
+
+        let data_callback = move |data: &cpal::Data, info: &cpal::InputCallbackInfo| {
+            let capture_time = info.timestamp().capture;
+            match clock.timestamp_for(capture_time) {
+                None => eprintln!("Clock is currently stopped. Dropping samples."),
+                Some(timestamp) => {
+                    let buffer = audio_info.wrap_frame(data.bytes(), timestamp.try_into().unwrap());
+                    written_until.store(
+                        timestamp + duration_micros(&audio_info, buffer.samples()),
+                        Ordering::Release,
+                    );
+                    // TODO(PJ): Send error when I bring error infra back online
+                    output.send(buffer).unwrap();
+                    // if let Err(_) = output.send(buffer) {
+                    //     tracing::debug!("Pipeline is unreachable. Recording will shut down.");
+                    // }
+                }
+            };
+        };
+
+        let error_callback = move |err: StreamError| {
+            eprintln!("An error occurred on the audio stream: {}", err);
+            let _ = errors.send(err);
+        };
+
+        self.device
+            .build_input_stream_raw(
+                &stream_config,
+                sample_format,
+                data_callback,
+                error_callback,
+                None,
+            )
+            .map_err(|error| {
+                eprintln!("Error while preparing audio capture: {error}");
+                MediaError::TaskLaunch("Failed to start audio capture".into())
+            })
+    }
+}
+
+impl AudioInputSource {
     pub fn get_devices() -> AudioInputDeviceMap {
         let host = cpal::default_host();
         let mut device_map = IndexMap::new();
@@ -101,49 +197,198 @@ impl AudioInputSource {
 
         device_map
     }
+}
 
-    pub fn build_stream(
-        &self,
-        mut clock: SynchronisedClock<StreamInstant>,
-        output: Sender<FFAudio>,
-    ) -> Result<Stream, MediaError> {
-        let audio_info = self.info();
-        let mut stream_config: StreamConfig = self.config.clone().into();
-        stream_config.buffer_size = BufferSize::Fixed(audio_info.buffer_size);
-        let sample_format = self.config.sample_format();
+/// The quietest level reported, in dBFS. Digital silence would otherwise be negative infinity.
+pub const MIN_AUDIO_LEVEL: f32 = -100.0;
+/// How often levels are reported. Buffers in between are combined.
+const LEVEL_INTERVAL: Duration = Duration::from_millis(50);
 
-        let data_callback = move |data: &cpal::Data, info: &cpal::InputCallbackInfo| {
-            let capture_time = info.timestamp().capture;
-            match clock.timestamp_for(capture_time) {
-                None => eprintln!("Clock is currently stopped. Dropping samples."),
-                Some(timestamp) => {
-                    let buffer = audio_info.wrap_frame(data.bytes(), timestamp.try_into().unwrap());
-                    // TODO(PJ): Send error when I bring error infra back online
-                    output.send(buffer).unwrap();
-                    // if let Err(_) = output.send(buffer) {
-                    //     tracing::debug!("Pipeline is unreachable. Recording will shut down.");
-                    // }
-                }
-            };
+/// Loudness of a stretch of audio across all channels, in dBFS.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Type)]
+pub struct AudioLevels {
+    pub rms: f32,
+    pub peak: f32,
+}
+
+fn to_dbfs(amplitude: f32) -> f32 {
+    (20.0 * amplitude.log10()).max(MIN_AUDIO_LEVEL)
+}
+
+#[derive(Default)]
+struct LevelMeter {
+    sum_of_squares: f64,
+    samples: usize,
+    peak: f32,
+    last_report: Option<Instant>,
+}
+
+impl LevelMeter {
+    fn add(&mut self, samples: impl Iterator<Item = f32>) {
+        for sample in samples {
+            self.sum_of_squares += (sample as f64).powi(2);
+            self.samples += 1;
+            self.peak = self.peak.max(sample.abs());
+        }
+    }
+
+    fn add_data(&mut self, data: &cpal::Data) {
+        self.add_bytes(data.sample_format(), data.bytes());
+    }
+
+    /// Samples of `format` in native byte order, as cpal delivers them.
+    fn add_bytes(&mut self, format: SampleFormat, bytes: &[u8]) {
+        fn samples<'a, T: SizedSample + 'a, const N: usize>(
+            bytes: &'a [u8],
+            from_ne_bytes: fn([u8; N]) -> T,
+        ) -> impl Iterator<Item = f32> + 'a
+        where
+            f32: FromSample<T>,
+        {
+            bytes
+                .chunks_exact(N)
+                .map(move |chunk| f32::from_sample_(from_ne_bytes(chunk.try_into().unwrap())))
+        }
+
+        match format {
+            SampleFormat::U8 => self.add(samples(bytes, u8::from_ne_bytes)),
+            SampleFormat::I16 => self.add(samples(bytes, i16::from_ne_bytes)),
+            SampleFormat::I32 => self.add(samples(bytes, i32::from_ne_bytes)),
+            SampleFormat::I64 => self.add(samples(bytes, i64::from_ne_bytes)),
+            SampleFormat::F32 => self.add(samples(bytes, f32::from_ne_bytes)),
+            SampleFormat::F64 => self.add(samples(bytes, f64::from_ne_bytes)),
+            _ => {}
+        }
+    }
+
+    fn take(&mut self) -> AudioLevels {
+        let rms = match self.samples {
+            0 => 0.0,
+            samples => (self.sum_of_squares / samples as f64).sqrt() as f32,
+        };
+        let levels = AudioLevels {
+            rms: to_dbfs(rms),
+            peak: to_dbfs(self.peak),
         };
 
-        let error_callback = |err| {
-            // TODO: Handle errors such as device being disconnected. Some kind of fallback or pop-up?
-            eprintln!("An error occurred on the audio stream: {}", err);
+        *self = Self {
+            last_report: self.last_report,
+            ..Default::default()
         };
 
-        self.device
-            .build_input_stream_raw(
-                &stream_config,
-                sample_format,
-                data_callback,
-                error_callback,
-                None,
-            )
-            .map_err(|error| {
-                eprintln!("Error while preparing audio capture: {error}");
-                MediaError::TaskLaunch("Failed to start audio capture".into())
-            })
+        levels
+    }
+
+    /// The levels since the last report, once every `LEVEL_INTERVAL`.
+    fn take_throttled(&mut self) -> Option<AudioLevels> {
+        let now = Instant::now();
+        if self
+            .last_report
+            .is_some_and(|last_report| now - last_report < LEVEL_INTERVAL)
+        {
+            return None;
+        }
+
+        self.last_report = Some(now);
+        Some(self.take())
+    }
+}
+
+/// Meters an audio input on a stream of its own, whether or not it's being recorded, so that
+/// a muted or wrong microphone is noticed before recording starts. Stops when dropped.
+pub struct AudioLevelMonitor {
+    device_name: String,
+    subscribers: Arc<Mutex<Vec<Sender<AudioLevels>>>>,
+    stop: Sender<()>,
+}
+
+impl AudioLevelMonitor {
+    pub fn start(device_name: &String) -> Result<Self, MediaError> {
+        let (device, config) = AudioInputSource::get_devices()
+            .swap_remove(device_name)
+            .ok_or_else(|| MediaError::DeviceUnreachable(device_name.clone()))?;
+        let subscribers = Arc::new(Mutex::new(Vec::<Sender<AudioLevels>>::new()));
+        let (stop, stop_rx) = flume::bounded(1);
+        let (ready_tx, ready_rx) = flume::bounded(1);
+
+        let callback_subscribers = subscribers.clone();
+        // Streams can't move between threads on every platform, so this one lives on its own.
+        std::thread::spawn(move || {
+            let mut meter = LevelMeter::default();
+            let data_callback = move |data: &cpal::Data, _: &cpal::InputCallbackInfo| {
+                meter.add_data(data);
+
+                if let Some(levels) = meter.take_throttled() {
+                    callback_subscribers.lock().unwrap().retain(|subscriber| {
+                        !matches!(
+                            subscriber.try_send(levels),
+                            Err(TrySendError::Disconnected(_))
+                        )
+                    });
+                }
+            };
+            let error_callback = |error: StreamError| {
+                eprintln!("An error occurred on the audio level stream: {error}")
+            };
+
+            let stream = device
+                .build_input_stream_raw(
+                    &config.config(),
+                    config.sample_format(),
+                    data_callback,
+                    error_callback,
+                    None,
+                )
+                .map_err(|error| {
+                    eprintln!("Error while preparing audio level monitoring: {error}");
+                    MediaError::TaskLaunch("Failed to start audio level monitoring".into())
+                })
+                .and_then(|stream| {
+                    stream.play().map_err(|error| {
+                        eprintln!("Error while starting audio level monitoring: {error}");
+                        MediaError::TaskLaunch("Failed to start audio level monitoring".into())
+                    })?;
+                    Ok(stream)
+                });
+
+            match stream {
+                Err(error) => ready_tx.send(Err(error)).unwrap(),
+                Ok(stream) => {
+                    ready_tx.send(Ok(())).unwrap();
+                    // Returns once the monitor is dropped.
+                    let _ = stop_rx.recv();
+                    drop(stream);
+                }
+            }
+        });
+
+        ready_rx
+            .recv()
+            .map_err(|_| MediaError::Any("Failed to start audio level monitoring"))??;
+
+        Ok(Self {
+            device_name: device_name.clone(),
+            subscribers,
+            stop,
+        })
+    }
+
+    pub fn device_name(&self) -> &str {
+        &self.device_name
+    }
+
+    /// A new stream of levels, about 20 times a second. Levels are dropped while the receiver
+    /// falls behind.
+    pub fn subscribe(&self) -> Receiver<AudioLevels> {
+        let (sender, receiver) = flume::bounded(8);
+        self.subscribers.lock().unwrap().push(sender);
+        receiver
+    }
+}
+
+impl Drop for AudioLevelMonitor {
+    fn drop(&mut self) {
+        let _ = self.stop.send(());
     }
 }
 
@@ -159,10 +404,131 @@ fn format_for(format: SampleFormat) -> Option<RawAudioFormat> {
     }
 }
 
-impl PipelineSourceTask for AudioInputSource {
+/// Frames of silence that stand in for a lost device, so that the audio track keeps its length.
+struct SilenceFiller<S: TimeSource> {
+    info: AudioInfo,
+    clock: SynchronisedClock<Instant, S>,
+    /// Silence has been written up to here, in microseconds.
+    written_until: Option<i64>,
+}
+
+impl<S: TimeSource> SilenceFiller<S> {
+    /// Writes silence from where the device left off up to now.
+    fn fill(&mut self, output: &Sender<FFAudio>) -> Result<(), MediaError> {
+        match self.clock.timestamp_for(Instant::now()) {
+            Some(now) => self.fill_until(now, output),
+            None => Ok(()),
+        }
+    }
+
+    /// Writes whole frames of silence up to `now`, in microseconds.
+    fn fill_until(&mut self, now: i64, output: &Sender<FFAudio>) -> Result<(), MediaError> {
+        let written_until = self.written_until.get_or_insert(now);
+
+        let samples = self.info.buffer_size as usize;
+        let duration = duration_micros(&self.info, samples);
+        while *written_until + duration <= now {
+            let mut frame =
+                FFAudio::new(self.info.sample_format, samples, self.info.channel_layout());
+            frame.set_pts(Some(*written_until));
+            frame.set_rate(self.info.rate() as u32);
+            // Unsigned samples are centred on half their range rather than zero.
+            let silence = if matches!(self.info.sample_format, Sample::U8(_)) {
+                0x80
+            } else {
+                0
+            };
+            for plane in 0..frame.planes() {
+                frame.data_mut(plane).fill(silence);
+            }
+
+            output
+                .send(frame)
+                .map_err(|_| MediaError::Any("Pipeline is unreachable! Stopping capture"))?;
+            *written_until += duration;
+        }
+
+        Ok(())
+    }
+}
+
+fn duration_micros(info: &AudioInfo, samples: usize) -> i64 {
+    (samples as i64 * 1_000_000) / info.rate() as i64
+}
+
+/// Finds a config of `device` that produces the same audio as `info`, since the rest of the
+/// pipeline can't change formats once it's running.
+fn matching_config(device: &Device, info: &AudioInfo) -> Option<SupportedStreamConfig> {
+    let sample_rate = info.rate() as u32;
+
+    device
+        .supported_input_configs()
+        .ok()?
+        .find(|config| {
+            format_for(config.sample_format())
+                .is_some_and(|format| Sample::from(format) == info.sample_format)
+                && config.channels() as usize == info.channels()
+                && (config.min_sample_rate().0..=config.max_sample_rate().0).contains(&sample_rate)
+        })
+        .map(|config| config.with_sample_rate(SampleRate(sample_rate)))
+}
+
+struct LostDevice {
+    since: Instant,
+    last_attempt: Instant,
+}
+
+impl<S: TimeSource> AudioInputSource<S> {
+    /// Looks for the lost device, or the default input once it's been gone for a while, and
+    /// switches to it. Returns the status to report if it did.
+    fn find_replacement(
+        &mut self,
+        info: &AudioInfo,
+        lost: &LostDevice,
+    ) -> Option<AudioInputStatus> {
+        let mut devices = AudioInputSource::get_devices();
+
+        // Keeps the order, so that the default input stays first.
+        if let Some((device, _)) = devices.shift_remove(&self.device_name) {
+            match matching_config(&device, info) {
+                Some(config) => {
+                    self.device = device;
+                    self.config = config;
+
+                    return Some(AudioInputStatus::Reconnected {
+                        device: self.device_name.clone(),
+                    });
+                }
+                None => eprintln!(
+                    "Audio input device {} is back, but can't record in the same format",
+                    self.device_name
+                ),
+            }
+        }
+
+        if lost.since.elapsed() < FALLBACK_AFTER {
+            return None;
+        }
+
+        // The default input, if there is one, always comes first.
+        let (name, (device, _)) = devices.shift_remove_index(0)?;
+        let config = matching_config(&device, info)?;
+        let from = std::mem::replace(&mut self.device_name, name.clone());
+        self.device = device;
+        self.config = config;
+
+        Some(AudioInputStatus::FellBack { from, to: name })
+    }
+}
+
+fn report(reporter: &StatusReporter, status: AudioInputStatus) {
+    reporter.report(PipelineStatus::AudioInput(status));
+}
+
+impl<S: TimeSource> PipelineSourceTask for AudioInputSource<S> {
     type Output = FFAudio;
 
-    type Clock = SynchronisedClock<StreamInstant>;
+    type Clock = SynchronisedClock<StreamInstant, S>;
 
     // #[tracing::instrument(skip_all)]
     fn run(
@@ -174,36 +540,235 @@ impl PipelineSourceTask for AudioInputSource {
     ) {
         println!("Preparing audio input source thread...");
 
-        match self.build_stream(clock, output) {
-            Err(error) => ready_signal.send(Err(error)).unwrap(),
-            Ok(stream) => {
-                println!("Using audio input device {}", self.device_name);
-                ready_signal.send(Ok(())).unwrap();
-
-                loop {
-                    // TODO: Handle these more gracefully than crashing (e.g. if user unplugged mic between pausing and resuming).
-                    // Some kind of error stream?
-                    match control_signal.blocking_last() {
-                        Some(Control::Play) => {
-                            stream
-                                .play()
-                                .expect("Failed to start audio input recording");
-                            println!("Audio input recording started.");
-                        }
-                        Some(Control::Pause) => {
-                            stream
-                                .pause()
-                                .expect("Failed to pause audio input recording");
+        let info = self.info();
+        let reporter = control_signal.messages().reporter();
+        let (errors_tx, errors_rx): (Sender<StreamError>, Receiver<StreamError>) =
+            flume::unbounded();
+        let written_until = Arc::new(AtomicI64::new(0));
+
+        let build_stream = |source: &Self| {
+            source.build_stream(
+                info,
+                CloneInto::clone_into(&clock),
+                output.clone(),
+                errors_tx.clone(),
+                written_until.clone(),
+            )
+        };
+
+        let mut stream = match build_stream(self) {
+            Err(error) => {
+                ready_signal.send(Err(error)).unwrap();
+                return;
+            }
+            Ok(stream) => stream,
+        };
+
+        println!("Using audio input device {}", self.device_name);
+        ready_signal.send(Ok(())).unwrap();
+
+        let mut playing = false;
+        let mut lost: Option<LostDevice> = None;
+        let mut silence = SilenceFiller {
+            info,
+            clock: CloneInto::clone_into(&clock),
+            written_until: None,
+        };
+
+        loop {
+            // Only blocks while paused. While playing, it returns straight away so that the
+            // device can be watched.
+            match control_signal.last() {
+                Some(Control::Play) => {
+                    if !playing {
+                        playing = true;
+                        if lost.is_none() {
+                            match stream.play() {
+                                Ok(()) => println!("Audio input recording started."),
+                                Err(error) => {
+                                    eprintln!("Failed to start audio input recording: {error}");
+                                    let _ = errors_tx.send(StreamError::DeviceNotAvailable);
+                                }
+                            }
                         }
-                        Some(Control::Shutdown) | None => {
-                            drop(stream);
-                            break;
+                    }
+                }
+                Some(Control::Pause) => {
+                    playing = false;
+                    silence.written_until = None;
+                    if let Err(error) = stream.pause() {
+                        eprintln!("Failed to pause audio input recording: {error}");
+                    }
+                    continue;
+                }
+                Some(Control::Shutdown) | None => break,
+            }
+
+            let Some(lost_device) = &mut lost else {
+                match errors_rx.recv_timeout(RECONNECT_INTERVAL) {
+                    Ok(StreamError::DeviceNotAvailable) => {
+                        eprintln!("Audio input device {} was lost", self.device_name);
+                        let now = Instant::now();
+                        lost = Some(LostDevice {
+                            since: now,
+                            last_attempt: now,
+                        });
+                        // Pick up where the device's last samples ended.
+                        let written_until = written_until.load(Ordering::Acquire);
+                        silence.written_until = (written_until > 0).then_some(written_until);
+                        report(
+                            &reporter,
+                            AudioInputStatus::Disconnected {
+                                device: self.device_name.clone(),
+                            },
+                        );
+                    }
+                    // Other errors are transient, and the stream carries on.
+                    Ok(_) | Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+                continue;
+            };
+
+            if let Err(error) = silence.fill(&output) {
+                eprintln!("{error}");
+                break;
+            }
+
+            if lost_device.last_attempt.elapsed() >= RECONNECT_INTERVAL {
+                lost_device.last_attempt = Instant::now();
+
+                if let Some(status) = self.find_replacement(&info, lost_device) {
+                    match build_stream(self).and_then(|new_stream| {
+                        new_stream.play().map_err(|error| {
+                            eprintln!("Failed to start audio input recording: {error}");
+                            MediaError::Any("Failed to start audio input recording")
+                        })?;
+                        Ok(new_stream)
+                    }) {
+                        Ok(new_stream) => {
+                            println!("Using audio input device {}", self.device_name);
+                            // Drop any errors the old stream reported on its way out.
+                            let _ = errors_rx.drain();
+                            stream = new_stream;
+                            lost = None;
+                            report(&reporter, status);
+                            continue;
                         }
+                        Err(error) => eprintln!("{error}"),
                     }
                 }
+            }
+
+            std::thread::sleep(Duration::from_millis(20));
+        }
+
+        drop(stream);
+        println!("Shutting down audio input source thread.")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_cover_everything_since_the_last_report() {
+        let mut meter = LevelMeter::default();
+        meter.add([0.5, -0.5].into_iter());
+        meter.add([0.5, -1.0].into_iter());
+
+        let levels = meter.take();
+        assert_eq!(levels.peak, 0.0);
+        // sqrt((0.25 * 3 + 1.0) / 4) is about 0.661
+        assert!((levels.rms - to_dbfs(0.661)).abs() < 0.01);
 
-                println!("Shutting down audio input source thread.")
+        assert_eq!(
+            meter.take(),
+            AudioLevels {
+                rms: MIN_AUDIO_LEVEL,
+                peak: MIN_AUDIO_LEVEL
             }
+        );
+    }
+
+    #[test]
+    fn levels_are_read_from_raw_input_data() {
+        let mut meter = LevelMeter::default();
+
+        // cpal can't construct `Data` outside of a stream, so this feeds what `Data::bytes` holds
+        let i16_bytes = [i16::MAX, i16::MIN / 2]
+            .iter()
+            .flat_map(|sample| sample.to_ne_bytes())
+            .collect::<Vec<_>>();
+        meter.add_bytes(SampleFormat::I16, &i16_bytes);
+        assert_eq!(meter.samples, 2);
+        assert!((meter.peak - 1.0).abs() < 0.001);
+
+        let f32_bytes = [0.25_f32, -0.25]
+            .iter()
+            .flat_map(|sample| sample.to_ne_bytes())
+            .collect::<Vec<_>>();
+        meter.add_bytes(SampleFormat::F32, &f32_bytes);
+        assert_eq!(meter.samples, 4);
+
+        meter.take();
+
+        // Unsigned silence sits at the middle of the range
+        meter.add_bytes(SampleFormat::U8, &[0x80, 0x80, 0x80]);
+        assert_eq!(meter.samples, 3);
+        assert_eq!(meter.take().peak, MIN_AUDIO_LEVEL);
+
+        // Trailing bytes that don't make up a whole sample are ignored
+        meter.add_bytes(SampleFormat::I32, &[0; 6]);
+        assert_eq!(meter.samples, 1);
+    }
+
+    #[test]
+    fn silence_fills_the_gap_left_by_the_device() {
+        use crate::pipeline::clock::SimulatedTime;
+
+        // 480 samples at 48kHz is 10ms a frame
+        let info = AudioInfo::from_raw(RawAudioFormat::U8, 48_000, 1, 480);
+        let mut silence = SilenceFiller {
+            info,
+            clock: SynchronisedClock::with_time_source(SimulatedTime::new()),
+            written_until: None,
+        };
+        let (tx, rx) = flume::unbounded();
+
+        // Without anything written yet, silence starts from now
+        silence.fill_until(5_000, &tx).unwrap();
+        assert!(rx.is_empty());
+        assert_eq!(silence.written_until, Some(5_000));
+
+        // Picks up where the device's last samples ended, writing only whole frames
+        silence.written_until = Some(1_000);
+        silence.fill_until(36_000, &tx).unwrap();
+        silence.fill_until(41_000, &tx).unwrap();
+        assert_eq!(silence.written_until, Some(41_000));
+
+        let frames = rx.drain().collect::<Vec<_>>();
+        assert_eq!(
+            frames.iter().map(|frame| frame.pts()).collect::<Vec<_>>(),
+            [1_000, 11_000, 21_000, 31_000].map(Some)
+        );
+        for frame in &frames {
+            assert_eq!(frame.samples(), 480);
+            assert_eq!(frame.rate(), 48_000);
+            // Unsigned silence sits halfway up the range
+            assert!(frame.data(0)[..480].iter().all(|&sample| sample == 0x80));
         }
+
+        let info = AudioInfo::from_raw(RawAudioFormat::F32, 48_000, 2, 480);
+        let mut silence = SilenceFiller {
+            info,
+            clock: SynchronisedClock::with_time_source(SimulatedTime::new()),
+            written_until: Some(0),
+        };
+        silence.fill_until(10_000, &tx).unwrap();
+        let frame = rx.try_recv().unwrap();
+        assert_eq!(frame.pts(), Some(0));
+        assert!(frame.data(0)[..480 * 2 * 4].iter().all(|&byte| byte == 0));
     }
 }
@@ -1,23 +1,29 @@
 use flume::{Receiver, Sender};
-use std::time::Instant;
+use std::{marker::PhantomData, time::Instant};
 
 use crate::{
     data::{FFVideo, VideoInfo},
     feeds::{CameraConnection, CameraFeed, RawCameraFrame},
-    pipeline::{clock::SynchronisedClock, control::Control, task::PipelineSourceTask},
+    pipeline::{
+        clock::{RealTime, SynchronisedClock, TimeSource},
+        control::Control,
+        task::PipelineSourceTask,
+    },
     MediaError,
 };
 
-pub struct CameraSource {
+pub struct CameraSource<S: TimeSource = RealTime> {
     feed_connection: CameraConnection,
     video_info: VideoInfo,
+    _time: PhantomData<S>,
 }
 
-impl CameraSource {
+impl<S: TimeSource> CameraSource<S> {
     pub fn init(camera_feed: Option<&CameraFeed>) -> Option<Self> {
         camera_feed.map(|feed| Self {
             feed_connection: feed.create_connection(),
             video_info: feed.video_info(),
+            _time: PhantomData,
         })
     }
 
@@ -27,7 +33,7 @@ impl CameraSource {
 
     fn process_frame(
         &self,
-        clock: &mut SynchronisedClock<Instant>,
+        clock: &mut SynchronisedClock<Instant, S>,
         output: &Sender<FFVideo>,
         camera_frame: RawCameraFrame,
     ) -> Result<(), MediaError> {
@@ -52,7 +58,7 @@ impl CameraSource {
 
     fn pause_and_drain_frames(
         &self,
-        clock: &mut SynchronisedClock<Instant>,
+        clock: &mut SynchronisedClock<Instant, S>,
         output: &Sender<FFVideo>,
         frames_rx: Receiver<RawCameraFrame>,
     ) {
@@ -68,10 +74,10 @@ impl CameraSource {
     }
 }
 
-impl PipelineSourceTask for CameraSource {
+impl<S: TimeSource> PipelineSourceTask for CameraSource<S> {
     type Output = FFVideo;
 
-    type Clock = SynchronisedClock<Instant>;
+    type Clock = SynchronisedClock<Instant, S>;
 
     // #[tracing::instrument(skip_all)]
     fn run(
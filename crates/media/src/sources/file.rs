@@ -0,0 +1,485 @@
+use ffmpeg::{codec, decoder, format, media};
+use flume::Sender;
+use std::{
+    marker::PhantomData,
+    ops::DerefMut,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    data::{AudioInfo, FFAudio, FFFrame, FFRational, FFVideo, VideoInfo},
+    pipeline::{
+        clock::{RawNanoseconds, RealTime, SynchronisedClock, TimeSource},
+        control::{Control, PipelineControlSignal, PipelineStatus},
+        task::{PipelineReadySignal, PipelineSourceTask},
+    },
+    MediaError,
+};
+
+/// How a file source sends its frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FilePacing {
+    /// At the speed they'd play back at, as if the file was a live source.
+    #[default]
+    RealTime,
+    /// As quickly as the rest of the pipeline takes them, e.g. to re-encode a recording.
+    AsFastAsPossible,
+}
+
+/// Demuxes a single stream of a media file and decodes it.
+struct FileReader<D> {
+    input: format::context::Input,
+    stream_index: usize,
+    time_base: FFRational,
+    decoder: D,
+    flushing: bool,
+    pacing: FilePacing,
+}
+
+impl<D: DerefMut<Target = decoder::Opened>> FileReader<D> {
+    fn open(
+        path: &Path,
+        medium: media::Type,
+        pacing: FilePacing,
+        open_decoder: impl FnOnce(decoder::Decoder) -> Result<D, ffmpeg::Error>,
+    ) -> Result<Self, MediaError> {
+        let input = format::input(&path)?;
+        let stream = input.streams().best(medium).ok_or(MediaError::Any(
+            "The file has no stream of the requested kind",
+        ))?;
+        let stream_index = stream.index();
+        let time_base = stream.time_base();
+
+        let context = codec::context::Context::from_parameters(stream.parameters())?;
+        let decoder = open_decoder(context.decoder())?;
+
+        Ok(Self {
+            input,
+            stream_index,
+            time_base,
+            decoder,
+            flushing: false,
+            pacing,
+        })
+    }
+
+    /// Decodes the next frame into `frame`, returning false once the whole file has been read.
+    fn next_frame(&mut self, frame: &mut FFFrame) -> Result<bool, MediaError> {
+        loop {
+            match self.decoder.receive_frame(frame) {
+                Ok(()) => return Ok(true),
+                Err(ffmpeg::Error::Eof) => return Ok(false),
+                // The decoder needs more input.
+                Err(ffmpeg::Error::Other {
+                    errno: ffmpeg::util::error::EAGAIN,
+                }) => {}
+                Err(error) => return Err(error.into()),
+            }
+
+            if self.flushing {
+                return Ok(false);
+            }
+
+            // Not `Input::packets`, which skips over read errors and so hides a damaged file.
+            let mut packet = ffmpeg::Packet::empty();
+            match packet.read(&mut self.input) {
+                Ok(()) if packet.stream() == self.stream_index => {
+                    self.decoder.send_packet(&packet)?
+                }
+                Ok(()) => {}
+                Err(ffmpeg::Error::Eof) => {
+                    self.decoder.send_eof()?;
+                    self.flushing = true;
+                }
+                Err(error) => return Err(error.into()),
+            }
+        }
+    }
+
+    /// Where `frame` is in the file, or `None` if the file doesn't say.
+    fn position(&self, frame: &FFFrame) -> Option<RawNanoseconds> {
+        let timestamp = frame.timestamp().or(frame.pts())?;
+        to_nanoseconds(timestamp, self.time_base).map(RawNanoseconds)
+    }
+}
+
+fn to_nanoseconds(timestamp: i64, time_base: FFRational) -> Option<u64> {
+    let nanoseconds = timestamp as i128 * time_base.numerator() as i128 * 1_000_000_000
+        / time_base.denominator() as i128;
+
+    nanoseconds.try_into().ok()
+}
+
+/// Why a file source stopped sending frames.
+enum Finish {
+    EndOfFile,
+    Failed(MediaError),
+    /// The pipeline shut down, or can't be reached any more.
+    Stopped,
+}
+
+/// Sends the reader's frames until the end of the file, a read error, or until the pipeline
+/// shuts down.
+fn send_frames<S, D, F>(
+    reader: &mut FileReader<D>,
+    clock: &mut SynchronisedClock<RawNanoseconds, S>,
+    control_signal: &mut PipelineControlSignal,
+    output: &Sender<F>,
+    empty_frame: fn() -> F,
+) -> Finish
+where
+    S: TimeSource,
+    D: DerefMut<Target = decoder::Opened>,
+    F: DerefMut<Target = FFFrame>,
+{
+    // The wall time and file position that playback was last started from.
+    let mut anchor: Option<(Instant, u64)> = None;
+
+    loop {
+        match control_signal.last() {
+            Some(Control::Play) => {}
+            Some(Control::Pause) => {
+                anchor = None;
+                continue;
+            }
+            Some(Control::Shutdown) | None => return Finish::Stopped,
+        }
+
+        let mut frame = empty_frame();
+        match reader.next_frame(&mut frame) {
+            Ok(true) => {}
+            Ok(false) => return Finish::EndOfFile,
+            Err(error) => return Finish::Failed(error),
+        }
+
+        let Some(position) = reader.position(&frame) else {
+            eprintln!("Skipping frame without a timestamp");
+            continue;
+        };
+
+        if reader.pacing == FilePacing::RealTime {
+            let (started_at, started_from) = *anchor.get_or_insert((Instant::now(), position.0));
+            let due = started_at + Duration::from_nanos(position.0.saturating_sub(started_from));
+            std::thread::sleep(due.saturating_duration_since(Instant::now()));
+        }
+
+        match clock.timestamp_for(position) {
+            None => eprintln!("Clock is currently stopped. Dropping frames."),
+            Some(timestamp) => {
+                frame.set_pts(Some(timestamp));
+                if output.send(frame).is_err() {
+                    eprintln!("Pipeline is unreachable! Stopping file source");
+                    return Finish::Stopped;
+                }
+            }
+        }
+    }
+}
+
+fn run_file_source<S, D, F>(
+    tag: &str,
+    reader: &mut FileReader<D>,
+    mut clock: SynchronisedClock<RawNanoseconds, S>,
+    ready_signal: PipelineReadySignal,
+    mut control_signal: PipelineControlSignal,
+    output: Sender<F>,
+    empty_frame: fn() -> F,
+) where
+    S: TimeSource,
+    D: DerefMut<Target = decoder::Opened>,
+    F: DerefMut<Target = FFFrame>,
+{
+    println!("Preparing {tag} file source thread...");
+    ready_signal.send(Ok(())).unwrap();
+
+    let status = match send_frames(
+        reader,
+        &mut clock,
+        &mut control_signal,
+        &output,
+        empty_frame,
+    ) {
+        Finish::EndOfFile => {
+            println!("Reached the end of the {tag} file");
+            Some(PipelineStatus::EndOfInput)
+        }
+        Finish::Failed(error) => {
+            eprintln!("Failed to read the {tag} file: {error}");
+            Some(PipelineStatus::InputFailed(error.to_string()))
+        }
+        Finish::Stopped => None,
+    };
+
+    if let Some(status) = status {
+        // Lets the rest of the pipeline finish up, while staying around for the shutdown signal.
+        drop(output);
+        control_signal.messages().reporter().report(status);
+
+        while !matches!(
+            control_signal.blocking_last(),
+            Some(Control::Shutdown) | None
+        ) {}
+    }
+
+    println!("Shutting down {tag} file source thread.");
+}
+
+/// Plays the best video stream of a file into a pipeline.
+pub struct FileVideoSource<S: TimeSource = RealTime> {
+    reader: FileReader<decoder::Video>,
+    info: VideoInfo,
+    _time: PhantomData<S>,
+}
+
+impl<S: TimeSource> FileVideoSource<S> {
+    pub fn init(path: impl AsRef<Path>, pacing: FilePacing) -> Result<Self, MediaError> {
+        let reader = FileReader::open(path.as_ref(), media::Type::Video, pacing, |decoder| {
+            decoder.video()
+        })?;
+        let frame_rate = reader
+            .input
+            .stream(reader.stream_index)
+            .map(|stream| stream.avg_frame_rate())
+            .filter(|rate| rate.numerator() > 0 && rate.denominator() > 0)
+            .unwrap_or(FFRational(30, 1));
+        let info = VideoInfo::from_decoder(&reader.decoder, frame_rate);
+
+        Ok(Self {
+            reader,
+            info,
+            _time: PhantomData,
+        })
+    }
+
+    pub fn info(&self) -> VideoInfo {
+        self.info
+    }
+}
+
+impl<S: TimeSource> PipelineSourceTask for FileVideoSource<S> {
+    type Output = FFVideo;
+    type Clock = SynchronisedClock<RawNanoseconds, S>;
+
+    fn run(
+        &mut self,
+        clock: Self::Clock,
+        ready_signal: PipelineReadySignal,
+        control_signal: PipelineControlSignal,
+        output: Sender<Self::Output>,
+    ) {
+        run_file_source(
+            "video",
+            &mut self.reader,
+            clock,
+            ready_signal,
+            control_signal,
+            output,
+            FFVideo::empty,
+        );
+    }
+}
+
+/// Plays the best audio stream of a file into a pipeline.
+pub struct FileAudioSource<S: TimeSource = RealTime> {
+    reader: FileReader<decoder::Audio>,
+    info: AudioInfo,
+    _time: PhantomData<S>,
+}
+
+impl<S: TimeSource> FileAudioSource<S> {
+    pub fn init(path: impl AsRef<Path>, pacing: FilePacing) -> Result<Self, MediaError> {
+        let reader = FileReader::open(path.as_ref(), media::Type::Audio, pacing, |decoder| {
+            decoder.audio()
+        })?;
+        let info = AudioInfo::from_decoder(&reader.decoder);
+
+        Ok(Self {
+            reader,
+            info,
+            _time: PhantomData,
+        })
+    }
+
+    pub fn info(&self) -> AudioInfo {
+        self.info
+    }
+}
+
+impl<S: TimeSource> PipelineSourceTask for FileAudioSource<S> {
+    type Output = FFAudio;
+    type Clock = SynchronisedClock<RawNanoseconds, S>;
+
+    fn run(
+        &mut self,
+        clock: Self::Clock,
+        ready_signal: PipelineReadySignal,
+        control_signal: PipelineControlSignal,
+        output: Sender<Self::Output>,
+    ) {
+        run_file_source(
+            "audio",
+            &mut self.reader,
+            clock,
+            ready_signal,
+            control_signal,
+            output,
+            FFAudio::empty,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::{control::PipelineMessages, task::PipelineSinkTask, Pipeline};
+    use flume::Receiver;
+    use std::process::Command;
+
+    /// Hands whatever reaches it back to the test.
+    struct Collect<F>(Sender<F>);
+
+    impl<F: Send> PipelineSinkTask for Collect<F> {
+        type Input = F;
+
+        fn run(
+            &mut self,
+            ready_signal: PipelineReadySignal,
+            _messages: PipelineMessages,
+            input: Receiver<F>,
+        ) {
+            ready_signal.send(Ok(())).unwrap();
+            for frame in input.iter() {
+                self.0.send(frame).unwrap();
+            }
+        }
+    }
+
+    /// A second of 10fps video and 8kHz audio.
+    fn test_file(name: &str) -> tempfile::TempPath {
+        let sine = ["-f", "lavfi", "-i", "sine=duration=1:sample_rate=8000"];
+        generate(name, ".mkv", &sine, &["-c:a", "pcm_s16le"])
+    }
+
+    /// Encodes a second of 10fps video, along with any further `inputs`, with the ffmpeg CLI.
+    fn generate(name: &str, suffix: &str, inputs: &[&str], args: &[&str]) -> tempfile::TempPath {
+        let path = tempfile::Builder::new()
+            .prefix(name)
+            .suffix(suffix)
+            .tempfile()
+            .unwrap()
+            .into_temp_path();
+
+        let status = Command::new("ffmpeg")
+            .args(["-y", "-loglevel", "error"])
+            .args(["-f", "lavfi", "-i", "testsrc=duration=1:size=64x48:rate=10"])
+            .args(inputs)
+            .args(["-c:v", "mpeg4"])
+            .args(args)
+            .arg(&*path)
+            .status()
+            .expect("The ffmpeg command line tool is needed to generate test media");
+        assert!(status.success());
+
+        path
+    }
+
+    type FileClock = SynchronisedClock<RawNanoseconds>;
+
+    /// Plays `source` through a pipeline until it reports the end of its input.
+    fn play_to_end<F: Send + 'static>(
+        source: impl PipelineSourceTask<Output = F, Clock = FileClock> + 'static,
+    ) -> Vec<F> {
+        let (frames, status) = play(source);
+        assert_eq!(status, PipelineStatus::EndOfInput);
+
+        frames
+    }
+
+    /// Plays `source` through a pipeline until it stops, returning what it sent and why it
+    /// stopped.
+    fn play<F: Send + 'static>(
+        source: impl PipelineSourceTask<Output = F, Clock = FileClock> + 'static,
+    ) -> (Vec<F>, PipelineStatus) {
+        let (tx, rx) = flume::unbounded();
+        let clock = SynchronisedClock::<()>::new();
+
+        futures::executor::block_on(async {
+            let mut pipeline = Pipeline::builder(clock)
+                .source("file", source)
+                .sink("collect", Collect(tx))
+                .build()
+                .await
+                .unwrap();
+            let status_updates = pipeline.status_updates();
+
+            pipeline.play().await.unwrap();
+            let (task, status) = status_updates
+                .recv_timeout(Duration::from_secs(10))
+                .unwrap();
+            assert_eq!(task, "file");
+            pipeline.shutdown().await.unwrap();
+
+            (rx.drain().collect(), status)
+        })
+    }
+
+    fn assert_increasing(pts: impl Iterator<Item = Option<i64>>) {
+        let pts = pts.map(Option::unwrap).collect::<Vec<_>>();
+        assert!(pts.windows(2).all(|pair| pair[0] < pair[1]), "{pts:?}");
+    }
+
+    #[test]
+    fn files_are_played_as_fast_as_possible() {
+        let path = test_file("file-source");
+
+        let source =
+            FileVideoSource::<RealTime>::init(&path, FilePacing::AsFastAsPossible).unwrap();
+        assert_eq!((source.info().width, source.info().height), (64, 48));
+        let frames = play_to_end(source);
+        assert_eq!(frames.len(), 10);
+        assert_increasing(frames.iter().map(|frame| frame.pts()));
+
+        let source =
+            FileAudioSource::<RealTime>::init(&path, FilePacing::AsFastAsPossible).unwrap();
+        assert_eq!(source.info().rate(), 8000);
+        let frames = play_to_end(source);
+        assert_eq!(
+            frames.iter().map(|frame| frame.samples()).sum::<usize>(),
+            8000
+        );
+        assert_increasing(frames.iter().map(|frame| frame.pts()));
+    }
+
+    #[test]
+    fn truncated_files_fail() {
+        // With the index at the start, the missing frames are known to be missing.
+        let path = generate("truncated", ".mp4", &[], &["-movflags", "+faststart"]);
+        let len = std::fs::metadata(&path).unwrap().len();
+        let file = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+        file.set_len(len * 2 / 3).unwrap();
+
+        let source =
+            FileVideoSource::<RealTime>::init(&path, FilePacing::AsFastAsPossible).unwrap();
+        let (frames, status) = play(source);
+
+        assert!(
+            matches!(status, PipelineStatus::InputFailed(_)),
+            "{status:?}"
+        );
+        assert!(frames.len() < 10, "{}", frames.len());
+    }
+
+    #[test]
+    fn positions_are_converted_from_the_stream_time_base() {
+        assert_eq!(
+            to_nanoseconds(90_000, FFRational(1, 90_000)),
+            Some(1_000_000_000)
+        );
+        assert_eq!(
+            to_nanoseconds(1_001, FFRational(1, 30_000)),
+            Some(33_366_666)
+        );
+        assert_eq!(to_nanoseconds(-1, FFRational(1, 1_000)), None);
+    }
+}
@@ -1,7 +1,9 @@
 mod audio_input;
 mod camera;
+mod file;
 mod screen_capture;
 
 pub use audio_input::*;
 pub use camera::*;
+pub use file::*;
 pub use screen_capture::*;
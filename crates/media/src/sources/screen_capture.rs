@@ -6,7 +6,7 @@ use scap::{
 };
 use serde::{Deserialize, Serialize};
 use specta::Type;
-use std::collections::HashMap;
+use std::{collections::HashMap, marker::PhantomData};
 
 use crate::pipeline::{clock::*, control::Control, task::PipelineSourceTask};
 use crate::{
@@ -47,13 +47,14 @@ impl PartialEq<Target> for ScreenCaptureTarget {
     }
 }
 
-pub struct ScreenCaptureSource {
+pub struct ScreenCaptureSource<S: TimeSource = RealTime> {
     options: Options,
     video_info: VideoInfo,
     target: ScreenCaptureTarget,
+    _time: PhantomData<S>,
 }
 
-impl ScreenCaptureSource {
+impl<S: TimeSource> ScreenCaptureSource<S> {
     pub const DEFAULT_FPS: u32 = 30;
 
     pub fn init(
@@ -108,9 +109,16 @@ impl ScreenCaptureSource {
             options,
             target: capture_target.clone(),
             video_info: VideoInfo::from_raw(RawVideoFormat::Bgra, frame_width, frame_height, fps),
+            _time: PhantomData,
         }
     }
 
+    pub fn info(&self) -> VideoInfo {
+        self.video_info
+    }
+}
+
+impl ScreenCaptureSource {
     pub fn list_targets() -> Vec<CaptureWindow> {
         if !scap::has_permission() {
             return vec![];
@@ -142,14 +150,10 @@ impl ScreenCaptureSource {
             })
             .collect()
     }
-
-    pub fn info(&self) -> VideoInfo {
-        self.video_info
-    }
 }
 
-impl PipelineSourceTask for ScreenCaptureSource {
-    type Clock = SynchronisedClock<RawNanoseconds>;
+impl<S: TimeSource> PipelineSourceTask for ScreenCaptureSource<S> {
+    type Clock = SynchronisedClock<RawNanoseconds, S>;
     type Output = FFVideo;
 
     fn run(
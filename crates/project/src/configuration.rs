@@ -1,5 +1,9 @@
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use specta::Type;
+use std::path::Path;
+
+use crate::{load_versioned, save_versioned, LoadResult, Migration, ProjectError, Versioned};
 
 #[derive(Type, Serialize, Deserialize, Clone, Debug, Default)]
 #[serde(rename_all = "camelCase")]
@@ -46,7 +50,7 @@ impl Default for BackgroundSource {
     }
 }
 
-#[derive(Type, Serialize, Deserialize, Clone, Debug, Default)]
+#[derive(Type, Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct XY<T> {
     pub x: T,
@@ -126,15 +130,26 @@ impl Default for CameraConfiguration {
 }
 
 impl CameraConfiguration {
-    fn default_size() -> f32 {
+    pub(crate) fn default_size() -> f32 {
         30.0
     }
 
-    fn default_rounding() -> f32 {
+    pub(crate) fn default_rounding() -> f32 {
         100.0
     }
 }
 
+/// How the screens of a recording with more than one are arranged.
+#[derive(Type, Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum DisplayLayout {
+    #[default]
+    SideBySide,
+    Stacked,
+    /// The other screens are shown small in the bottom right corner of the main one.
+    PictureInPicture,
+}
+
 #[derive(Type, Serialize, Deserialize, Clone, Debug, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct AudioConfiguration {
@@ -218,6 +233,8 @@ pub struct ProjectConfiguration {
     pub aspect_ratio: Option<AspectRatio>,
     pub background: BackgroundConfiguration,
     pub camera: CameraConfiguration,
+    #[serde(default)]
+    pub display_layout: DisplayLayout,
     pub audio: AudioConfiguration,
     pub cursor: CursorConfiguration,
     pub hotkeys: HotkeysConfiguration,
@@ -225,7 +242,32 @@ pub struct ProjectConfiguration {
     pub timeline: Option<TimelineConfiguration>,
 }
 
+impl Versioned for ProjectConfiguration {
+    const FILE_NAME: &'static str = "project-config.json";
+    const MIGRATIONS: &'static [Migration] = &[add_camera_size_and_rounding];
+}
+
+/// The camera's size and rounding weren't always configurable.
+fn add_camera_size_and_rounding(config: &mut Value) {
+    if let Some(camera) = config.get_mut("camera").and_then(Value::as_object_mut) {
+        camera
+            .entry("size")
+            .or_insert(CameraConfiguration::default_size().into());
+        camera
+            .entry("rounding")
+            .or_insert(CameraConfiguration::default_rounding().into());
+    }
+}
+
 impl ProjectConfiguration {
+    pub fn load(project_path: &Path) -> LoadResult<Self> {
+        load_versioned(project_path)
+    }
+
+    pub fn save(&self, project_path: &Path) -> Result<(), ProjectError> {
+        save_versioned(self, project_path)
+    }
+
     pub fn timeline(&self) -> Option<&TimelineConfiguration> {
         self.timeline.as_ref()
     }
@@ -240,6 +282,7 @@ impl Default for ProjectConfiguration {
                 ..Default::default()
             },
             camera: CameraConfiguration::default(),
+            display_layout: DisplayLayout::default(),
             audio: AudioConfiguration::default(),
             cursor: CursorConfiguration::default(),
             hotkeys: HotkeysConfiguration::default(),
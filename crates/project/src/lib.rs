@@ -1,10 +1,30 @@
 mod configuration;
+mod validation;
+mod versioning;
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 pub use configuration::*;
 use serde::{Deserialize, Serialize};
 use specta::Type;
+use thiserror::Error;
+pub use validation::*;
+pub use versioning::*;
+
+#[derive(Error, Debug)]
+pub enum ProjectError {
+    #[error("The project has no {0}")]
+    Missing(&'static str),
+
+    #[error("Failed to read {file}: {error}")]
+    Corrupt { file: &'static str, error: String },
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize, Type)]
 pub struct Display {
@@ -19,6 +39,20 @@ pub struct CameraMeta {
 #[derive(Debug, Clone, Serialize, Deserialize, Type)]
 pub struct AudioMeta {
     pub path: PathBuf,
+    #[serde(default)]
+    pub drift: Option<AudioDrift>,
+}
+
+/// How far the audio device's sample clock drifted from the recording clock.
+/// The drift is compensated during recording; this is kept for diagnostics.
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct AudioDrift {
+    pub sample_rate: u32,
+    /// Audio the recording clock says should have been captured, in seconds.
+    pub expected_duration: f64,
+    /// Audio the device actually delivered, in seconds.
+    pub actual_duration: f64,
+    pub ppm: f64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Type)]
@@ -33,6 +67,21 @@ pub struct RecordingSegment {
     pub end: f64,
 }
 
+/// A point of interest dropped during recording, in seconds from the start of the recording.
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct RecordingMarker {
+    pub time: f64,
+    pub label: String,
+}
+
+/// The camera being changed during recording, in seconds from the start of the recording.
+/// The camera track keeps its size, so the new camera's frames are scaled to fit it.
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct CameraSwitch {
+    pub time: f64,
+    pub camera: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Type)]
 pub struct RecordingMeta {
     // this field is just for convenience, it shouldn't be persisted
@@ -41,42 +90,39 @@ pub struct RecordingMeta {
     pub pretty_name: String,
     #[serde(default)]
     pub sharing: Option<SharingMeta>,
-    pub display: Display,
+    /// Missing for camera-only and audio-only recordings.
+    #[serde(default)]
+    pub display: Option<Display>,
+    /// Screens or windows recorded alongside `display`, laid out according to the project's
+    /// `display_layout`.
+    #[serde(default)]
+    pub additional_displays: Vec<Display>,
     #[serde(default)]
     pub camera: Option<CameraMeta>,
     #[serde(default)]
     pub audio: Option<AudioMeta>,
     #[serde(default)]
     pub segments: Vec<RecordingSegment>,
+    #[serde(default)]
+    pub markers: Vec<RecordingMarker>,
+    #[serde(default)]
+    pub camera_switches: Vec<CameraSwitch>,
+}
+
+impl Versioned for RecordingMeta {
+    const FILE_NAME: &'static str = "recording-meta.json";
+    // Unversioned files only ever gained fields with defaults, so they parse as they are.
+    const MIGRATIONS: &'static [Migration] = &[|_| {}];
 }
 
 impl RecordingMeta {
-    pub fn load_for_project(project_path: &PathBuf) -> Result<Self, String> {
-        let meta_path = project_path.join("recording-meta.json");
-        let meta = match std::fs::read_to_string(&meta_path) {
-            Ok(content) => content,
-            Err(_) => {
-                return Ok(Self {
-                    project_path: project_path.clone(),
-                    pretty_name: String::new(),
-                    sharing: None,
-                    display: Display {
-                        path: PathBuf::new(),
-                    },
-                    camera: None,
-                    audio: None,
-                    segments: Vec::new(),
-                });
-            }
-        };
-        let mut meta: Self = serde_json::from_str(&meta).map_err(|e| e.to_string())?;
-        meta.project_path = project_path.clone();
+    pub fn load_for_project(project_path: &Path) -> Result<Self, ProjectError> {
+        let mut meta = load_versioned::<Self>(project_path).into_result()?;
+        meta.project_path = project_path.to_path_buf();
         Ok(meta)
     }
 
-    pub fn save_for_project(&self) {
-        let meta_path = &self.project_path.join("recording-meta.json");
-        let meta = serde_json::to_string_pretty(&self).unwrap();
-        std::fs::write(meta_path, meta).unwrap();
+    pub fn save_for_project(&self) -> Result<(), ProjectError> {
+        save_versioned(self, &self.project_path)
     }
 }
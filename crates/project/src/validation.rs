@@ -0,0 +1,353 @@
+use std::{cmp::Ordering, ops::RangeInclusive};
+use thiserror::Error;
+
+use crate::{
+    CameraConfiguration, Crop, ProjectConfiguration, TimelineConfiguration, TimelineSegment, XY,
+};
+
+/// The ranges offered by the editor.
+const CAMERA_SIZE_RANGE: RangeInclusive<f32> = 20.0..=80.0;
+const CAMERA_ROUNDING_RANGE: RangeInclusive<f32> = 0.0..=100.0;
+
+/// Recording lengths are only known to the second.
+const DURATION_TOLERANCE_SECS: f64 = 1.0;
+
+/// What a project's configuration is checked against.
+pub trait RecordingProperties {
+    /// Size of the frames that the crop applies to.
+    fn frame_size(&self) -> XY<u32>;
+    /// Length of the recording, in seconds.
+    fn duration(&self) -> f64;
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ValidationIssue {
+    #[error("The crop is empty")]
+    EmptyCrop,
+
+    #[error("The crop goes past the edge of the {}x{} frame", frame_size.x, frame_size.y)]
+    CropOutOfBounds { frame_size: XY<u32> },
+
+    #[error("Timeline segment {segment} has a timescale of {timescale}")]
+    InvalidTimescale { segment: usize, timescale: f64 },
+
+    #[error("Timeline segment {segment} ends before it starts")]
+    EmptySegment { segment: usize },
+
+    #[error("Timeline segments {first} and {second} overlap or are out of order")]
+    OverlappingSegments { first: usize, second: usize },
+
+    #[error("Timeline segment {segment} ends after the recording's {duration}s")]
+    SegmentPastEnd { segment: usize, duration: f64 },
+
+    #[error("The timeline has no segments")]
+    EmptyTimeline,
+
+    #[error("The camera's size of {0} is out of range")]
+    CameraSizeOutOfRange(f32),
+
+    #[error("The camera's rounding of {0} is out of range")]
+    CameraRoundingOutOfRange(f32),
+}
+
+/// Errors make for broken frames or an empty export, while warnings are only unexpected.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ValidationReport {
+    pub errors: Vec<ValidationIssue>,
+    pub warnings: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+impl Crop {
+    fn is_empty(&self) -> bool {
+        self.size.x == 0 || self.size.y == 0
+    }
+
+    fn fits(&self, frame_size: &XY<u32>) -> bool {
+        self.position.x.saturating_add(self.size.x) <= frame_size.x
+            && self.position.y.saturating_add(self.size.y) <= frame_size.y
+    }
+
+    /// Moves and shrinks the crop until it fits in the frame, keeping it at least a pixel in size.
+    pub fn clamp_to(&mut self, frame_size: &XY<u32>) {
+        let clamp = |position: &mut u32, size: &mut u32, frame: u32| {
+            let frame = frame.max(1);
+            *position = (*position).min(frame - 1);
+            *size = (*size).clamp(1, frame - *position);
+        };
+
+        clamp(&mut self.position.x, &mut self.size.x, frame_size.x);
+        clamp(&mut self.position.y, &mut self.size.y, frame_size.y);
+    }
+}
+
+impl TimelineSegment {
+    fn is_empty(&self) -> bool {
+        // Also true if either end is NaN
+        self.end.partial_cmp(&self.start) != Some(Ordering::Greater)
+    }
+
+    fn has_valid_timescale(&self) -> bool {
+        self.timescale.is_finite() && self.timescale > 0.0
+    }
+}
+
+impl TimelineConfiguration {
+    /// Drops empty segments, resets invalid timescales and merges overlapping segments into
+    /// the earliest one, leaving the segments ordered by their start.
+    pub fn merge_overlapping(&mut self) {
+        self.segments.retain(|segment| !segment.is_empty());
+        for segment in &mut self.segments {
+            if !segment.has_valid_timescale() {
+                segment.timescale = 1.0;
+            }
+        }
+        self.segments.sort_by(|a, b| a.start.total_cmp(&b.start));
+
+        let mut merged = Vec::<TimelineSegment>::with_capacity(self.segments.len());
+        for segment in self.segments.drain(..) {
+            match merged.last_mut() {
+                Some(last) if segment.start < last.end => last.end = last.end.max(segment.end),
+                _ => merged.push(segment),
+            }
+        }
+        self.segments = merged;
+    }
+}
+
+impl CameraConfiguration {
+    fn clamp_to_ranges(&mut self) {
+        let clamp = |value: f32, range: &RangeInclusive<f32>, default: f32| {
+            if value.is_nan() {
+                default
+            } else {
+                value.clamp(*range.start(), *range.end())
+            }
+        };
+
+        self.size = clamp(self.size, &CAMERA_SIZE_RANGE, Self::default_size());
+        self.rounding = clamp(
+            self.rounding,
+            &CAMERA_ROUNDING_RANGE,
+            Self::default_rounding(),
+        );
+    }
+}
+
+impl ProjectConfiguration {
+    pub fn validate(&self, recording: &impl RecordingProperties) -> ValidationReport {
+        let mut report = ValidationReport::default();
+
+        if let Some(crop) = &self.background.crop {
+            let frame_size = recording.frame_size();
+
+            if crop.is_empty() {
+                report.errors.push(ValidationIssue::EmptyCrop);
+            } else if !crop.fits(&frame_size) {
+                report
+                    .errors
+                    .push(ValidationIssue::CropOutOfBounds { frame_size });
+            }
+        }
+
+        if let Some(timeline) = &self.timeline {
+            if timeline.segments.is_empty() {
+                report.errors.push(ValidationIssue::EmptyTimeline);
+            }
+
+            let duration = recording.duration();
+            for (i, segment) in timeline.segments.iter().enumerate() {
+                if !segment.has_valid_timescale() {
+                    report.errors.push(ValidationIssue::InvalidTimescale {
+                        segment: i,
+                        timescale: segment.timescale,
+                    });
+                }
+                if segment.is_empty() {
+                    report
+                        .errors
+                        .push(ValidationIssue::EmptySegment { segment: i });
+                }
+                if segment.end > duration + DURATION_TOLERANCE_SECS {
+                    report.warnings.push(ValidationIssue::SegmentPastEnd {
+                        segment: i,
+                        duration,
+                    });
+                }
+            }
+
+            for (i, pair) in timeline.segments.windows(2).enumerate() {
+                if pair[1].start < pair[0].end {
+                    report.errors.push(ValidationIssue::OverlappingSegments {
+                        first: i,
+                        second: i + 1,
+                    });
+                }
+            }
+        }
+
+        if !CAMERA_SIZE_RANGE.contains(&self.camera.size) {
+            report
+                .warnings
+                .push(ValidationIssue::CameraSizeOutOfRange(self.camera.size));
+        }
+        if !CAMERA_ROUNDING_RANGE.contains(&self.camera.rounding) {
+            report
+                .warnings
+                .push(ValidationIssue::CameraRoundingOutOfRange(
+                    self.camera.rounding,
+                ));
+        }
+
+        report
+    }
+
+    /// Fixes what `validate` complains about where there's an obvious fix: empty crops and
+    /// timelines are dropped, other crops are clamped, segments are merged and camera
+    /// settings are brought into range.
+    pub fn fix(&mut self, recording: &impl RecordingProperties) {
+        if let Some(crop) = &mut self.background.crop {
+            if crop.is_empty() {
+                self.background.crop = None;
+            } else {
+                crop.clamp_to(&recording.frame_size());
+            }
+        }
+
+        if let Some(timeline) = &mut self.timeline {
+            timeline.merge_overlapping();
+            if timeline.segments.is_empty() {
+                self.timeline = None;
+            }
+        }
+
+        self.camera.clamp_to_ranges();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Recording;
+
+    impl RecordingProperties for Recording {
+        fn frame_size(&self) -> XY<u32> {
+            XY { x: 1920, y: 1080 }
+        }
+
+        fn duration(&self) -> f64 {
+            30.0
+        }
+    }
+
+    fn segment(start: f64, end: f64, timescale: f64) -> TimelineSegment {
+        TimelineSegment {
+            timescale,
+            start,
+            end,
+        }
+    }
+
+    fn crop(x: u32, y: u32, width: u32, height: u32) -> Crop {
+        Crop {
+            position: XY { x, y },
+            size: XY {
+                x: width,
+                y: height,
+            },
+        }
+    }
+
+    #[test]
+    fn default_configuration_is_valid() {
+        assert_eq!(
+            ProjectConfiguration::default().validate(&Recording),
+            ValidationReport::default()
+        );
+    }
+
+    #[test]
+    fn invalid_configuration_is_reported_and_fixed() {
+        let mut config = ProjectConfiguration::default();
+        config.background.crop = Some(crop(1000, 0, 1920, 1080));
+        config.timeline = Some(TimelineConfiguration {
+            segments: vec![
+                segment(10.0, 20.0, 1.0),
+                segment(0.0, 12.0, 0.0),
+                segment(15.0, 15.0, 1.0),
+                segment(25.0, 40.0, 2.0),
+            ],
+        });
+        config.camera.size = 200.0;
+
+        let report = config.validate(&Recording);
+        assert_eq!(
+            report.errors,
+            vec![
+                ValidationIssue::CropOutOfBounds {
+                    frame_size: Recording.frame_size()
+                },
+                ValidationIssue::InvalidTimescale {
+                    segment: 1,
+                    timescale: 0.0
+                },
+                ValidationIssue::EmptySegment { segment: 2 },
+                ValidationIssue::OverlappingSegments {
+                    first: 0,
+                    second: 1
+                },
+            ]
+        );
+        assert_eq!(
+            report.warnings,
+            vec![
+                ValidationIssue::SegmentPastEnd {
+                    segment: 3,
+                    duration: 30.0
+                },
+                ValidationIssue::CameraSizeOutOfRange(200.0),
+            ]
+        );
+
+        config.fix(&Recording);
+
+        let crop = config.background.crop.as_ref().unwrap();
+        assert_eq!((crop.position.x, crop.size.x), (1000, 920));
+        assert_eq!((crop.position.y, crop.size.y), (0, 1080));
+
+        let segments = &config.timeline().unwrap().segments;
+        assert_eq!(segments.len(), 2);
+        assert_eq!(
+            (segments[0].start, segments[0].end, segments[0].timescale),
+            (0.0, 20.0, 1.0)
+        );
+        assert_eq!((segments[1].start, segments[1].end), (25.0, 40.0));
+        assert_eq!(config.camera.size, 80.0);
+
+        let report = config.validate(&Recording);
+        assert!(report.is_valid());
+        assert_eq!(report.warnings.len(), 1);
+    }
+
+    #[test]
+    fn empty_crops_and_timelines_are_dropped() {
+        let mut config = ProjectConfiguration::default();
+        config.background.crop = Some(crop(0, 0, 0, 1080));
+        config.timeline = Some(TimelineConfiguration { segments: vec![] });
+
+        assert_eq!(
+            config.validate(&Recording).errors,
+            vec![ValidationIssue::EmptyCrop, ValidationIssue::EmptyTimeline]
+        );
+
+        config.fix(&Recording);
+        assert!(config.background.crop.is_none());
+        assert!(config.timeline.is_none());
+    }
+}
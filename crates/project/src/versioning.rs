@@ -0,0 +1,306 @@
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::{
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use crate::ProjectError;
+
+/// Brings a document one version forward.
+pub type Migration = fn(&mut Value);
+
+/// A document stored in a project directory with a `version` field, so that files written
+/// by older versions of Cap can be brought up to date instead of failing to parse.
+pub trait Versioned: Serialize + DeserializeOwned {
+    const FILE_NAME: &'static str;
+    /// Indexed by the version they migrate from. Version 0 is any file written before
+    /// documents were versioned.
+    const MIGRATIONS: &'static [Migration];
+    const VERSION: u32 = Self::MIGRATIONS.len() as u32;
+}
+
+/// The outcome of loading a versioned document.
+#[derive(Debug)]
+pub enum LoadResult<T> {
+    /// The project has no such file.
+    Missing,
+    Loaded(T),
+    /// The file was written by an older version and has been rewritten in the current one.
+    /// The original is kept next to it, see `backup_path`.
+    Migrated {
+        value: T,
+        from_version: u32,
+    },
+    /// The file couldn't be read or doesn't match any known version. A copy of it is kept
+    /// so that saving over it doesn't lose it.
+    Corrupt(String),
+}
+
+impl<T> LoadResult<T> {
+    /// The document, if there is a usable one.
+    pub fn value(self) -> Option<T> {
+        match self {
+            Self::Loaded(value) | Self::Migrated { value, .. } => Some(value),
+            Self::Missing | Self::Corrupt(_) => None,
+        }
+    }
+}
+
+impl<T: Versioned> LoadResult<T> {
+    /// The document, treating a missing file as an error.
+    pub fn into_result(self) -> Result<T, ProjectError> {
+        match self {
+            Self::Loaded(value) | Self::Migrated { value, .. } => Ok(value),
+            Self::Missing => Err(ProjectError::Missing(T::FILE_NAME)),
+            Self::Corrupt(error) => Err(ProjectError::Corrupt {
+                file: T::FILE_NAME,
+                error,
+            }),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct WithVersion<'a, T> {
+    version: u32,
+    #[serde(flatten)]
+    value: &'a T,
+}
+
+/// Where the original of a migrated document is kept, e.g. `project-config.json.v0.bak`.
+pub fn backup_path<T: Versioned>(project_path: &Path, from_version: u32) -> PathBuf {
+    project_path.join(format!("{}.v{from_version}.bak", T::FILE_NAME))
+}
+
+fn corrupt_backup_path<T: Versioned>(project_path: &Path) -> PathBuf {
+    project_path.join(format!("{}.corrupt.bak", T::FILE_NAME))
+}
+
+/// Parses a document of any known version, returning it along with the version it was in.
+fn migrate<T: Versioned>(contents: &str) -> Result<(T, u32), String> {
+    let mut value: Value = serde_json::from_str(contents).map_err(|e| e.to_string())?;
+
+    let version = match value.get("version") {
+        None => 0,
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or("Invalid version")?,
+    };
+    if version > T::VERSION {
+        return Err(format!(
+            "Written by a newer version of Cap (version {version})"
+        ));
+    }
+
+    for migration in &T::MIGRATIONS[version as usize..] {
+        migration(&mut value);
+    }
+
+    let document = serde_json::from_value(value).map_err(|e| e.to_string())?;
+
+    Ok((document, version))
+}
+
+pub fn to_versioned_json<T: Versioned>(value: &T) -> Result<String, serde_json::Error> {
+    serde_json::to_string_pretty(&WithVersion {
+        version: T::VERSION,
+        value,
+    })
+}
+
+/// Writes `contents` to a temporary file that replaces `path` once it's on disk, so that a
+/// full disk or a crash leaves either the old or the new contents rather than a mix.
+fn write_atomic(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+    let temp_path = PathBuf::from(temp_path);
+
+    let result = File::create(&temp_path)
+        .and_then(|mut file| {
+            file.write_all(contents)?;
+            file.sync_all()
+        })
+        .and_then(|_| std::fs::rename(&temp_path, path));
+
+    if result.is_err() {
+        std::fs::remove_file(&temp_path).ok();
+    }
+
+    // Makes the rename itself durable. Directories can't be opened like this on Windows.
+    #[cfg(unix)]
+    if let Some(dir) = path.parent() {
+        File::open(dir).and_then(|dir| dir.sync_all()).ok();
+    }
+
+    result
+}
+
+pub fn save_versioned<T: Versioned>(value: &T, project_path: &Path) -> Result<(), ProjectError> {
+    let contents = to_versioned_json(value)?;
+    write_atomic(&project_path.join(T::FILE_NAME), contents.as_bytes())?;
+
+    Ok(())
+}
+
+pub fn load_versioned<T: Versioned>(project_path: &Path) -> LoadResult<T> {
+    let path = project_path.join(T::FILE_NAME);
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return LoadResult::Missing,
+        Err(e) => return LoadResult::Corrupt(e.to_string()),
+    };
+
+    match migrate::<T>(&contents) {
+        Ok((value, version)) if version == T::VERSION => LoadResult::Loaded(value),
+        Ok((value, from_version)) => {
+            let backup = backup_path::<T>(project_path, from_version);
+            // An existing backup is the oldest original, which is the one worth keeping.
+            let backed_up = backup.exists() || write_atomic(&backup, contents.as_bytes()).is_ok();
+
+            if backed_up {
+                if let Err(e) = save_versioned(&value, project_path) {
+                    eprintln!("Failed to save migrated {}: {e}", T::FILE_NAME);
+                }
+            } else {
+                eprintln!("Failed to back up {}, not migrating it", T::FILE_NAME);
+            }
+
+            LoadResult::Migrated {
+                value,
+                from_version,
+            }
+        }
+        Err(error) => {
+            eprintln!("Failed to load {}: {error}", path.display());
+            write_atomic(&corrupt_backup_path::<T>(project_path), contents.as_bytes()).ok();
+
+            LoadResult::Corrupt(error)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CameraXPosition, ProjectConfiguration, RecordingMeta};
+
+    fn project_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("cap-project-{}-{name}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn recording_meta_fixtures() {
+        let (meta, version) =
+            migrate::<RecordingMeta>(include_str!("../fixtures/recording-meta-v0.json")).unwrap();
+        assert_eq!(version, 0);
+        assert_eq!(meta.pretty_name, "Cap 2024-08-30 at 14.02.11");
+        assert_eq!(
+            meta.display.unwrap().path,
+            PathBuf::from("content/display.mp4")
+        );
+        assert!(meta.camera.is_none());
+        assert!(meta.markers.is_empty());
+
+        let (meta, version) =
+            migrate::<RecordingMeta>(include_str!("../fixtures/recording-meta-v1.json")).unwrap();
+        assert_eq!(version, 1);
+        assert!(meta.display.is_none());
+        assert_eq!(meta.markers.len(), 1);
+        assert_eq!(meta.camera_switches[0].camera, "FaceTime HD Camera");
+    }
+
+    #[test]
+    fn project_config_fixtures() {
+        let (config, version) =
+            migrate::<ProjectConfiguration>(include_str!("../fixtures/project-config-v0.json"))
+                .unwrap();
+        assert_eq!(version, 0);
+        assert_eq!(config.camera.size, 30.0);
+        assert_eq!(config.camera.rounding, 100.0);
+        assert!(matches!(config.camera.position.x, CameraXPosition::Left));
+
+        let (config, version) = migrate::<ProjectConfiguration>(include_str!(
+            "../fixtures/project-config-v0-camera-size.json"
+        ))
+        .unwrap();
+        assert_eq!(version, 0);
+        assert_eq!(config.camera.size, 45.0);
+        assert_eq!(config.camera.rounding, 20.0);
+        assert_eq!(config.timeline().unwrap().segments.len(), 2);
+
+        let (config, version) =
+            migrate::<ProjectConfiguration>(include_str!("../fixtures/project-config-v1.json"))
+                .unwrap();
+        assert_eq!(version, 1);
+        assert_eq!(config.background.padding, 12.5);
+
+        assert!(migrate::<ProjectConfiguration>(r#"{ "version": 99 }"#).is_err());
+        assert!(migrate::<ProjectConfiguration>(r#"{ "version": "1" }"#).is_err());
+    }
+
+    #[test]
+    fn saving_replaces_the_file_in_one_step() {
+        let dir = project_dir("save");
+        let path = dir.join(ProjectConfiguration::FILE_NAME);
+        std::fs::write(&path, "{}").unwrap();
+
+        ProjectConfiguration::default().save(&dir).unwrap();
+
+        assert!(matches!(
+            load_versioned::<ProjectConfiguration>(&dir),
+            LoadResult::Loaded(_)
+        ));
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        assert!(matches!(
+            ProjectConfiguration::default().save(&dir.join("missing")),
+            Err(ProjectError::Io(_))
+        ));
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn load_distinguishes_missing_migrated_and_corrupt() {
+        let dir = project_dir("load");
+        let path = dir.join(ProjectConfiguration::FILE_NAME);
+
+        assert!(matches!(
+            load_versioned::<ProjectConfiguration>(&dir),
+            LoadResult::Missing
+        ));
+
+        let original = include_str!("../fixtures/project-config-v0.json");
+        std::fs::write(&path, original).unwrap();
+        assert!(matches!(
+            load_versioned::<ProjectConfiguration>(&dir),
+            LoadResult::Migrated {
+                from_version: 0,
+                ..
+            }
+        ));
+        assert_eq!(
+            std::fs::read_to_string(backup_path::<ProjectConfiguration>(&dir, 0)).unwrap(),
+            original
+        );
+        assert!(matches!(
+            load_versioned::<ProjectConfiguration>(&dir),
+            LoadResult::Loaded(_)
+        ));
+
+        std::fs::write(&path, "{ \"aspectRatio\": ").unwrap();
+        assert!(matches!(
+            load_versioned::<ProjectConfiguration>(&dir),
+            LoadResult::Corrupt(_)
+        ));
+        assert!(corrupt_backup_path::<ProjectConfiguration>(&dir).exists());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
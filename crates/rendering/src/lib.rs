@@ -1,7 +1,7 @@
 use anyhow::Result;
 use bytemuck::{Pod, Zeroable};
 use decoder::AsyncVideoDecoderHandle;
-use futures::future::OptionFuture;
+use futures::future::{join_all, OptionFuture};
 use futures_intrusive::channel::shared::oneshot_channel;
 use serde::{Deserialize, Serialize};
 use specta::Type;
@@ -10,18 +10,40 @@ use wgpu::util::DeviceExt;
 use wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
 
 use cap_project::{
-    AspectRatio, BackgroundSource, CameraXPosition, CameraYPosition, Crop, ProjectConfiguration, XY,
+    AspectRatio, BackgroundSource, CameraXPosition, CameraYPosition, Crop, DisplayLayout,
+    ProjectConfiguration, XY,
 };
 
 use std::time::Instant;
 
 pub mod decoder;
 pub use decoder::DecodedFrame;
+mod waveform;
+pub use waveform::{Waveform, WAVEFORM_SIZE};
 
 #[derive(Debug, Clone, Serialize, Deserialize, Type)]
 pub struct RenderOptions {
     pub camera_size: Option<(u32, u32)>,
-    pub screen_size: (u32, u32),
+    /// `None` for camera-only and audio-only projects.
+    pub screen_size: Option<(u32, u32)>,
+    /// Screens recorded alongside the main one.
+    #[serde(default)]
+    pub additional_screen_sizes: Vec<(u32, u32)>,
+}
+
+impl RenderOptions {
+    /// Without a screen, the camera fills the frame instead of being overlaid on it.
+    pub fn camera_fills_frame(&self) -> bool {
+        self.screen_size.is_none() && self.camera_size.is_some()
+    }
+
+    /// Size of the video the project is laid out around: the screen, or else the camera,
+    /// or else the waveform drawn for audio-only projects.
+    pub fn frame_size(&self) -> (u32, u32) {
+        self.screen_size
+            .or(self.camera_size)
+            .unwrap_or(WAVEFORM_SIZE)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Type)]
@@ -71,26 +93,78 @@ impl From<BackgroundSource> for Background {
     }
 }
 
+/// Everything that goes into one frame of a project.
+#[derive(Clone)]
+pub struct RecordingFrames {
+    /// The screen, or the camera or waveform filling the output when there's no screen.
+    pub main: DecodedFrame,
+    /// In the order of `RenderOptions::additional_screen_sizes`, `None` once a screen has ended.
+    pub additional_screens: Vec<Option<DecodedFrame>>,
+    /// Overlaid on the screens, so always `None` when the camera fills the output.
+    pub camera: Option<DecodedFrame>,
+}
+
+impl RecordingFrames {
+    fn only(main: DecodedFrame) -> Self {
+        Self {
+            main,
+            additional_screens: vec![],
+            camera: None,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct RecordingDecoders {
-    screen: AsyncVideoDecoderHandle,
+    screen: Option<AsyncVideoDecoderHandle>,
+    additional_screens: Vec<AsyncVideoDecoderHandle>,
     camera: Option<AsyncVideoDecoderHandle>,
+    /// Stands in for the screen when there's neither a screen nor a camera.
+    waveform: Option<Waveform>,
 }
 
 impl RecordingDecoders {
-    pub fn new(screen: AsyncVideoDecoderHandle, camera: Option<AsyncVideoDecoderHandle>) -> Self {
-        RecordingDecoders { screen, camera }
+    pub fn new(
+        screen: Option<AsyncVideoDecoderHandle>,
+        additional_screens: Vec<AsyncVideoDecoderHandle>,
+        camera: Option<AsyncVideoDecoderHandle>,
+        waveform: Option<Waveform>,
+    ) -> Self {
+        RecordingDecoders {
+            screen,
+            additional_screens,
+            camera,
+            waveform,
+        }
     }
-    pub async fn get_frames(
-        &self,
-        frame_number: u32,
-    ) -> Option<(DecodedFrame, Option<DecodedFrame>)> {
-        let (screen_frame, camera_frame) = tokio::join!(
-            self.screen.get_frame(frame_number),
+
+    pub async fn get_frames(&self, frame_number: u32) -> Option<RecordingFrames> {
+        let Some(screen) = &self.screen else {
+            return match (&self.camera, &self.waveform) {
+                (Some(camera), _) => camera
+                    .get_frame(frame_number)
+                    .await
+                    .map(RecordingFrames::only),
+                (None, Some(waveform)) => waveform.frame(frame_number).map(RecordingFrames::only),
+                (None, None) => None,
+            };
+        };
+
+        let (screen_frame, additional_screens, camera_frame) = tokio::join!(
+            screen.get_frame(frame_number),
+            join_all(
+                self.additional_screens
+                    .iter()
+                    .map(|d| d.get_frame(frame_number))
+            ),
             OptionFuture::from(self.camera.as_ref().map(|d| d.get_frame(frame_number)))
         );
 
-        screen_frame.map(|f| (f, camera_frame.flatten()))
+        screen_frame.map(|main| RecordingFrames {
+            main,
+            additional_screens,
+            camera: camera_frame.flatten(),
+        })
     }
 
     pub async fn stop(&self) {
@@ -100,7 +174,12 @@ impl RecordingDecoders {
         if let Some(camera) = &self.camera {
             camera.stop().await;
         }
-        self.screen.stop().await;
+        if let Some(screen) = &self.screen {
+            screen.stop().await;
+        }
+        for screen in &self.additional_screens {
+            screen.stop().await;
+        }
         println!("Decoders stopped");
     }
 }
@@ -141,21 +220,11 @@ pub async fn render_video_to_channel(
                 frame_number as f64 / 30_f64
             };
 
-            let Some((screen_frame, camera_frame)) =
-                decoders.get_frames((time * 30.0) as u32).await
-            else {
+            let Some(frames) = decoders.get_frames((time * 30.0) as u32).await else {
                 break;
             };
 
-            let frame = match produce_frame(
-                &constants,
-                &screen_frame,
-                &camera_frame,
-                background,
-                &uniforms,
-            )
-            .await
-            {
+            let frame = match produce_frame(&constants, &frames, background, &uniforms).await {
                 Ok(frame) => frame,
                 Err(e) => {
                     eprintln!("{e}");
@@ -234,7 +303,8 @@ impl RenderVideoConstants {
 #[derive(Clone, Debug)]
 pub struct ProjectUniforms {
     pub output_size: (u32, u32),
-    display: CompositeVideoFrameUniforms,
+    /// The main screen first, then the additional screens.
+    displays: Vec<CompositeVideoFrameUniforms>,
     camera: Option<CompositeVideoFrameUniforms>,
 }
 
@@ -242,37 +312,120 @@ const CAMERA_PADDING: f32 = 50.0;
 
 const SCREEN_MAX_PADDING: f32 = 0.4;
 
+/// Space between screens laid out side by side or stacked, in canvas pixels.
+const DISPLAY_GAP: f32 = 40.0;
+/// Width of the additional screens in picture-in-picture layouts, relative to the main one.
+const PIP_SIZE: f32 = 0.3;
+const PIP_MARGIN: f32 = 0.03;
+
+/// Where a screen goes on the canvas, which holds the cropped main screen and the additional
+/// screens laid out around it. Padding and the aspect ratio apply to the canvas as a whole.
+struct DisplayPlacement {
+    frame_size: [f32; 2],
+    /// In frame pixels.
+    crop_bounds: [f32; 4],
+    /// In canvas pixels.
+    bounds: [f32; 4],
+}
+
 impl ProjectUniforms {
     fn get_crop(options: &RenderOptions, project: &ProjectConfiguration) -> Crop {
+        let frame_size = options.frame_size();
+
         project.background.crop.clone().unwrap_or(Crop {
             position: XY { x: 0, y: 0 },
             size: XY {
-                x: options.screen_size.0,
-                y: options.screen_size.1,
+                x: frame_size.0,
+                y: frame_size.1,
             },
         })
     }
 
-    fn get_padding(options: &RenderOptions, project: &ProjectConfiguration) -> f32 {
+    /// The canvas size and the placement of every screen on it, main screen first.
+    fn get_canvas(
+        options: &RenderOptions,
+        project: &ProjectConfiguration,
+    ) -> ([f32; 2], Vec<DisplayPlacement>) {
         let crop = Self::get_crop(options, project);
+        let frame_size = options.frame_size();
+        let (width, height) = (crop.size.x as f32, crop.size.y as f32);
+
+        let mut canvas = [width, height];
+        let mut placements = vec![DisplayPlacement {
+            frame_size: [frame_size.0 as f32, frame_size.1 as f32],
+            crop_bounds: [
+                crop.position.x as f32,
+                crop.position.y as f32,
+                (crop.position.x + crop.size.x) as f32,
+                (crop.position.y + crop.size.y) as f32,
+            ],
+            bounds: [0.0, 0.0, width, height],
+        }];
+
+        let margin = width * PIP_MARGIN;
+        let mut pip_bottom = height - margin;
 
-        let basis = u32::max(crop.size.x, crop.size.y);
+        for &(screen_width, screen_height) in &options.additional_screen_sizes {
+            let frame_size = [screen_width as f32, screen_height as f32];
+            let aspect = frame_size[0] / frame_size[1];
+
+            let bounds = match project.display_layout {
+                DisplayLayout::SideBySide => {
+                    let left = canvas[0] + DISPLAY_GAP;
+                    canvas[0] = left + height * aspect;
+                    [left, 0.0, canvas[0], height]
+                }
+                DisplayLayout::Stacked => {
+                    let top = canvas[1] + DISPLAY_GAP;
+                    canvas[1] = top + width / aspect;
+                    [0.0, top, width, canvas[1]]
+                }
+                DisplayLayout::PictureInPicture => {
+                    let pip_width = width * PIP_SIZE;
+                    let top = pip_bottom - pip_width / aspect;
+                    let bounds = [width - margin - pip_width, top, width - margin, pip_bottom];
+                    pip_bottom = top - margin;
+                    bounds
+                }
+            };
+
+            placements.push(DisplayPlacement {
+                frame_size,
+                crop_bounds: [0.0, 0.0, frame_size[0], frame_size[1]],
+                bounds,
+            });
+        }
+
+        (canvas, placements)
+    }
+
+    fn get_padding(options: &RenderOptions, project: &ProjectConfiguration) -> f32 {
+        let (canvas, _) = Self::get_canvas(options, project);
+
+        let basis = canvas[0].max(canvas[1]);
         let padding_factor = project.background.padding / 100.0 * SCREEN_MAX_PADDING;
 
-        basis as f32 * padding_factor
+        basis * padding_factor
     }
 
     pub fn get_output_size(options: &RenderOptions, project: &ProjectConfiguration) -> (u32, u32) {
-        let crop = Self::get_crop(options, project);
+        let (canvas, _) = Self::get_canvas(options, project);
+        let canvas = Crop {
+            position: XY { x: 0, y: 0 },
+            size: XY {
+                x: canvas[0].round() as u32,
+                y: canvas[1].round() as u32,
+            },
+        };
 
-        let crop_aspect = crop.aspect_ratio();
+        let crop_aspect = canvas.aspect_ratio();
 
         let padding = Self::get_padding(options, project) * 2.0;
 
         let aspect = match &project.aspect_ratio {
             None => {
-                let width = ((crop.size.x as f32 + padding) as u32 + 1) & !1;
-                let height = ((crop.size.y as f32 + padding) as u32 + 1) & !1;
+                let width = ((canvas.size.x as f32 + padding) as u32 + 1) & !1;
+                let height = ((canvas.size.y as f32 + padding) as u32 + 1) & !1;
                 return (width, height);
             }
             Some(AspectRatio::Square) => 1.0,
@@ -283,11 +436,11 @@ impl ProjectUniforms {
         };
 
         let (width, height) = if crop_aspect > aspect {
-            (crop.size.x, (crop.size.x as f32 / aspect) as u32)
+            (canvas.size.x, (canvas.size.x as f32 / aspect) as u32)
         } else if crop_aspect < aspect {
-            ((crop.size.y as f32 * aspect) as u32, crop.size.y)
+            ((canvas.size.y as f32 * aspect) as u32, canvas.size.y)
         } else {
-            (crop.size.x, crop.size.y)
+            (canvas.size.x, canvas.size.y)
         };
 
         // Ensure width and height are divisible by 2
@@ -299,24 +452,11 @@ impl ProjectUniforms {
         let output_size = Self::get_output_size(options, project);
         let output_aspect = output_size.0 as f32 / output_size.1 as f32;
 
-        let display = {
+        let displays = {
             let output_size = [output_size.0 as f32, output_size.1 as f32];
-            let size = [options.screen_size.0 as f32, options.screen_size.1 as f32];
 
-            let crop = Self::get_crop(options, project);
-
-            let crop_bounds = [
-                crop.position.x as f32,
-                crop.position.y as f32,
-                (crop.position.x + crop.size.x) as f32,
-                (crop.position.y + crop.size.y) as f32,
-            ];
-
-            let cropped_size = [
-                crop_bounds[2] - crop_bounds[0],
-                crop_bounds[3] - crop_bounds[1],
-            ];
-            let cropped_aspect = cropped_size[0] / cropped_size[1];
+            let (canvas, placements) = Self::get_canvas(options, project);
+            let cropped_aspect = canvas[0] / canvas[1];
 
             let padding = Self::get_padding(options, project);
             let is_height_constrained = cropped_aspect <= output_aspect;
@@ -350,23 +490,44 @@ impl ProjectUniforms {
                 output_size[1] - target_start[1],
             ];
 
-            let target_size = [target_bounds[2] - target_bounds[0], target_size[1]];
-            let min_target_axis = target_size[0].min(target_size[1]);
-
-            CompositeVideoFrameUniforms {
-                output_size,
-                frame_size: size,
-                crop_bounds,
-                target_bounds,
-                target_size,
-                rounding_px: project.background.rounding / 100.0 * 0.5 * min_target_axis,
-                ..Default::default()
-            }
+            let canvas_scale = (target_bounds[2] - target_bounds[0]) / canvas[0];
+
+            placements
+                .into_iter()
+                .map(|placement| {
+                    let target_bounds = [
+                        target_bounds[0] + placement.bounds[0] * canvas_scale,
+                        target_bounds[1] + placement.bounds[1] * canvas_scale,
+                        target_bounds[0] + placement.bounds[2] * canvas_scale,
+                        target_bounds[1] + placement.bounds[3] * canvas_scale,
+                    ];
+                    let target_size = [
+                        target_bounds[2] - target_bounds[0],
+                        target_bounds[3] - target_bounds[1],
+                    ];
+                    let min_target_axis = target_size[0].min(target_size[1]);
+
+                    CompositeVideoFrameUniforms {
+                        output_size,
+                        frame_size: placement.frame_size,
+                        crop_bounds: placement.crop_bounds,
+                        target_bounds,
+                        target_size,
+                        rounding_px: project.background.rounding / 100.0 * 0.5 * min_target_axis,
+                        mirror_x: if options.camera_fills_frame() && project.camera.mirror {
+                            1.0
+                        } else {
+                            0.0
+                        },
+                        ..Default::default()
+                    }
+                })
+                .collect()
         };
 
         let camera = options
             .camera_size
-            .filter(|_| !project.camera.hide)
+            .filter(|_| options.screen_size.is_some() && !project.camera.hide)
             .map(|camera_size| {
                 let output_size = [output_size.0 as f32, output_size.1 as f32];
 
@@ -421,7 +582,7 @@ impl ProjectUniforms {
 
         Self {
             output_size,
-            display,
+            displays,
             camera,
         }
     }
@@ -436,8 +597,7 @@ pub async fn produce_frame(
         queue,
         ..
     }: &RenderVideoConstants,
-    screen_frame: &Vec<u8>,
-    camera_frame: &Option<DecodedFrame>,
+    frames: &RecordingFrames,
     background: Background,
     uniforms: &ProjectUniforms,
 ) -> Result<Vec<u8>, String> {
@@ -498,14 +658,24 @@ pub async fn produce_frame(
         output_is_left = !output_is_left;
     }
 
-    {
-        let frame_size = options.screen_size;
+    let screens = std::iter::once((options.frame_size(), Some(&frames.main))).chain(
+        options
+            .additional_screen_sizes
+            .iter()
+            .copied()
+            .zip(frames.additional_screens.iter().map(Option::as_ref)),
+    );
+
+    for ((frame_size, frame), display_uniforms) in screens.zip(&uniforms.displays) {
+        let Some(frame) = frame else {
+            continue;
+        };
 
         let texture = device.create_texture(
             &(wgpu::TextureDescriptor {
                 size: wgpu::Extent3d {
-                    width: options.screen_size.0,
-                    height: options.screen_size.1,
+                    width: frame_size.0,
+                    height: frame_size.1,
                     depth_or_array_layers: 1,
                 },
                 mip_level_count: 1,
@@ -529,10 +699,10 @@ pub async fn produce_frame(
                 origin: wgpu::Origin3d::ZERO,
                 aspect: wgpu::TextureAspect::All,
             },
-            screen_frame,
+            frame,
             wgpu::ImageDataLayout {
                 offset: 0,
-                bytes_per_row: Some(options.screen_size.0 * 4),
+                bytes_per_row: Some(frame_size.0 * 4),
                 rows_per_image: None,
             },
             wgpu::Extent3d {
@@ -548,7 +718,7 @@ pub async fn produce_frame(
             &composite_video_frame_pipeline.render_pipeline,
             composite_video_frame_pipeline.bind_group(
                 device,
-                &uniforms.display.to_buffer(device),
+                &display_uniforms.to_buffer(device),
                 &texture_view,
                 get_either(texture_views, !output_is_left),
             ),
@@ -558,7 +728,7 @@ pub async fn produce_frame(
     }
 
     if let (Some(camera_size), Some(camera_frame), Some(uniforms)) =
-        (options.camera_size, camera_frame, &uniforms.camera)
+        (options.camera_size, &frames.camera, &uniforms.camera)
     {
         let texture = device.create_texture(
             &(wgpu::TextureDescriptor {
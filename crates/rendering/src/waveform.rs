@@ -0,0 +1,160 @@
+use std::sync::Arc;
+
+use crate::DecodedFrame;
+
+/// Size of the frames drawn for audio-only projects.
+pub const WAVEFORM_SIZE: (u32, u32) = (1280, 720);
+
+const FPS: f64 = 30.0;
+const BARS: usize = 64;
+/// How much audio the bars cover, ending at the frame being drawn.
+const WINDOW_SECS: f64 = 2.0;
+const BACKGROUND: [u8; 4] = [24, 24, 27, 255];
+const BAR: [u8; 4] = [255, 255, 255, 255];
+
+/// Draws the audio of audio-only projects as a scrolling waveform, so they can be previewed
+/// and exported like any other project.
+#[derive(Clone)]
+pub struct Waveform {
+    /// Interleaved samples.
+    samples: Arc<Vec<f64>>,
+    channels: u16,
+    sample_rate: u32,
+}
+
+impl Waveform {
+    pub fn new(samples: Arc<Vec<f64>>, channels: u16, sample_rate: u32) -> Self {
+        Self {
+            samples,
+            channels: channels.max(1),
+            sample_rate,
+        }
+    }
+
+    fn duration_secs(&self) -> f64 {
+        // A sample rate of 0 would make this NaN, and the waveform would never end
+        if self.sample_rate == 0 {
+            return 0.0;
+        }
+
+        self.samples.len() as f64 / (self.channels as f64 * self.sample_rate as f64)
+    }
+
+    /// Peak level of each bar between 0 and 1, oldest first.
+    fn levels(&self, time: f64) -> [f64; BARS] {
+        let channels = self.channels as usize;
+        let frames_per_bar = (WINDOW_SECS / BARS as f64 * self.sample_rate as f64) as i64;
+        let end_frame = (time * self.sample_rate as f64) as i64;
+        let sample_index = |frame: i64| (frame.max(0) as usize * channels).min(self.samples.len());
+
+        let mut levels = [0.0; BARS];
+        for (i, level) in levels.iter_mut().enumerate() {
+            let start_frame = end_frame - (BARS - i) as i64 * frames_per_bar;
+            let samples = &self.samples
+                [sample_index(start_frame)..sample_index(start_frame + frames_per_bar)];
+
+            *level = samples
+                .iter()
+                .fold(0.0_f64, |peak, sample| peak.max(sample.abs()))
+                .min(1.0);
+        }
+
+        levels
+    }
+
+    /// An RGBA frame of `WAVEFORM_SIZE`, or `None` once the audio has ended.
+    pub fn frame(&self, frame_number: u32) -> Option<DecodedFrame> {
+        let time = frame_number as f64 / FPS;
+        if time > self.duration_secs() {
+            return None;
+        }
+
+        let (width, height) = (WAVEFORM_SIZE.0 as usize, WAVEFORM_SIZE.1 as usize);
+        let mut frame = BACKGROUND.repeat(width * height);
+
+        let bar_width = width / BARS;
+        let gap = bar_width / 4;
+        let center = height / 2;
+
+        for (i, level) in self.levels(time).into_iter().enumerate() {
+            // Silence still shows up as a thin line
+            let half_height = ((level * 0.8 * center as f64) as usize).max(2);
+            let left = i * bar_width + gap / 2;
+
+            for y in center - half_height..center + half_height {
+                for x in left..left + bar_width - gap {
+                    let offset = (y * width + x) * 4;
+                    frame[offset..offset + 4].copy_from_slice(&BAR);
+                }
+            }
+        }
+
+        Some(Arc::new(frame))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A second each of loud, silent and quiet mono audio at 1kHz.
+    fn waveform() -> Waveform {
+        let samples = [0.5, 0.0, -0.25]
+            .into_iter()
+            .flat_map(|level| std::iter::repeat(level).take(1000))
+            .collect();
+
+        Waveform::new(Arc::new(samples), 1, 1000)
+    }
+
+    #[test]
+    fn bars_show_the_peak_of_the_window_ending_at_the_frame() {
+        let waveform = waveform();
+
+        // Each bar covers 31 samples, so the window starts just after the first 16.
+        let levels = waveform.levels(2.0);
+        assert_eq!(levels[0], 0.5);
+        assert_eq!(levels[31], 0.5);
+        assert_eq!(levels[32], 0.0);
+        assert_eq!(levels[63], 0.0);
+
+        assert_eq!(waveform.levels(3.0)[63], 0.25);
+
+        // Bars before the start of the audio are silent.
+        let levels = waveform.levels(0.5);
+        assert_eq!(levels[0], 0.0);
+        assert_eq!(levels[63], 0.5);
+    }
+
+    #[test]
+    fn levels_are_capped() {
+        let waveform = Waveform::new(Arc::new(vec![-2.0; 1000]), 1, 1000);
+
+        assert!(waveform.levels(1.0).iter().all(|level| *level == 1.0));
+    }
+
+    #[test]
+    fn frames_end_with_the_audio() {
+        let waveform = waveform();
+
+        let frame = waveform.frame(0).unwrap();
+        assert_eq!(
+            frame.len(),
+            (WAVEFORM_SIZE.0 * WAVEFORM_SIZE.1 * 4) as usize
+        );
+        assert!(waveform.frame(90).is_some());
+        assert!(waveform.frame(91).is_none());
+
+        let stereo = Waveform::new(Arc::new(vec![0.0; 2000]), 2, 1000);
+        assert!(stereo.frame(30).is_some());
+        assert!(stereo.frame(31).is_none());
+    }
+
+    #[test]
+    fn audio_without_a_sample_rate_ends_immediately() {
+        let waveform = Waveform::new(Arc::new(vec![0.5; 1000]), 1, 0);
+
+        assert_eq!(waveform.duration_secs(), 0.0);
+        assert!(waveform.frame(1).is_none());
+    }
+}